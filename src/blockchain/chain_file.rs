@@ -0,0 +1,193 @@
+use super::*;
+use std::io::{Read, Write};
+use thiserror::Error;
+
+// A chain file is a sequence of records, each a little-endian u64 length
+// followed by a bincode encoded `BlockAndPatch`.
+const MAX_RECORD_SIZE: u64 = 1 << 30;
+
+#[derive(Error, Debug)]
+pub enum ChainFileError {
+    #[error("blockchain error happened: {0}")]
+    BlockchainError(#[from] BlockchainError),
+    #[error("io error happened: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("bincode error happened: {0}")]
+    BincodeError(#[from] bincode::Error),
+    #[error("record of {0} bytes is too large")]
+    RecordTooLarge(u64),
+    #[error("chain is at height {expected}, but the file continues from block {found}")]
+    MissingBlocks { expected: u64, found: u64 },
+    #[error("block {0} of the file is different from the one on the chain")]
+    DifferentBlock(u64),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub imported: u64,
+    pub skipped: u64,
+}
+
+pub fn write_record<W: Write>(
+    writer: &mut W,
+    record: &BlockAndPatch,
+) -> Result<(), ChainFileError> {
+    let bytes = bincode::serialize(record)?;
+    writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
+/// Returns `None` at the end of the file
+pub fn read_record<R: Read>(reader: &mut R) -> Result<Option<BlockAndPatch>, ChainFileError> {
+    let mut len = [0u8; 8];
+    let mut read = 0;
+    while read < len.len() {
+        match reader.read(&mut len[read..])? {
+            0 if read == 0 => {
+                return Ok(None);
+            }
+            0 => {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+            n => {
+                read += n;
+            }
+        }
+    }
+    let len = u64::from_le_bytes(len);
+    if len > MAX_RECORD_SIZE {
+        return Err(ChainFileError::RecordTooLarge(len));
+    }
+    let mut bytes = vec![0u8; len as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(Some(bincode::deserialize(&bytes)?))
+}
+
+fn state_changing_contract(tx: &Transaction) -> Option<ContractId> {
+    match &tx.data {
+        TransactionData::CreateContract { .. } => Some(ContractId::new(tx)),
+        TransactionData::UpdateContract { contract_id, .. } => Some(*contract_id),
+        _ => None,
+    }
+}
+
+// Full-states of the contracts right after block `index`. Nodes only retain
+// a few rollbacks of each contract, the contracts whose states at that block
+// are not retained anymore, or are outdated, are left out.
+fn states_after_block<K: KvStore>(
+    chain: &KvStoreChain<K>,
+    contracts: &[ContractId],
+    index: u64,
+) -> Result<ZkBlockchainPatch, ChainFileError> {
+    let outdated_contracts = chain.get_outdated_contracts()?;
+    // Contract heights are increased by each transaction changing the state
+    let mut later_updates = HashMap::<ContractId, u64>::new();
+    for i in index + 1..chain.get_height()? {
+        for cid in chain
+            .get_block(i)?
+            .body
+            .iter()
+            .filter_map(state_changing_contract)
+        {
+            *later_updates.entry(cid).or_default() += 1;
+        }
+    }
+    let mut patches = HashMap::new();
+    for cid in contracts.iter() {
+        if outdated_contracts.contains(cid) {
+            continue;
+        }
+        let height = chain.get_contract_account(*cid)?.height
+            - later_updates.get(cid).copied().unwrap_or_default();
+        match chain.get_full_state_at(*cid, height) {
+            Ok(state) => {
+                patches.insert(*cid, zk::ZkStatePatch::Full(state));
+            }
+            Err(BlockchainError::StateManagerError(zk::StateManagerError::HeightNotAvailable(
+                _,
+            ))) => {}
+            Err(e) => {
+                return Err(e.into());
+            }
+        }
+    }
+    Ok(ZkBlockchainPatch { patches })
+}
+
+/// Writes the blocks in `from..to` (capped at the current height) to `writer`.
+/// Nodes only keep the recent contract states, so the last exported record
+/// carries the full-states of the contracts updated by the exported blocks,
+/// as they were after that block, and the patches of the other records are
+/// empty.
+pub fn export_chain<K: KvStore, W: Write, F: FnMut(u64)>(
+    chain: &KvStoreChain<K>,
+    from: u64,
+    to: u64,
+    writer: &mut W,
+    mut progress: F,
+) -> Result<u64, ChainFileError> {
+    let to = std::cmp::min(to, chain.get_height()?);
+    let mut updated_contracts = Vec::<ContractId>::new();
+    for index in from..to {
+        let block = chain.get_block(index)?;
+        for cid in block.body.iter().filter_map(state_changing_contract) {
+            if !updated_contracts.contains(&cid) {
+                updated_contracts.push(cid);
+            }
+        }
+        let patch = if index + 1 == to {
+            states_after_block(chain, &updated_contracts, index)?
+        } else {
+            ZkBlockchainPatch {
+                patches: HashMap::new(),
+            }
+        };
+        write_record(writer, &BlockAndPatch { block, patch })?;
+        progress(index);
+    }
+    Ok(to.saturating_sub(from))
+}
+
+/// Extends the chain with the blocks read from `reader`. Blocks the chain
+/// already has are skipped, so an interrupted import can be resumed with the
+/// same file. Contract states are updated whenever a record carries the
+/// states of all the outdated contracts.
+pub fn import_chain<B: Blockchain, R: Read, F: FnMut(u64)>(
+    chain: &mut B,
+    reader: &mut R,
+    mut progress: F,
+) -> Result<ImportSummary, ChainFileError> {
+    let mut summary = ImportSummary::default();
+    while let Some(record) = read_record(reader)? {
+        let number = record.block.header.number;
+        let height = chain.get_height()?;
+        if number < height {
+            if chain.get_block(number)?.header.hash() != record.block.header.hash() {
+                return Err(ChainFileError::DifferentBlock(number));
+            }
+            summary.skipped += 1;
+        } else if number == height {
+            chain.extend(number, &[record.block])?;
+            summary.imported += 1;
+        } else {
+            return Err(ChainFileError::MissingBlocks {
+                expected: height,
+                found: number,
+            });
+        }
+
+        if number + 1 == chain.get_height()? {
+            let outdated_contracts = chain.get_outdated_contracts()?;
+            if !outdated_contracts.is_empty()
+                && outdated_contracts
+                    .iter()
+                    .all(|cid| record.patch.patches.contains_key(cid))
+            {
+                chain.update_states(&record.patch)?;
+            }
+        }
+        progress(number);
+    }
+    Ok(summary)
+}
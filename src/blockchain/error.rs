@@ -1,5 +1,7 @@
+use crate::core::{hash::Hash, Hasher};
 use crate::db::KvStoreError;
 use crate::zk::{StateManagerError, ZkError};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -76,14 +78,207 @@ pub enum BlockchainError {
     InsufficientMpnUpdates,
     #[error("invalid zero-transaction")]
     InvalidMpnTransaction,
+    #[error("no free mpn account left")]
+    NoFreeMpnAccount,
     #[error("cannot send funds to yourself")]
     SelfPaymentNotAllowed,
     #[error("executor cannot execute his own payments")]
     CannotExecuteOwnPayments,
     #[error("contract contains invalid state-model")]
     InvalidStateModel,
+    #[error("contract contains a PLONK key of an untrusted setup")]
+    UntrustedPlonkSetup,
     #[error("height limit reached! if you are on a testnet, make sure you update your software")]
     TestnetHeightLimitReached,
     #[error("address not allowed to mine")]
     AddressNotAllowedToMine,
+    #[error("upgrade {0} is not supported by this version")]
+    UnsupportedUpgrade(String),
+    #[error("block #{height} is invalid: {source}")]
+    InvalidBlock {
+        height: u64,
+        source: Box<BlockchainError>,
+    },
+    #[error("transaction #{index} ({}) is invalid: {source}", hex::encode(.hash))]
+    InvalidTransaction {
+        index: usize,
+        hash: <Hasher as Hash>::Output,
+        source: Box<BlockchainError>,
+    },
+    #[error("contract update #{index} is invalid: {source}")]
+    InvalidContractUpdate {
+        index: usize,
+        source: Box<BlockchainError>,
+    },
+}
+
+/// Identifies the root cause of a `BlockchainError`, without its payload
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockchainErrorCode {
+    KvStoreError,
+    DifferentGenesis,
+    SignatureError,
+    BalanceInsufficient,
+    ContractBalanceInsufficient,
+    Inconsistency,
+    BlockNotFound,
+    ExtendFromGenesis,
+    ExtendFromFuture,
+    InvalidBlockNumber,
+    InvalidParentHash,
+    InvalidMerkleRoot,
+    InvalidTransactionNonce,
+    InvalidTimestamp,
+    DifficultyTargetUnmet,
+    DifficultyTargetWrong,
+    MinerRewardNotFound,
+    IllegalTreasuryAccess,
+    InvalidMinerReward,
+    ContractNotFound,
+    ContractFunctionNotFound,
+    IncorrectZkProof,
+    FullStateNotFound,
+    FullStateNotValid,
+    StatesOutdated,
+    StatesUnavailable,
+    BlockTooBig,
+    StateDeltaTooBig,
+    CompressedStateNotFound,
+    DeltasInvalid,
+    NoBlocksToRollback,
+    ZkError,
+    StateManagerError,
+    InvalidContractPaymentSignature,
+    InsufficientMpnUpdates,
+    InvalidMpnTransaction,
+    NoFreeMpnAccount,
+    SelfPaymentNotAllowed,
+    CannotExecuteOwnPayments,
+    InvalidStateModel,
+    UntrustedPlonkSetup,
+    TestnetHeightLimitReached,
+    AddressNotAllowedToMine,
+    UnsupportedUpgrade,
+}
+
+/// Serializable form of a `BlockchainError`, for API responses
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BlockchainErrorInfo {
+    pub code: BlockchainErrorCode,
+    pub message: String,
+    pub block_height: Option<u64>,
+    pub transaction_index: Option<usize>,
+    pub contract_update_index: Option<usize>,
+}
+
+impl From<&BlockchainError> for BlockchainErrorInfo {
+    fn from(err: &BlockchainError) -> Self {
+        Self {
+            code: err.code(),
+            message: err.to_string(),
+            block_height: err.block_height(),
+            transaction_index: err.transaction_index(),
+            contract_update_index: err.contract_update_index(),
+        }
+    }
+}
+
+impl BlockchainError {
+    /// The underlying error, stripped of block/transaction/update context
+    pub fn root_cause(&self) -> &BlockchainError {
+        match self {
+            BlockchainError::InvalidBlock { source, .. }
+            | BlockchainError::InvalidTransaction { source, .. }
+            | BlockchainError::InvalidContractUpdate { source, .. } => source.root_cause(),
+            _ => self,
+        }
+    }
+    pub fn code(&self) -> BlockchainErrorCode {
+        match self {
+            BlockchainError::KvStoreError(_) => BlockchainErrorCode::KvStoreError,
+            BlockchainError::DifferentGenesis => BlockchainErrorCode::DifferentGenesis,
+            BlockchainError::SignatureError => BlockchainErrorCode::SignatureError,
+            BlockchainError::BalanceInsufficient => BlockchainErrorCode::BalanceInsufficient,
+            BlockchainError::ContractBalanceInsufficient => {
+                BlockchainErrorCode::ContractBalanceInsufficient
+            }
+            BlockchainError::Inconsistency => BlockchainErrorCode::Inconsistency,
+            BlockchainError::BlockNotFound => BlockchainErrorCode::BlockNotFound,
+            BlockchainError::ExtendFromGenesis => BlockchainErrorCode::ExtendFromGenesis,
+            BlockchainError::ExtendFromFuture => BlockchainErrorCode::ExtendFromFuture,
+            BlockchainError::InvalidBlockNumber => BlockchainErrorCode::InvalidBlockNumber,
+            BlockchainError::InvalidParentHash => BlockchainErrorCode::InvalidParentHash,
+            BlockchainError::InvalidMerkleRoot => BlockchainErrorCode::InvalidMerkleRoot,
+            BlockchainError::InvalidTransactionNonce => {
+                BlockchainErrorCode::InvalidTransactionNonce
+            }
+            BlockchainError::InvalidTimestamp => BlockchainErrorCode::InvalidTimestamp,
+            BlockchainError::DifficultyTargetUnmet => BlockchainErrorCode::DifficultyTargetUnmet,
+            BlockchainError::DifficultyTargetWrong => BlockchainErrorCode::DifficultyTargetWrong,
+            BlockchainError::MinerRewardNotFound => BlockchainErrorCode::MinerRewardNotFound,
+            BlockchainError::IllegalTreasuryAccess => BlockchainErrorCode::IllegalTreasuryAccess,
+            BlockchainError::InvalidMinerReward => BlockchainErrorCode::InvalidMinerReward,
+            BlockchainError::ContractNotFound => BlockchainErrorCode::ContractNotFound,
+            BlockchainError::ContractFunctionNotFound => {
+                BlockchainErrorCode::ContractFunctionNotFound
+            }
+            BlockchainError::IncorrectZkProof => BlockchainErrorCode::IncorrectZkProof,
+            BlockchainError::FullStateNotFound => BlockchainErrorCode::FullStateNotFound,
+            BlockchainError::FullStateNotValid => BlockchainErrorCode::FullStateNotValid,
+            BlockchainError::StatesOutdated => BlockchainErrorCode::StatesOutdated,
+            BlockchainError::StatesUnavailable => BlockchainErrorCode::StatesUnavailable,
+            BlockchainError::BlockTooBig => BlockchainErrorCode::BlockTooBig,
+            BlockchainError::StateDeltaTooBig => BlockchainErrorCode::StateDeltaTooBig,
+            BlockchainError::CompressedStateNotFound => {
+                BlockchainErrorCode::CompressedStateNotFound
+            }
+            BlockchainError::DeltasInvalid => BlockchainErrorCode::DeltasInvalid,
+            BlockchainError::NoBlocksToRollback => BlockchainErrorCode::NoBlocksToRollback,
+            BlockchainError::ZkError(_) => BlockchainErrorCode::ZkError,
+            BlockchainError::StateManagerError(_) => BlockchainErrorCode::StateManagerError,
+            BlockchainError::InvalidContractPaymentSignature => {
+                BlockchainErrorCode::InvalidContractPaymentSignature
+            }
+            BlockchainError::InsufficientMpnUpdates => BlockchainErrorCode::InsufficientMpnUpdates,
+            BlockchainError::InvalidMpnTransaction => BlockchainErrorCode::InvalidMpnTransaction,
+            BlockchainError::NoFreeMpnAccount => BlockchainErrorCode::NoFreeMpnAccount,
+            BlockchainError::SelfPaymentNotAllowed => BlockchainErrorCode::SelfPaymentNotAllowed,
+            BlockchainError::CannotExecuteOwnPayments => {
+                BlockchainErrorCode::CannotExecuteOwnPayments
+            }
+            BlockchainError::InvalidStateModel => BlockchainErrorCode::InvalidStateModel,
+            BlockchainError::UntrustedPlonkSetup => BlockchainErrorCode::UntrustedPlonkSetup,
+            BlockchainError::TestnetHeightLimitReached => {
+                BlockchainErrorCode::TestnetHeightLimitReached
+            }
+            BlockchainError::AddressNotAllowedToMine => {
+                BlockchainErrorCode::AddressNotAllowedToMine
+            }
+            BlockchainError::UnsupportedUpgrade(_) => BlockchainErrorCode::UnsupportedUpgrade,
+            BlockchainError::InvalidBlock { .. }
+            | BlockchainError::InvalidTransaction { .. }
+            | BlockchainError::InvalidContractUpdate { .. } => self.root_cause().code(),
+        }
+    }
+    pub fn block_height(&self) -> Option<u64> {
+        match self {
+            BlockchainError::InvalidBlock { height, .. } => Some(*height),
+            _ => None,
+        }
+    }
+    pub fn transaction_index(&self) -> Option<usize> {
+        match self {
+            BlockchainError::InvalidBlock { source, .. } => source.transaction_index(),
+            BlockchainError::InvalidTransaction { index, .. } => Some(*index),
+            _ => None,
+        }
+    }
+    pub fn contract_update_index(&self) -> Option<usize> {
+        match self {
+            BlockchainError::InvalidBlock { source, .. }
+            | BlockchainError::InvalidTransaction { source, .. } => source.contract_update_index(),
+            BlockchainError::InvalidContractUpdate { index, .. } => Some(*index),
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,124 @@
+use super::*;
+
+/// Model of the payments of a `ContractUpdate::Payment`, the compressed state
+/// of which is the aux-data of the update
+pub fn contract_payments_state_model(log4_payment_capacity: u8) -> zk::ZkStateModel {
+    zk::ZkStateModel::List {
+        item_type: Box::new(zk::CONTRACT_PAYMENT_STATE_MODEL.clone()),
+        log4_size: log4_payment_capacity,
+    }
+}
+
+/// Compresses the payments into the aux-data of a `ContractUpdate::Payment`
+pub fn compress_contract_payments(
+    payments: &[ContractPayment],
+    log4_payment_capacity: u8,
+) -> Result<zk::ZkCompressedState, BlockchainError> {
+    let mut state_builder =
+        zk::ZkStateBuilder::<ZkHasher>::new(contract_payments_state_model(log4_payment_capacity));
+    for (i, contract_payment) in payments.iter().enumerate() {
+        let pk = contract_payment.zk_address.0.decompress();
+        state_builder.batch_set(&zk::ZkDeltaPairs(
+            [
+                (
+                    zk::ZkDataLocator(vec![i as u32, 0]),
+                    Some(zk::ZkScalar::from(contract_payment.amount)),
+                ),
+                (
+                    zk::ZkDataLocator(vec![i as u32, 1]),
+                    Some(zk::ZkScalar::from(match contract_payment.direction {
+                        PaymentDirection::Deposit(_) => 0,
+                        PaymentDirection::Withdraw(_) => 1,
+                    })),
+                ),
+                (
+                    zk::ZkDataLocator(vec![i as u32, 2]),
+                    Some(zk::ZkScalar::from(pk.0)),
+                ),
+                (
+                    zk::ZkDataLocator(vec![i as u32, 3]),
+                    Some(zk::ZkScalar::from(pk.1)),
+                ),
+            ]
+            .into(),
+        ))?;
+    }
+    Ok(state_builder.compress()?)
+}
+
+/// The MPN account after executing the payment on it. Deposits go to empty
+/// accounts or accounts of the same address, withdrawals need the account to
+/// be owned by the address and to cover both the amount and the executor fee.
+/// `None` when the payment can't be executed on the account.
+pub fn execute_mpn_payment(
+    account: &zk::MpnAccount,
+    payment: &ContractPayment,
+) -> Option<zk::MpnAccount> {
+    let owned = account.address.compress() == payment.zk_address.0;
+    match payment.direction {
+        PaymentDirection::Deposit(_) => {
+            if !owned && account.address != Default::default() {
+                return None;
+            }
+            Some(zk::MpnAccount {
+                nonce: account.nonce,
+                address: payment.zk_address.0.decompress(),
+                balance: account.balance + payment.amount,
+            })
+        }
+        PaymentDirection::Withdraw(_) => {
+            if !owned || account.balance < payment.amount + payment.fee {
+                return None;
+            }
+            Some(zk::MpnAccount {
+                nonce: account.nonce + 1,
+                address: account.address,
+                balance: account.balance - payment.amount - payment.fee,
+            })
+        }
+    }
+}
+
+/// A payment of a batch, along with the account it is executed on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MpnPaymentWitness {
+    pub payment: MpnPayment,
+    /// The account before executing the payment
+    pub account: zk::MpnAccount,
+    /// Merkle path of the account before executing the payment, from leaf to
+    /// root
+    pub proof: Vec<[zk::ZkScalar; 3]>,
+}
+
+/// What a prover needs in order to prove the execution of a batch of MPN
+/// payments, and an executor to submit it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MpnPaymentBatch {
+    pub circuit_id: u32,
+    /// Height of the MPN contract the batch is executed on
+    pub height: u64,
+    pub prev_state: zk::ZkCompressedState,
+    pub aux_data: zk::ZkCompressedState,
+    pub next_state: zk::ZkCompressedState,
+    /// Changes of the MPN state, to be provided along with the update
+    pub state_delta: zk::ZkDeltaPairs,
+    pub payments: Vec<MpnPaymentWitness>,
+}
+
+impl MpnPaymentBatch {
+    pub fn contract_payments(&self) -> Vec<ContractPayment> {
+        self.payments
+            .iter()
+            .map(|w| w.payment.payment.clone())
+            .collect()
+    }
+
+    pub fn contract_update(&self, proof: zk::ZkProof) -> ContractUpdate {
+        ContractUpdate::Payment {
+            circuit_id: self.circuit_id,
+            payments: self.contract_payments(),
+            next_state: self.next_state,
+            proof,
+        }
+    }
+}
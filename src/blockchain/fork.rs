@@ -0,0 +1,44 @@
+use super::BlockchainError;
+use serde::{Deserialize, Serialize};
+
+/// Names of the protocol upgrades this version of the software implements.
+/// Rule changes should be guarded by `ForkSchedule::is_active` queries on
+/// one of these names.
+pub const SUPPORTED_UPGRADES: &[&str] = &[];
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledUpgrade {
+    pub name: String,
+    pub activation_height: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForkSchedule {
+    pub upgrades: Vec<ScheduledUpgrade>,
+}
+
+impl ForkSchedule {
+    pub fn is_active(&self, name: &str, height: u64) -> bool {
+        self.upgrades
+            .iter()
+            .any(|u| u.name == name && height >= u.activation_height)
+    }
+
+    pub fn active_upgrades(&self, height: u64) -> impl Iterator<Item = &ScheduledUpgrade> {
+        self.upgrades
+            .iter()
+            .filter(move |u| height >= u.activation_height)
+    }
+
+    /// A node can't follow the chain past the activation height of an
+    /// upgrade it doesn't implement, the software needs to be updated.
+    pub fn check_supported(&self, height: u64) -> Result<(), BlockchainError> {
+        if let Some(upgrade) = self
+            .active_upgrades(height)
+            .find(|u| !SUPPORTED_UPGRADES.contains(&u.name.as_str()))
+        {
+            return Err(BlockchainError::UnsupportedUpgrade(upgrade.name.clone()));
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,15 @@
 mod error;
 pub use error::*;
+mod fork;
+pub use fork::*;
+mod chain_file;
+pub use chain_file::*;
+mod verify;
+pub use verify::*;
+mod executor;
+pub use executor::*;
+mod proofs;
+use proofs::{ContractCircuit, ProofVerifier};
 
 use crate::core::{
     hash::Hash, Account, Address, Block, ContractAccount, ContractId, ContractPayment,
@@ -7,7 +17,7 @@ use crate::core::{
     Transaction, TransactionAndDelta, TransactionData, ZkHasher,
 };
 use crate::crypto::jubjub;
-use crate::db::{keys, KvStore, RamMirrorKvStore, WriteOp};
+use crate::db::{keys, migrations, BackupJob, CacheStats, KvStore, RamMirrorKvStore, WriteOp};
 use crate::utils;
 use crate::wallet::Wallet;
 use crate::zk;
@@ -19,7 +29,7 @@ use std::collections::{HashMap, HashSet};
 
 use crate::consensus::pow::Difficulty;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BlockchainConfig {
     pub limited_miners: Option<HashSet<Address>>,
     pub genesis: BlockAndPatch,
@@ -29,7 +39,7 @@ pub struct BlockchainConfig {
     pub max_delta_count: usize,
     pub block_time: usize,
     pub difficulty_calc_interval: u64,
-    pub pow_base_key: &'static [u8],
+    pub pow_base_key: Vec<u8>,
     pub pow_key_change_delay: u64,
     pub pow_key_change_interval: u64,
     pub median_timestamp_count: u64,
@@ -38,6 +48,13 @@ pub struct BlockchainConfig {
     pub mpn_num_contract_payments: usize,
     pub minimum_pow_difficulty: Difficulty,
     pub testnet_height_limit: Option<u64>,
+    pub fork_schedule: ForkSchedule,
+    /// Blocks are generated on demand, always at the minimum difficulty
+    pub regtest: bool,
+    /// Compressed `[x]₂` of the ceremony PLONK circuits are set up with.
+    /// Contracts with PLONK keys of any other SRS are rejected, and when
+    /// unset, no PLONK keys are accepted.
+    pub plonk_srs_x_g2: Option<Vec<u8>>,
 }
 
 #[derive(Debug, Clone)]
@@ -52,17 +69,18 @@ pub struct ZkBlockchainPatch {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ZkCompressedStateChange {
-    prev_state: zk::ZkCompressedState,
-    state: zk::ZkCompressedState,
-    prev_height: u64,
+    pub prev_state: zk::ZkCompressedState,
+    pub state: zk::ZkCompressedState,
+    pub prev_height: u64,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BlockAndPatch {
     pub block: Block,
     pub patch: ZkBlockchainPatch,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum TxSideEffect {
     StateChange {
         contract_id: ContractId,
@@ -71,6 +89,26 @@ pub enum TxSideEffect {
     Nothing,
 }
 
+impl TxSideEffect {
+    pub fn state_size_delta(&self) -> isize {
+        if let TxSideEffect::StateChange { state_change, .. } = self {
+            state_change.state.size() as isize - state_change.prev_state.size() as isize
+        } else {
+            0
+        }
+    }
+}
+
+// Outcome of applying a transaction on top of the current chain without
+// persisting anything.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TransactionSimulation {
+    pub side_effect: TxSideEffect,
+    pub state_size_delta: isize,
+    pub fee: Money,
+    pub balances: HashMap<Address, Money>,
+}
+
 pub trait Blockchain {
     fn config(&self) -> &BlockchainConfig;
 
@@ -90,16 +128,37 @@ pub trait Blockchain {
     fn validate_mpn_transaction(&self, tx: &zk::MpnTransaction) -> Result<(), BlockchainError>;
     fn validate_mpn_payment(&self, tx: &MpnPayment) -> Result<(), BlockchainError>;
     fn validate_transaction(&self, tx_delta: &TransactionAndDelta) -> Result<(), BlockchainError>;
+    fn simulate_transaction(
+        &self,
+        tx_delta: &TransactionAndDelta,
+    ) -> Result<TransactionSimulation, BlockchainError>;
 
     fn db_checksum(&self) -> Result<String, BlockchainError>;
+    fn db_cache_stats(&self) -> Option<CacheStats>;
+    /// Snapshots the database, the returned job writes the snapshot as a
+    /// backup without holding the blockchain
+    fn db_backup_job(&self) -> Result<BackupJob, BlockchainError>;
 
     fn get_account(&self, addr: Address) -> Result<Account, BlockchainError>;
     fn get_mpn_account(&self, index: u32) -> Result<zk::MpnAccount, BlockchainError>;
+    /// Index of the MPN account owned by the address, or of a free account
+    /// when the address owns none
+    fn get_mpn_account_index(&self, address: &jubjub::PublicKey) -> Result<u32, BlockchainError>;
     fn get_mpn_accounts(
         &self,
         page: usize,
         page_size: usize,
     ) -> Result<Vec<(u32, zk::MpnAccount)>, BlockchainError>;
+    /// Executes the MPN payments that can be executed, in the given order, up
+    /// to the capacity of the payment circuit. Payments of the executor are
+    /// skipped, as well as payments that are invalid or that can't be
+    /// executed on their account.
+    fn execute_mpn_payments(
+        &self,
+        executor: &Address,
+        circuit_id: u32,
+        payments: &[MpnPayment],
+    ) -> Result<MpnPaymentBatch, BlockchainError>;
 
     fn get_contract_account(
         &self,
@@ -110,6 +169,34 @@ pub trait Blockchain {
         contract_id: ContractId,
         locator: zk::ZkDataLocator,
     ) -> Result<zk::ZkScalar, BlockchainError>;
+    /// Reads the state of a contract as it was at an earlier height of the
+    /// contract, while the rollback deltas to that height are retained
+    fn read_state_at(
+        &self,
+        contract_id: ContractId,
+        locator: zk::ZkDataLocator,
+        height: u64,
+    ) -> Result<zk::ZkScalar, BlockchainError>;
+    fn get_full_state_at(
+        &self,
+        contract_id: ContractId,
+        height: u64,
+    ) -> Result<zk::ZkState, BlockchainError>;
+    /// A page of the values of the full-state of a contract at an earlier
+    /// height, ordered by locator
+    fn get_full_state_page_at(
+        &self,
+        contract_id: ContractId,
+        height: u64,
+        page: usize,
+        page_size: usize,
+    ) -> Result<Vec<(zk::ZkDataLocator, zk::ZkScalar)>, BlockchainError>;
+    /// Proves the data at the locator against the current state of a contract
+    fn prove_state(
+        &self,
+        contract_id: ContractId,
+        locator: zk::ZkDataLocator,
+    ) -> Result<zk::ZkStateProof, BlockchainError>;
     fn next_reward(&self) -> Result<Money, BlockchainError>;
     fn will_extend(
         &self,
@@ -148,16 +235,49 @@ pub trait Blockchain {
     fn update_states(&mut self, patch: &ZkBlockchainPatch) -> Result<(), BlockchainError>;
 }
 
+// Accounts whose balances may change when the transaction is applied
+fn touched_addresses(tx: &Transaction) -> Vec<Address> {
+    let mut addrs = vec![tx.src.clone(), Address::Treasury];
+    match &tx.data {
+        TransactionData::RegularSend { dst, .. } => {
+            addrs.push(dst.clone());
+        }
+        TransactionData::UpdateContract { updates, .. } => {
+            for update in updates.iter() {
+                if let ContractUpdate::Payment { payments, .. } = update {
+                    for payment in payments.iter() {
+                        addrs.push(Address::PublicKey(payment.address.clone()));
+                    }
+                }
+            }
+        }
+        TransactionData::CreateContract { .. } => {}
+    }
+    let mut uniq = Vec::new();
+    for addr in addrs {
+        if !uniq.contains(&addr) {
+            uniq.push(addr);
+        }
+    }
+    uniq
+}
+
 pub struct KvStoreChain<K: KvStore> {
     config: BlockchainConfig,
     database: K,
+    proofs: ProofVerifier,
 }
 
 impl<K: KvStore> KvStoreChain<K> {
-    pub fn new(database: K, config: BlockchainConfig) -> Result<KvStoreChain<K>, BlockchainError> {
+    pub fn new(
+        mut database: K,
+        config: BlockchainConfig,
+    ) -> Result<KvStoreChain<K>, BlockchainError> {
+        migrations::migrate(&mut database)?;
         let mut chain = KvStoreChain::<K> {
             database,
             config: config.clone(),
+            proofs: Default::default(),
         };
         if chain.get_height()? == 0 {
             chain.apply_block(&config.genesis.block, true)?;
@@ -174,6 +294,7 @@ impl<K: KvStore> KvStoreChain<K> {
         KvStoreChain {
             database: self.database.mirror(),
             config: self.config.clone(),
+            proofs: self.proofs.clone(),
         }
     }
 
@@ -186,18 +307,32 @@ impl<K: KvStore> KvStoreChain<K> {
         Ok((mirror.database.to_ops(), result))
     }
 
+    // Difficulty of the interval following the one from `prev_pow` to
+    // `last_pow`. Regtest blocks are generated way faster than the block
+    // time, so the difficulty is kept at its minimum.
+    fn recalculated_difficulty(
+        &self,
+        last_pow: &ProofOfWork,
+        prev_pow: &ProofOfWork,
+    ) -> Difficulty {
+        if self.config.regtest {
+            return self.config.minimum_pow_difficulty;
+        }
+        utils::calc_pow_difficulty(
+            self.config.difficulty_calc_interval,
+            self.config.block_time,
+            self.config.minimum_pow_difficulty,
+            last_pow,
+            prev_pow,
+        )
+    }
+
     fn next_difficulty(&self) -> Result<Difficulty, BlockchainError> {
         let height = self.get_height()?;
         let last_block = self.get_tip()?;
         if height % self.config.difficulty_calc_interval == 0 {
             let prev_block = self.get_header(height - self.config.difficulty_calc_interval)?;
-            Ok(utils::calc_pow_difficulty(
-                self.config.difficulty_calc_interval,
-                self.config.block_time,
-                self.config.minimum_pow_difficulty,
-                &last_block.proof_of_work,
-                &prev_block.proof_of_work,
-            ))
+            Ok(self.recalculated_difficulty(&last_block.proof_of_work, &prev_block.proof_of_work))
         } else {
             Ok(last_block.proof_of_work.target)
         }
@@ -288,6 +423,10 @@ impl<K: KvStore> KvStoreChain<K> {
             if src.balance < tx.fee + tx.amount {
                 return Err(BlockchainError::InvalidMpnTransaction);
             }
+            // The destination account may not be owned by someone else
+            if dst.address != Default::default() && dst.address.compress() != tx.dst_pub_key.0 {
+                return Err(BlockchainError::InvalidMpnTransaction);
+            }
             let mut size_diff = 0;
             zk::KvStoreStateManager::<ZkHasher>::set_mpn_account(
                 &mut chain.database,
@@ -317,12 +456,130 @@ impl<K: KvStore> KvStoreChain<K> {
         Ok(())
     }
 
+    fn apply_contract_update(
+        &mut self,
+        tx: &Transaction,
+        contract_id: &ContractId,
+        contract: &zk::ZkContract,
+        prev_height: u64,
+        update_index: usize,
+        update: &ContractUpdate,
+    ) -> Result<Money, BlockchainError> {
+        let (ops, executor_fee) = self.isolated(|chain| {
+            let mut executor_fee = Money(0);
+            let (circuit, aux_data, next_state, proof) = match update {
+                ContractUpdate::Payment {
+                    circuit_id,
+                    payments,
+                    next_state,
+                    proof,
+                } => {
+                    let payment_func = contract
+                        .payment_functions
+                        .get(*circuit_id as usize)
+                        .ok_or(BlockchainError::ContractFunctionNotFound)?;
+                    let circuit = (
+                        ContractCircuit::Payment(*circuit_id),
+                        &payment_func.verifier_key,
+                    );
+                    for contract_payment in payments.iter() {
+                        if Address::PublicKey(contract_payment.address.clone()) == tx.src {
+                            return Err(BlockchainError::CannotExecuteOwnPayments);
+                        }
+                        executor_fee += contract_payment.fee;
+                        chain.apply_contract_payment(contract_payment)?;
+                    }
+                    let aux_data =
+                        compress_contract_payments(payments, payment_func.log4_payment_capacity)?;
+                    (circuit, aux_data, next_state, proof)
+                }
+                ContractUpdate::FunctionCall {
+                    function_id,
+                    next_state,
+                    proof,
+                    fee,
+                } => {
+                    executor_fee += *fee;
+
+                    let mut cont_account = chain.get_contract_account(*contract_id)?;
+                    cont_account.balance -= *fee;
+                    chain.database.update(&[WriteOp::Put(
+                        keys::contract_account(contract_id),
+                        cont_account.into(),
+                    )])?;
+
+                    let circuit = (
+                        ContractCircuit::Function(*function_id),
+                        contract
+                            .functions
+                            .get(*function_id as usize)
+                            .ok_or(BlockchainError::ContractFunctionNotFound)?,
+                    );
+                    let mut state_builder =
+                        zk::ZkStateBuilder::<ZkHasher>::new(zk::ZkStateModel::Scalar);
+                    state_builder.batch_set(&zk::ZkDeltaPairs(
+                        [(zk::ZkDataLocator(vec![]), Some(zk::ZkScalar::from(*fee)))].into(),
+                    ))?;
+                    let aux_data = state_builder.compress()?;
+                    (circuit, aux_data, next_state, proof)
+                }
+            };
+
+            let mut cont_account = chain.get_contract_account(*contract_id)?;
+            if !chain.proofs.check(
+                tx,
+                update_index,
+                (*contract_id, circuit.0),
+                circuit.1,
+                prev_height,
+                &cont_account.compressed_state,
+                &aux_data,
+                next_state,
+                proof,
+            ) {
+                return Err(BlockchainError::IncorrectZkProof);
+            }
+            cont_account.compressed_state = *next_state;
+            chain.database.update(&[WriteOp::Put(
+                keys::contract_account(contract_id),
+                cont_account.into(),
+            )])?;
+
+            Ok(executor_fee)
+        })?;
+        self.database.update(&ops)?;
+        Ok(executor_fee)
+    }
+
+    // The SRS of a PLONK key comes with the key, so a deployer could
+    // otherwise pick one whose trapdoor they know
+    fn check_plonk_setup(&self, contract: &zk::ZkContract) -> Result<(), BlockchainError> {
+        let keys = contract
+            .payment_functions
+            .iter()
+            .map(|f| &f.verifier_key)
+            .chain(contract.functions.iter());
+        for key in keys {
+            if let zk::ZkVerifierKey::Plonk(vk) = key {
+                if self.config.plonk_srs_x_g2.as_ref() != Some(&vk.x_g2) {
+                    return Err(BlockchainError::UntrustedPlonkSetup);
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn apply_tx(
         &mut self,
         tx: &Transaction,
         allow_treasury: bool,
     ) -> Result<TxSideEffect, BlockchainError> {
         let (ops, side_effect) = self.isolated(|chain| {
+            chain
+                .config
+                .fork_schedule
+                .check_supported(chain.get_height()?)?;
+
             let mut side_effect = TxSideEffect::Nothing;
 
             let mut acc_src = chain.get_account(tx.src.clone())?;
@@ -363,11 +620,17 @@ impl<K: KvStore> KvStoreChain<K> {
                     if !contract.state_model.is_valid::<ZkHasher>() {
                         return Err(BlockchainError::InvalidStateModel);
                     }
+                    chain.check_plonk_setup(contract)?;
                     let contract_id = ContractId::new(tx);
                     chain.database.update(&[WriteOp::Put(
                         keys::contract(&contract_id),
                         contract.clone().into(),
                     )])?;
+                    // Contracts holding MPN accounts are indexed from the start
+                    zk::KvStoreStateManager::<ZkHasher>::index_mpn_accounts(
+                        &mut chain.database,
+                        contract_id,
+                    )?;
                     let compressed_empty =
                         zk::ZkCompressedState::empty::<ZkHasher>(contract.state_model.clone());
                     chain.database.update(&[WriteOp::Put(
@@ -409,111 +672,20 @@ impl<K: KvStore> KvStoreChain<K> {
                         cont_account.into(),
                     )])?;
 
-                    for update in updates {
-                        let (circuit, aux_data, next_state, proof) = match update {
-                            ContractUpdate::Payment {
-                                circuit_id,
-                                payments,
-                                next_state,
-                                proof,
-                            } => {
-                                let payment_func = contract
-                                    .payment_functions
-                                    .get(*circuit_id as usize)
-                                    .ok_or(BlockchainError::ContractFunctionNotFound)?;
-                                let circuit = &payment_func.verifier_key;
-                                let state_model = zk::ZkStateModel::List {
-                                    item_type: Box::new(zk::CONTRACT_PAYMENT_STATE_MODEL.clone()),
-                                    log4_size: payment_func.log4_payment_capacity,
-                                };
-                                let mut state_builder =
-                                    zk::ZkStateBuilder::<ZkHasher>::new(state_model);
-                                for (i, contract_payment) in payments.iter().enumerate() {
-                                    if Address::PublicKey(contract_payment.address.clone())
-                                        == tx.src
-                                    {
-                                        return Err(BlockchainError::CannotExecuteOwnPayments);
-                                    }
-                                    executor_fee += contract_payment.fee;
-                                    let pk = contract_payment.zk_address.0.decompress();
-                                    state_builder.batch_set(&zk::ZkDeltaPairs(
-                                        [
-                                            (
-                                                zk::ZkDataLocator(vec![i as u32, 0]),
-                                                Some(zk::ZkScalar::from(contract_payment.amount)),
-                                            ),
-                                            (
-                                                zk::ZkDataLocator(vec![i as u32, 1]),
-                                                Some(zk::ZkScalar::from(
-                                                    match contract_payment.direction {
-                                                        PaymentDirection::Deposit(_) => 0,
-                                                        PaymentDirection::Withdraw(_) => 1,
-                                                    },
-                                                )),
-                                            ),
-                                            (
-                                                zk::ZkDataLocator(vec![i as u32, 2]),
-                                                Some(zk::ZkScalar::from(pk.0)),
-                                            ),
-                                            (
-                                                zk::ZkDataLocator(vec![i as u32, 3]),
-                                                Some(zk::ZkScalar::from(pk.1)),
-                                            ),
-                                        ]
-                                        .into(),
-                                    ))?;
-
-                                    chain.apply_contract_payment(contract_payment)?;
-                                }
-                                let aux_data = state_builder.compress()?;
-                                (circuit, aux_data, next_state, proof)
-                            }
-                            ContractUpdate::FunctionCall {
-                                function_id,
-                                next_state,
-                                proof,
-                                fee,
-                            } => {
-                                executor_fee += *fee;
-
-                                let mut cont_account = chain.get_contract_account(*contract_id)?;
-                                cont_account.balance -= *fee;
-                                chain.database.update(&[WriteOp::Put(
-                                    keys::contract_account(contract_id),
-                                    cont_account.into(),
-                                )])?;
-
-                                let circuit = contract
-                                    .functions
-                                    .get(*function_id as usize)
-                                    .ok_or(BlockchainError::ContractFunctionNotFound)?;
-                                let mut state_builder =
-                                    zk::ZkStateBuilder::<ZkHasher>::new(zk::ZkStateModel::Scalar);
-                                state_builder.batch_set(&zk::ZkDeltaPairs(
-                                    [(zk::ZkDataLocator(vec![]), Some(zk::ZkScalar::from(*fee)))]
-                                        .into(),
-                                ))?;
-                                let aux_data = state_builder.compress()?;
-                                (circuit, aux_data, next_state, proof)
-                            }
-                        };
-
-                        let mut cont_account = chain.get_contract_account(*contract_id)?;
-                        if !zk::check_proof(
-                            circuit,
-                            prev_account.height,
-                            &cont_account.compressed_state,
-                            &aux_data,
-                            next_state,
-                            proof,
-                        ) {
-                            return Err(BlockchainError::IncorrectZkProof);
-                        }
-                        cont_account.compressed_state = *next_state;
-                        chain.database.update(&[WriteOp::Put(
-                            keys::contract_account(contract_id),
-                            cont_account.into(),
-                        )])?;
+                    for (i, update) in updates.iter().enumerate() {
+                        executor_fee += chain
+                            .apply_contract_update(
+                                tx,
+                                contract_id,
+                                &contract,
+                                prev_account.height,
+                                i,
+                                update,
+                            )
+                            .map_err(|e| BlockchainError::InvalidContractUpdate {
+                                index: i,
+                                source: Box::new(e),
+                            })?;
 
                         acc_src.balance += executor_fee; // Pay executor fee
                     }
@@ -594,11 +766,7 @@ impl<K: KvStore> KvStoreChain<K> {
             let mut delta_cnt = 0isize;
             for tx in sorted.into_iter().rev() {
                 if let Ok((ops, eff)) = chain.isolated(|chain| chain.apply_tx(&tx.tx, false)) {
-                    let delta_diff = if let TxSideEffect::StateChange { state_change, .. } = eff {
-                        state_change.state.size() as isize - state_change.prev_state.size() as isize
-                    } else {
-                        0
-                    };
+                    let delta_diff = eff.state_size_delta();
                     let block_diff = tx.tx.size();
                     if delta_cnt + delta_diff <= chain.config.max_delta_count as isize
                         && block_sz + block_diff <= chain.config.max_block_size
@@ -618,6 +786,8 @@ impl<K: KvStore> KvStoreChain<K> {
 
     fn apply_block(&mut self, block: &Block, check_pow: bool) -> Result<(), BlockchainError> {
         let (ops, _) = self.isolated(|chain| {
+            // Groth16 proofs are checked in batches, after applying the txs
+            chain.proofs = chain.proofs.deferring();
             let curr_height = chain.get_height()?;
 
             if let Some(height_limit) = self.config.testnet_height_limit {
@@ -626,6 +796,10 @@ impl<K: KvStore> KvStoreChain<K> {
                 }
             }
 
+            self.config
+                .fork_schedule
+                .check_supported(block.header.number)?;
+
             let is_genesis = block.header.number == 0;
             let next_reward = chain.next_reward()?;
 
@@ -674,25 +848,37 @@ impl<K: KvStore> KvStoreChain<K> {
                 }
 
                 // Reward tx allowed to get money from Treasury
-                chain.apply_tx(reward_tx, true)?;
+                chain.apply_tx(reward_tx, true).map_err(|e| {
+                    BlockchainError::InvalidTransaction {
+                        index: 0,
+                        hash: reward_tx.hash(),
+                        source: Box::new(e),
+                    }
+                })?;
                 &block.body[1..]
             } else {
                 &block.body[..]
             };
+            // Index of the first transaction of `txs` within the block body
+            let txs_offset = block.body.len() - txs.len();
 
             let mut body_size = 0usize;
             let mut state_size_delta = 0isize;
             let mut state_updates: HashMap<ContractId, ZkCompressedStateChange> = HashMap::new();
             let mut outdated_contracts = self.get_outdated_contracts()?;
 
-            if !txs.par_iter().all(|tx| tx.verify_signature()) {
-                return Err(BlockchainError::SignatureError);
+            if let Some(i) = txs.par_iter().position_first(|tx| !tx.verify_signature()) {
+                return Err(BlockchainError::InvalidTransaction {
+                    index: txs_offset + i,
+                    hash: txs[i].hash(),
+                    source: Box::new(BlockchainError::SignatureError),
+                });
             }
 
             let mut num_mpn_function_calls = 0;
             let mut num_mpn_contract_payments = 0;
 
-            for tx in txs.iter() {
+            for (i, tx) in txs.iter().enumerate() {
                 // Count MPN updates
                 if let TransactionData::UpdateContract {
                     contract_id,
@@ -718,8 +904,13 @@ impl<K: KvStore> KvStoreChain<K> {
                 if let TxSideEffect::StateChange {
                     contract_id,
                     state_change,
-                } = chain.apply_tx(tx, is_genesis)?
-                {
+                } = chain.apply_tx(tx, is_genesis).map_err(|e| {
+                    BlockchainError::InvalidTransaction {
+                        index: txs_offset + i,
+                        hash: tx.hash(),
+                        source: Box::new(e),
+                    }
+                })? {
                     state_size_delta += state_change.state.size() as isize
                         - state_change.prev_state.size() as isize;
                     state_updates.insert(contract_id, state_change.clone());
@@ -729,6 +920,21 @@ impl<K: KvStore> KvStoreChain<K> {
                 }
             }
 
+            if let Some((hash, index)) = chain.proofs.verify_deferred() {
+                return Err(BlockchainError::InvalidTransaction {
+                    index: block
+                        .body
+                        .iter()
+                        .position(|tx| tx.hash() == hash)
+                        .ok_or(BlockchainError::Inconsistency)?,
+                    hash,
+                    source: Box::new(BlockchainError::InvalidContractUpdate {
+                        index,
+                        source: Box::new(BlockchainError::IncorrectZkProof),
+                    }),
+                });
+            }
+
             if !is_genesis
                 && (num_mpn_function_calls < self.config.mpn_num_function_calls
                     || num_mpn_contract_payments < self.config.mpn_num_contract_payments)
@@ -783,7 +989,13 @@ impl<K: KvStore> KvStoreChain<K> {
 
 impl<K: KvStore> Blockchain for KvStoreChain<K> {
     fn db_checksum(&self) -> Result<String, BlockchainError> {
-        Ok(hex::encode(self.database.checksum::<Hasher>()?))
+        Ok(self.database.checksum()?.to_string())
+    }
+    fn db_cache_stats(&self) -> Option<CacheStats> {
+        self.database.cache_stats()
+    }
+    fn db_backup_job(&self) -> Result<BackupJob, BlockchainError> {
+        Ok(self.database.backup_job()?)
     }
     fn get_header(&self, index: u64) -> Result<Header, BlockchainError> {
         if index >= self.get_height()? {
@@ -939,6 +1151,21 @@ impl<K: KvStore> Blockchain for KvStoreChain<K> {
         )?)
     }
 
+    fn get_mpn_account_index(&self, address: &jubjub::PublicKey) -> Result<u32, BlockchainError> {
+        if let Some(index) = zk::KvStoreStateManager::<ZkHasher>::find_mpn_account(
+            &self.database,
+            self.config.mpn_contract_id,
+            address,
+        )? {
+            return Ok(index);
+        }
+        zk::KvStoreStateManager::<ZkHasher>::free_mpn_account(
+            &self.database,
+            self.config.mpn_contract_id,
+        )?
+        .ok_or(BlockchainError::NoFreeMpnAccount)
+    }
+
     fn get_mpn_accounts(
         &self,
         page: usize,
@@ -952,6 +1179,112 @@ impl<K: KvStore> Blockchain for KvStoreChain<K> {
         )?)
     }
 
+    fn execute_mpn_payments(
+        &self,
+        executor: &Address,
+        circuit_id: u32,
+        payments: &[MpnPayment],
+    ) -> Result<MpnPaymentBatch, BlockchainError> {
+        let mpn_contract_id = self.config.mpn_contract_id;
+        if self.get_outdated_contracts()?.contains(&mpn_contract_id) {
+            return Err(BlockchainError::StatesOutdated);
+        }
+        let log4_payment_capacity = self
+            .get_contract(mpn_contract_id)?
+            .payment_functions
+            .get(circuit_id as usize)
+            .ok_or(BlockchainError::ContractFunctionNotFound)?
+            .log4_payment_capacity;
+        let contract_account = self.get_contract_account(mpn_contract_id)?;
+
+        let (_, (next_state, state_delta, witnesses)) = self.isolated(|chain| {
+            let mut next_state = contract_account.compressed_state;
+            let mut state_delta = zk::ZkDeltaPairs(Default::default());
+            let mut witnesses = Vec::new();
+            for payment in payments.iter() {
+                if witnesses.len() >= 1 << (2 * log4_payment_capacity) {
+                    break;
+                }
+                if payment.payment.contract_id != mpn_contract_id
+                    || Address::PublicKey(payment.payment.address.clone()) == *executor
+                {
+                    continue;
+                }
+                let index = payment.zk_address_index;
+                let account = match zk::KvStoreStateManager::<ZkHasher>::get_mpn_account(
+                    &chain.database,
+                    mpn_contract_id,
+                    index,
+                ) {
+                    Ok(account) => account,
+                    Err(zk::StateManagerError::LocatorError(_)) => {
+                        continue;
+                    }
+                    Err(e) => {
+                        return Err(e.into());
+                    }
+                };
+                let next_account =
+                    if let Some(next_account) = execute_mpn_payment(&account, &payment.payment) {
+                        next_account
+                    } else {
+                        continue;
+                    };
+                if chain.apply_contract_payment(&payment.payment).is_err() {
+                    continue;
+                }
+                let proof = zk::KvStoreStateManager::<ZkHasher>::prove(
+                    &chain.database,
+                    mpn_contract_id,
+                    zk::ZkDataLocator(vec![]),
+                    index,
+                )?;
+                let vals = [
+                    next_account.nonce.into(),
+                    next_account.address.0,
+                    next_account.address.1,
+                    next_account.balance.into(),
+                ];
+                for (i, val) in vals.into_iter().enumerate() {
+                    state_delta
+                        .0
+                        .insert(zk::ZkDataLocator(vec![index, i as u32]), Some(val));
+                }
+                zk::KvStoreStateManager::<ZkHasher>::set_mpn_account(
+                    &mut chain.database,
+                    mpn_contract_id,
+                    index,
+                    next_account,
+                    &mut next_state.state_size,
+                )?;
+                witnesses.push(MpnPaymentWitness {
+                    payment: payment.clone(),
+                    account,
+                    proof,
+                });
+            }
+            next_state.state_hash = zk::KvStoreStateManager::<ZkHasher>::get_data(
+                &chain.database,
+                mpn_contract_id,
+                &zk::ZkDataLocator(vec![]),
+            )?;
+            Ok((next_state, state_delta, witnesses))
+        })?;
+
+        let mut batch = MpnPaymentBatch {
+            circuit_id,
+            height: contract_account.height,
+            prev_state: contract_account.compressed_state,
+            aux_data: Default::default(),
+            next_state,
+            state_delta,
+            payments: witnesses,
+        };
+        batch.aux_data =
+            compress_contract_payments(&batch.contract_payments(), log4_payment_capacity)?;
+        Ok(batch)
+    }
+
     fn will_extend(
         &self,
         from: u64,
@@ -987,15 +1320,11 @@ impl<K: KvStore> Blockchain for KvStoreChain<K> {
             .collect::<Result<Vec<u32>, BlockchainError>>()?;
 
         for h in headers.iter() {
+            self.config.fork_schedule.check_supported(h.number)?;
+
             if h.number % self.config.difficulty_calc_interval == 0 {
                 if h.proof_of_work.target
-                    != utils::calc_pow_difficulty(
-                        self.config.difficulty_calc_interval,
-                        self.config.block_time,
-                        self.config.minimum_pow_difficulty,
-                        &last_header.proof_of_work,
-                        &last_pow,
-                    )
+                    != self.recalculated_difficulty(&last_header.proof_of_work, &last_pow)
                 {
                     return Err(BlockchainError::DifficultyTargetWrong);
                 }
@@ -1049,8 +1378,13 @@ impl<K: KvStore> Blockchain for KvStoreChain<K> {
                 chain.rollback()?;
             }
 
-            for block in blocks.iter() {
-                chain.apply_block(block, true)?;
+            for (i, block) in blocks.iter().enumerate() {
+                chain
+                    .apply_block(block, true)
+                    .map_err(|e| BlockchainError::InvalidBlock {
+                        height: from + i as u64,
+                        source: Box::new(e),
+                    })?;
             }
 
             Ok(())
@@ -1190,7 +1524,7 @@ impl<K: KvStore> Blockchain for KvStoreChain<K> {
 
     fn pow_key(&self, index: u64) -> Result<Vec<u8>, BlockchainError> {
         Ok(if index < self.config.pow_key_change_delay {
-            self.config.pow_base_key.to_vec()
+            self.config.pow_base_key.clone()
         } else {
             let reference = ((index - self.config.pow_key_change_delay)
                 / self.config.pow_key_change_interval)
@@ -1327,6 +1661,27 @@ impl<K: KvStore> Blockchain for KvStoreChain<K> {
         Ok(())
     }
 
+    fn simulate_transaction(
+        &self,
+        tx_delta: &TransactionAndDelta,
+    ) -> Result<TransactionSimulation, BlockchainError> {
+        let (_, (side_effect, balances)) = self.isolated(|chain| {
+            let side_effect = chain.apply_tx(&tx_delta.tx, false)?;
+            let mut balances = HashMap::new();
+            for addr in touched_addresses(&tx_delta.tx) {
+                let balance = chain.get_account(addr.clone())?.balance;
+                balances.insert(addr, balance);
+            }
+            Ok((side_effect, balances))
+        })?;
+        Ok(TransactionSimulation {
+            state_size_delta: side_effect.state_size_delta(),
+            side_effect,
+            fee: tx_delta.tx.fee,
+            balances,
+        })
+    }
+
     fn read_state(
         &self,
         contract_id: ContractId,
@@ -1339,6 +1694,60 @@ impl<K: KvStore> Blockchain for KvStoreChain<K> {
         )?)
     }
 
+    fn read_state_at(
+        &self,
+        contract_id: ContractId,
+        locator: zk::ZkDataLocator,
+        height: u64,
+    ) -> Result<zk::ZkScalar, BlockchainError> {
+        Ok(zk::KvStoreStateManager::<ZkHasher>::get_data_at(
+            &self.database,
+            contract_id,
+            &locator,
+            height,
+        )?)
+    }
+
+    fn get_full_state_at(
+        &self,
+        contract_id: ContractId,
+        height: u64,
+    ) -> Result<zk::ZkState, BlockchainError> {
+        Ok(zk::KvStoreStateManager::<ZkHasher>::get_full_state_at(
+            &self.database,
+            contract_id,
+            height,
+        )?)
+    }
+
+    fn get_full_state_page_at(
+        &self,
+        contract_id: ContractId,
+        height: u64,
+        page: usize,
+        page_size: usize,
+    ) -> Result<Vec<(zk::ZkDataLocator, zk::ZkScalar)>, BlockchainError> {
+        Ok(zk::KvStoreStateManager::<ZkHasher>::get_data_page_at(
+            &self.database,
+            contract_id,
+            height,
+            page,
+            page_size,
+        )?)
+    }
+
+    fn prove_state(
+        &self,
+        contract_id: ContractId,
+        locator: zk::ZkDataLocator,
+    ) -> Result<zk::ZkStateProof, BlockchainError> {
+        Ok(zk::KvStoreStateManager::<ZkHasher>::prove_data(
+            &self.database,
+            contract_id,
+            &locator,
+        )?)
+    }
+
     fn generate_state_patch(
         &self,
         heights: HashMap<ContractId, u64>,
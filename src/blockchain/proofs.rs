@@ -0,0 +1,130 @@
+use super::*;
+use std::sync::{Arc, Mutex};
+use zk::groth16::{Groth16PreparedKey, Groth16Statement};
+
+// The cache is cleared when it reaches this size
+const MAX_PREPARED_KEYS: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContractCircuit {
+    Payment(u32),
+    Function(u32),
+}
+
+type CircuitKeys = HashMap<(ContractId, ContractCircuit), Arc<Groth16PreparedKey>>;
+
+struct DeferredProof {
+    tx_hash: <Hasher as Hash>::Output,
+    update_index: usize,
+    circuit: (ContractId, ContractCircuit),
+    key: Arc<Groth16PreparedKey>,
+    statement: Groth16Statement,
+}
+
+/// Checks the proofs of contract updates, caching the prepared verifying keys
+/// of the contract circuits. A deferring verifier queues the Groth16 proofs
+/// instead, so that the proofs of a block are checked in batches.
+#[derive(Clone, Default)]
+pub struct ProofVerifier {
+    keys: Arc<Mutex<CircuitKeys>>,
+    deferred: Option<Arc<Mutex<Vec<DeferredProof>>>>,
+}
+
+impl ProofVerifier {
+    /// A verifier sharing the cache of this one, deferring Groth16 proofs
+    /// until `verify_deferred` is called
+    pub fn deferring(&self) -> Self {
+        Self {
+            keys: self.keys.clone(),
+            deferred: Some(Default::default()),
+        }
+    }
+
+    fn prepared_key(
+        &self,
+        circuit: (ContractId, ContractCircuit),
+        vk: &zk::groth16::Groth16VerifyingKey,
+    ) -> Arc<Groth16PreparedKey> {
+        let mut keys = self.keys.lock().unwrap();
+        if let Some(key) = keys.get(&circuit) {
+            return key.clone();
+        }
+        if keys.len() >= MAX_PREPARED_KEYS {
+            keys.clear();
+        }
+        let key = Arc::new(vk.prepare());
+        keys.insert(circuit, key.clone());
+        key
+    }
+
+    /// Returns `true` when the proof is either valid or deferred
+    #[allow(clippy::too_many_arguments)]
+    pub fn check(
+        &self,
+        tx: &Transaction,
+        update_index: usize,
+        circuit: (ContractId, ContractCircuit),
+        vk: &zk::ZkVerifierKey,
+        prev_height: u64,
+        prev_state: &zk::ZkCompressedState,
+        aux_data: &zk::ZkCompressedState,
+        next_state: &zk::ZkCompressedState,
+        proof: &zk::ZkProof,
+    ) -> bool {
+        match (vk, proof) {
+            (zk::ZkVerifierKey::Groth16(vk), zk::ZkProof::Groth16(proof)) => {
+                let key = self.prepared_key(circuit, vk);
+                let statement = Groth16Statement::new(
+                    prev_height,
+                    prev_state.state_hash,
+                    aux_data.state_hash,
+                    next_state.state_hash,
+                    proof,
+                );
+                if let Some(deferred) = &self.deferred {
+                    deferred.lock().unwrap().push(DeferredProof {
+                        tx_hash: tx.hash(),
+                        update_index,
+                        circuit,
+                        key,
+                        statement,
+                    });
+                    true
+                } else {
+                    key.verify(&statement)
+                }
+            }
+            _ => zk::check_proof(vk, prev_height, prev_state, aux_data, next_state, proof),
+        }
+    }
+
+    /// Checks the deferred proofs in batches of the same circuit. Batches that
+    /// fail are checked proof by proof, and the transaction hash and update
+    /// index of the first invalid proof are returned.
+    pub fn verify_deferred(&self) -> Option<(<Hasher as Hash>::Output, usize)> {
+        let deferred = std::mem::take(&mut *self.deferred.as_ref()?.lock().unwrap());
+        let mut batches = HashMap::<(ContractId, ContractCircuit), Vec<usize>>::new();
+        for (i, proof) in deferred.iter().enumerate() {
+            batches.entry(proof.circuit).or_default().push(i);
+        }
+        let first_invalid = batches
+            .into_par_iter()
+            .filter_map(|(_, indices)| {
+                let key = &deferred[indices[0]].key;
+                let statements = indices
+                    .iter()
+                    .map(|i| deferred[*i].statement.clone())
+                    .collect::<Vec<_>>();
+                if key.batch_verify(&statements) {
+                    None
+                } else {
+                    indices
+                        .into_iter()
+                        .find(|i| !key.verify(&deferred[*i].statement))
+                }
+            })
+            .min()?;
+        let invalid = &deferred[first_invalid];
+        Some((invalid.tx_hash, invalid.update_index))
+    }
+}
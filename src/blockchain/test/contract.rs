@@ -46,6 +46,60 @@ fn test_contract_create_patch() -> Result<(), BlockchainError> {
     Ok(())
 }
 
+#[test]
+fn test_contract_plonk_setup() -> Result<(), BlockchainError> {
+    let alice = Wallet::new(Vec::from("ABC"));
+    let state_model = zk::ZkStateModel::List {
+        item_type: Box::new(zk::ZkStateModel::Scalar),
+        log4_size: 5,
+    };
+    let plonk_contract = |x_g2: Vec<u8>| {
+        let vk = zk::plonk::PlonkVerifyingKey {
+            domain_size: 8,
+            q_m: vec![],
+            q_l: vec![],
+            q_r: vec![],
+            q_o: vec![],
+            q_c: vec![],
+            s_sigma1: vec![],
+            s_sigma2: vec![],
+            s_sigma3: vec![],
+            x_g2,
+        };
+        alice.create_contract(
+            zk::ZkContract {
+                state_model: state_model.clone(),
+                initial_state: state_model
+                    .compress::<ZkHasher>(&Default::default())
+                    .unwrap(),
+                payment_functions: Vec::new(),
+                functions: vec![zk::ZkVerifierKey::Plonk(Box::new(vk))],
+            },
+            Default::default(),
+            Money(0),
+            1,
+        )
+    };
+
+    // No ceremony pinned
+    let mut chain = KvStoreChain::new(db::RamKvStore::new(), easy_config())?;
+    assert!(matches!(
+        chain.apply_tx(&plonk_contract(vec![1; 96]).tx, false),
+        Err(BlockchainError::UntrustedPlonkSetup)
+    ));
+
+    let mut conf = easy_config();
+    conf.plonk_srs_x_g2 = Some(vec![1; 96]);
+    let mut chain = KvStoreChain::new(db::RamKvStore::new(), conf)?;
+    assert!(matches!(
+        chain.apply_tx(&plonk_contract(vec![2; 96]).tx, false),
+        Err(BlockchainError::UntrustedPlonkSetup)
+    ));
+    chain.apply_tx(&plonk_contract(vec![1; 96]).tx, false)?;
+
+    Ok(())
+}
+
 #[test]
 fn test_contract_update() -> Result<(), BlockchainError> {
     let miner = Wallet::new(Vec::from("MINER"));
@@ -254,8 +308,8 @@ fn test_contract_update() -> Result<(), BlockchainError> {
         Err(BlockchainError::ContractNotFound)
     ));
 
-    assert!(matches!(
-        chain.apply_tx(
+    let err = chain
+        .apply_tx(
             &alice
                 .call_function(
                     cid,
@@ -268,13 +322,17 @@ fn test_contract_update() -> Result<(), BlockchainError> {
                     2,
                 )
                 .tx,
-            false
-        ),
-        Err(BlockchainError::ContractFunctionNotFound)
+            false,
+        )
+        .unwrap_err();
+    assert_eq!(err.contract_update_index(), Some(0));
+    assert!(matches!(
+        err.root_cause(),
+        BlockchainError::ContractFunctionNotFound
     ));
 
-    assert!(matches!(
-        chain.apply_tx(
+    let err = chain
+        .apply_tx(
             &alice
                 .call_function(
                     cid,
@@ -287,9 +345,13 @@ fn test_contract_update() -> Result<(), BlockchainError> {
                     2,
                 )
                 .tx,
-            false
-        ),
-        Err(BlockchainError::IncorrectZkProof)
+            false,
+        )
+        .unwrap_err();
+    assert_eq!(err.contract_update_index(), Some(0));
+    assert!(matches!(
+        err.root_cause(),
+        BlockchainError::IncorrectZkProof
     ));
 
     chain.rollback()?;
@@ -304,3 +366,461 @@ fn test_contract_update() -> Result<(), BlockchainError> {
 
     Ok(())
 }
+
+#[test]
+fn test_chain_export_import() -> Result<(), ChainFileError> {
+    let miner = Wallet::new(Vec::from("MINER"));
+    let alice = Wallet::new(Vec::from("ABC"));
+    let mut chain = KvStoreChain::new(db::RamKvStore::new(), easy_config())?;
+
+    let state_model = zk::ZkStateModel::List {
+        item_type: Box::new(zk::ZkStateModel::Scalar),
+        log4_size: 5,
+    };
+    let mut full_state = zk::ZkState {
+        rollbacks: vec![],
+        data: Default::default(),
+    };
+    let tx = alice.create_contract(
+        zk::ZkContract {
+            state_model: state_model.clone(),
+            initial_state: state_model
+                .compress::<ZkHasher>(&full_state.data)
+                .map_err(BlockchainError::from)?,
+            payment_functions: Vec::new(),
+            functions: vec![zk::ZkVerifierKey::Dummy],
+        },
+        full_state.data.clone(),
+        Money(0),
+        1,
+    );
+    let cid = ContractId::new(&tx.tx);
+    let delta = zk::ZkDeltaPairs(
+        [(zk::ZkDataLocator(vec![1]), Some(zk::ZkScalar::from(5)))]
+            .into_iter()
+            .collect(),
+    );
+    full_state.apply_delta(&delta);
+    let update = alice.call_function(
+        cid,
+        0,
+        delta,
+        state_model
+            .compress::<ZkHasher>(&full_state.data)
+            .map_err(BlockchainError::from)?,
+        zk::ZkProof::Dummy(true),
+        Money(0),
+        Money(0),
+        2,
+    );
+    for (i, mempool) in [vec![tx], vec![], vec![update]].into_iter().enumerate() {
+        let draft = chain
+            .draft_block(i as u32 + 1, &with_dummy_stats(&mempool), &miner, true)?
+            .unwrap();
+        chain.apply_block(&draft.block, true)?;
+        chain.update_states(&draft.patch)?;
+    }
+    assert_eq!(chain.get_height()?, 4);
+
+    let mut file = Vec::new();
+    assert_eq!(export_chain(&chain, 0, u64::MAX, &mut file, |_| {})?, 4);
+    let mut partial = Vec::new();
+    assert_eq!(export_chain(&chain, 0, 3, &mut partial, |_| {})?, 3);
+
+    let mut imported = KvStoreChain::new(db::RamKvStore::new(), easy_config())?;
+    let summary = import_chain(&mut imported, &mut partial.as_slice(), |_| {})?;
+    assert_eq!(
+        summary,
+        ImportSummary {
+            imported: 2,
+            skipped: 1
+        }
+    );
+    // The last record carries the states as they were after its block
+    assert_eq!(imported.get_height()?, 3);
+    assert_eq!(imported.get_outdated_contracts()?.len(), 0);
+    assert_eq!(
+        imported.read_state(cid, zk::ZkDataLocator(vec![1]))?,
+        zk::ZkScalar::from(0)
+    );
+
+    let mut progress = Vec::new();
+    let summary = import_chain(&mut imported, &mut file.as_slice(), |i| progress.push(i))?;
+    assert_eq!(
+        summary,
+        ImportSummary {
+            imported: 1,
+            skipped: 3
+        }
+    );
+    assert_eq!(progress, vec![0, 1, 2, 3]);
+    assert_eq!(imported.get_height()?, 4);
+    assert_eq!(imported.get_outdated_contracts()?.len(), 0);
+    assert_eq!(
+        imported.read_state(cid, zk::ZkDataLocator(vec![1]))?,
+        zk::ZkScalar::from(5)
+    );
+    assert_eq!(imported.get_tip()?.hash(), chain.get_tip()?.hash());
+
+    // Files of other chains are rejected
+    let mut other = KvStoreChain::new(db::RamKvStore::new(), easy_config())?;
+    let draft = other
+        .draft_block(10, &HashMap::new(), &alice, true)?
+        .unwrap();
+    other.apply_block(&draft.block, true)?;
+    let mut other_file = Vec::new();
+    export_chain(&other, 0, u64::MAX, &mut other_file, |_| {})?;
+    assert!(matches!(
+        import_chain(&mut imported, &mut other_file.as_slice(), |_| {}),
+        Err(ChainFileError::DifferentBlock(1))
+    ));
+
+    // Truncated records are reported
+    assert!(matches!(
+        import_chain(&mut imported, &mut &file[..file.len() - 1], |_| {}),
+        Err(ChainFileError::IoError(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_contract_batched_proofs() -> Result<(), BlockchainError> {
+    let miner = Wallet::new(Vec::from("MINER"));
+    let alice = Wallet::new(Vec::from("ABC"));
+    let mut chain = KvStoreChain::new(db::RamKvStore::new(), easy_config())?;
+    let provers = [
+        zk::groth16::TestProver::new(1),
+        zk::groth16::TestProver::new(2),
+    ];
+
+    let state_model = zk::ZkStateModel::List {
+        item_type: Box::new(zk::ZkStateModel::Scalar),
+        log4_size: 5,
+    };
+    let full_state = zk::ZkState {
+        rollbacks: vec![],
+        data: Default::default(),
+    };
+    let tx = alice.create_contract(
+        zk::ZkContract {
+            state_model: state_model.clone(),
+            initial_state: state_model.compress::<ZkHasher>(&full_state.data)?,
+            payment_functions: Vec::new(),
+            functions: provers
+                .iter()
+                .map(|p| zk::ZkVerifierKey::Groth16(Box::new(p.verifying_key())))
+                .collect(),
+        },
+        full_state.data.clone(),
+        Money(0),
+        1,
+    );
+    let cid = ContractId::new(&tx.tx);
+    let draft = chain
+        .draft_block(1, &with_dummy_stats(&[tx]), &miner, false)?
+        .unwrap();
+    chain.apply_block(&draft.block, true)?;
+    chain.update_states(&draft.patch)?;
+
+    let mut fee_builder = zk::ZkStateBuilder::<ZkHasher>::new(zk::ZkStateModel::Scalar);
+    fee_builder.batch_set(&zk::ZkDeltaPairs(
+        [(zk::ZkDataLocator(vec![]), Some(zk::ZkScalar::from(0)))].into(),
+    ))?;
+    let aux_data = fee_builder.compress()?;
+
+    // Calls of both functions, each on the state left by the previous one.
+    // The proof of the `invalid` call is created for another next-state. The
+    // deltas are cumulative, since drafts keep the last delta of a contract.
+    let calls = |chain: &KvStoreChain<db::RamKvStore>,
+                 invalid: Option<u32>|
+     -> Result<Vec<TransactionAndDelta>, BlockchainError> {
+        let account = chain.get_contract_account(cid)?;
+        let (mut height, mut prev_state) = (account.height, account.compressed_state);
+        let mut full_state = full_state.clone();
+        let mut delta = zk::ZkDeltaPairs(Default::default());
+        let mut txs = Vec::new();
+        for i in 0..4u32 {
+            delta.0.insert(
+                zk::ZkDataLocator(vec![i]),
+                Some(zk::ZkScalar::from(i as u64 + 1)),
+            );
+            full_state.apply_delta(&delta);
+            let next_state = state_model.compress::<ZkHasher>(&full_state.data)?;
+            let mut proven_state = next_state.state_hash;
+            if invalid == Some(i) {
+                proven_state += zk::ZkScalar::from(1);
+            }
+            let proof = provers[i as usize % 2].prove(
+                height,
+                prev_state.state_hash,
+                aux_data.state_hash,
+                proven_state,
+            );
+            txs.push(alice.call_function(
+                cid,
+                i % 2,
+                delta.clone(),
+                next_state,
+                zk::ZkProof::Groth16(Box::new(proof)),
+                Money(0),
+                Money(0),
+                i + 2,
+            ));
+            height += 1;
+            prev_state = next_state;
+        }
+        Ok(txs)
+    };
+
+    let draft = chain
+        .draft_block(2, &with_dummy_stats(&calls(&chain, None)?), &miner, true)?
+        .unwrap();
+    assert_eq!(draft.block.body.len(), 5);
+
+    // The invalid proof is found, even though its batch has valid proofs too
+    let invalid_tx = calls(&chain, Some(2))?.remove(2).tx;
+    let mut block = draft.block.clone();
+    let index = block
+        .body
+        .iter()
+        .position(|tx| tx.src == invalid_tx.src && tx.nonce == invalid_tx.nonce)
+        .unwrap();
+    block.body[index] = invalid_tx;
+    block.header.block_root = block.merkle_tree().root();
+    let err = chain.fork_on_ram().apply_block(&block, false).unwrap_err();
+    assert_eq!(err.transaction_index(), Some(index));
+    assert_eq!(err.contract_update_index(), Some(0));
+    assert!(matches!(
+        err.root_cause(),
+        BlockchainError::IncorrectZkProof
+    ));
+
+    // Proofs are still checked one by one outside of blocks
+    let invalid_tx = calls(&chain, Some(0))?.remove(0).tx;
+    let err = chain
+        .fork_on_ram()
+        .apply_tx(&invalid_tx, false)
+        .unwrap_err();
+    assert!(matches!(
+        err.root_cause(),
+        BlockchainError::IncorrectZkProof
+    ));
+    let valid_tx = calls(&chain, None)?.remove(0).tx;
+    chain.fork_on_ram().apply_tx(&valid_tx, false)?;
+
+    chain.apply_block(&draft.block, true)?;
+    chain.update_states(&draft.patch)?;
+    assert_eq!(chain.get_height()?, 3);
+    assert_eq!(chain.get_outdated_contracts()?.len(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_contract_state_history() -> Result<(), BlockchainError> {
+    let miner = Wallet::new(Vec::from("MINER"));
+    let alice = Wallet::new(Vec::from("ABC"));
+    let cid =
+        ContractId::from_str("94f768758eebc1e0a1fc806726db01aaff5331763ce7c93b253770abfa7a53ee")
+            .unwrap();
+    let mut chain = KvStoreChain::new(db::RamKvStore::new(), easy_config())?;
+    let state_model = chain.get_contract(cid)?.state_model;
+    let loc = zk::ZkDataLocator(vec![123]);
+
+    let mut full_state = chain.get_full_state_at(cid, chain.get_contract_account(cid)?.height)?;
+    let mut states = vec![full_state.data.clone()];
+    for i in 1..8 {
+        let delta = zk::ZkDeltaPairs(
+            [(loc.clone(), Some(zk::ZkScalar::from(i * 10)))]
+                .into_iter()
+                .collect(),
+        );
+        full_state.apply_delta(&delta);
+        let tx = alice.call_function(
+            cid,
+            0,
+            delta,
+            state_model.compress::<ZkHasher>(&full_state.data)?,
+            zk::ZkProof::Dummy(true),
+            Money(0),
+            Money(0),
+            i as u32,
+        );
+        let draft = chain
+            .draft_block(i as u32, &with_dummy_stats(&[tx]), &miner, false)?
+            .unwrap();
+        chain.apply_block(&draft.block, true)?;
+        chain.update_states(&draft.patch)?;
+        states.push(full_state.data.clone());
+    }
+
+    let height = chain.get_contract_account(cid)?.height;
+    let first = height + 1 - states.len() as u64;
+    assert_eq!(chain.read_state_at(cid, loc.clone(), height)?, 70.into());
+    assert_eq!(
+        chain.read_state_at(cid, loc.clone(), height - 1)?,
+        60.into()
+    );
+    // Rollback deltas of the last 5 heights are retained
+    for h in height - 5..=height {
+        let state = &states[(h - first) as usize];
+        assert_eq!(
+            chain.read_state_at(cid, loc.clone(), h)?,
+            state.0.get(&loc).cloned().unwrap_or_default()
+        );
+        let full_state = chain.get_full_state_at(cid, h)?;
+        assert_eq!(&full_state.data, state);
+        let mut paged = Vec::new();
+        for page in 0.. {
+            let values = chain.get_full_state_page_at(cid, h, page, 1)?;
+            if values.is_empty() {
+                break;
+            }
+            assert_eq!(values.len(), 1);
+            paged.extend(values);
+        }
+        assert_eq!(paged.len(), state.0.len());
+        assert_eq!(zk::ZkDataPairs(paged.into_iter().collect()), *state);
+        assert_eq!(
+            state_model.compress::<ZkHasher>(&full_state.data)?,
+            if h < height {
+                chain.get_compressed_state_at(cid, h)?
+            } else {
+                chain.get_contract_account(cid)?.compressed_state
+            }
+        );
+    }
+    for h in [height - 6, height + 1] {
+        assert!(matches!(
+            chain.read_state_at(cid, loc.clone(), h),
+            Err(BlockchainError::StateManagerError(
+                zk::StateManagerError::HeightNotAvailable(_)
+            ))
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_contract_state_proof() -> Result<(), BlockchainError> {
+    let cid =
+        ContractId::from_str("94f768758eebc1e0a1fc806726db01aaff5331763ce7c93b253770abfa7a53ee")
+            .unwrap();
+    let chain = KvStoreChain::new(db::RamKvStore::new(), easy_config())?;
+    let state_model = chain.get_contract(cid)?.state_model;
+    let root = chain.get_contract_account(cid)?.compressed_state.state_hash;
+    for loc in [zk::ZkDataLocator(vec![]), zk::ZkDataLocator(vec![123])] {
+        let value = chain.read_state(cid, loc.clone())?;
+        let proof = chain.prove_state(cid, loc.clone())?;
+        assert!(zk::verify_state_proof::<ZkHasher>(
+            &state_model,
+            &loc,
+            value,
+            &proof,
+            root
+        ));
+    }
+    Ok(())
+}
+
+// Test chain whose MPN contract holds actual MPN accounts
+fn mpn_config() -> BlockchainConfig {
+    let mut conf = easy_config();
+    let mpn_tx = &mut conf.genesis.block.body[0];
+    if let TransactionData::CreateContract { contract } = &mut mpn_tx.data {
+        contract.state_model = zk::ZkStateModel::List {
+            item_type: Box::new(zk::MPN_ACCOUNT_STATE_MODEL.clone()),
+            log4_size: 5,
+        };
+        contract.initial_state =
+            zk::ZkCompressedState::empty::<ZkHasher>(contract.state_model.clone());
+    }
+    conf.mpn_contract_id = ContractId::new(mpn_tx);
+    conf.genesis.patch = ZkBlockchainPatch {
+        patches: [(
+            conf.mpn_contract_id,
+            zk::ZkStatePatch::Delta(Default::default()),
+        )]
+        .into_iter()
+        .collect(),
+    };
+    conf
+}
+
+#[test]
+fn test_execute_mpn_payments() -> Result<(), BlockchainError> {
+    let miner = Wallet::new(Vec::from("MINER"));
+    let alice = Wallet::new(Vec::from("ABC"));
+    let bob = Wallet::new(Vec::from("CBA"));
+    let mut chain = KvStoreChain::new(db::RamKvStore::new(), mpn_config())?;
+    let mpn_id = chain.config.mpn_contract_id;
+
+    let payments = [
+        alice.pay_contract(mpn_id, 0, 1, Money(100), Money(1), false),
+        // Executors can't execute their own payments
+        miner.pay_contract(mpn_id, 1, 1, Money(0), Money(0), false),
+        // Not enough balance in the MPN account
+        alice.pay_contract(mpn_id, 0, 2, Money(200), Money(1), true),
+        alice.pay_contract(mpn_id, 0, 2, Money(50), Money(1), true),
+    ];
+    let batch = chain.execute_mpn_payments(&miner.get_address(), 0, &payments)?;
+    assert_eq!(
+        batch
+            .payments
+            .iter()
+            .map(|w| w.payment.clone())
+            .collect::<Vec<_>>(),
+        vec![payments[0].clone(), payments[3].clone()]
+    );
+    assert_eq!(batch.payments[0].account, Default::default());
+    assert_eq!(batch.payments[1].account.balance, Money(100));
+    assert_eq!(
+        batch.payments[0].proof.concat(),
+        chain.prove_state(mpn_id, zk::ZkDataLocator(vec![0]))?.0
+    );
+    assert_eq!(
+        batch.prev_state,
+        chain.get_contract_account(mpn_id)?.compressed_state
+    );
+    assert_eq!(
+        batch.aux_data,
+        compress_contract_payments(&batch.contract_payments(), 1)?
+    );
+    // Executing the batch leaves the chain untouched
+    assert_eq!(chain.get_mpn_account(0)?, Default::default());
+
+    // Deposits can't go to accounts owned by other addresses
+    let deposited = execute_mpn_payment(&Default::default(), &payments[0].payment).unwrap();
+    assert!(execute_mpn_payment(
+        &deposited,
+        &bob.pay_contract(mpn_id, 0, 1, Money(10), Money(0), false)
+            .payment
+    )
+    .is_none());
+
+    let tx = miner.update_contract(
+        mpn_id,
+        vec![batch.contract_update(zk::ZkProof::Dummy(true))],
+        batch.state_delta.clone(),
+        Money(0),
+        1,
+    );
+    let draft = chain
+        .draft_block(1, &with_dummy_stats(&[tx]), &miner, false)?
+        .unwrap();
+    assert_eq!(draft.block.body.len(), 2);
+    chain.apply_block(&draft.block, true)?;
+    chain.update_states(&draft.patch)?;
+    assert_eq!(
+        chain.get_contract_account(mpn_id)?.compressed_state,
+        batch.next_state
+    );
+    let account = chain.get_mpn_account(0)?;
+    assert_eq!(account.nonce, 1);
+    assert_eq!(account.balance, Money(49));
+    assert_eq!(account.address, alice.get_zk_address().0.decompress());
+    assert_eq!(chain.get_account(alice.get_address())?.balance, Money(9949));
+    Ok(())
+}
@@ -0,0 +1,172 @@
+use super::*;
+use crate::db::{Fault, FaultyKvStore};
+use proptest::prelude::*;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+type FaultyChain = KvStoreChain<FaultyKvStore<db::RamKvStore>>;
+
+#[derive(Clone, Debug)]
+enum Step {
+    Send(u64),
+    CreateContract,
+    UpdateStates,
+    Rollback,
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    prop_oneof![
+        3 => (1..100u64).prop_map(Step::Send),
+        1 => Just(Step::CreateContract),
+        2 => Just(Step::UpdateStates),
+        1 => Just(Step::Rollback),
+    ]
+}
+
+#[derive(Clone, Debug)]
+enum FaultPoint {
+    Update(usize),
+    Get(usize),
+}
+
+fn fault_point() -> impl Strategy<Value = FaultPoint> {
+    prop_oneof![
+        (0..8usize).prop_map(FaultPoint::Update),
+        (0..400usize).prop_map(FaultPoint::Get),
+    ]
+}
+
+fn contract_tx(alice: &Wallet, nonce: u32) -> TransactionAndDelta {
+    let state_model = zk::ZkStateModel::List {
+        item_type: Box::new(zk::ZkStateModel::Scalar),
+        log4_size: 5,
+    };
+    let data = zk::ZkDataPairs(Default::default());
+    alice.create_contract(
+        zk::ZkContract {
+            state_model: state_model.clone(),
+            initial_state: state_model.compress::<ZkHasher>(&data).unwrap(),
+            payment_functions: Vec::new(),
+            functions: Vec::new(),
+        },
+        data,
+        Money(0),
+        nonce,
+    )
+}
+
+// Steps that can't be taken in the current state of the chain are skipped
+fn run_step(
+    chain: &mut FaultyChain,
+    step: &Step,
+    patch: &mut Option<ZkBlockchainPatch>,
+) -> Result<(), BlockchainError> {
+    let miner = Wallet::new(Vec::from("MINER"));
+    let alice = Wallet::new(Vec::from("ABC"));
+    let bob = Wallet::new(Vec::from("CBA"));
+    let outdated = !chain.get_outdated_contracts()?.is_empty();
+    match step {
+        Step::Send(_) | Step::CreateContract if !outdated => {
+            let nonce = chain.get_account(alice.get_address())?.nonce + 1;
+            let tx = match step {
+                Step::Send(amount) => {
+                    alice.create_transaction(bob.get_address(), Money(*amount), Money(0), nonce)
+                }
+                _ => contract_tx(&alice, nonce),
+            };
+            let timestamp = chain.get_height()? as u32;
+            let draft = chain
+                .draft_block(timestamp, &with_dummy_stats(&[tx]), &miner, true)?
+                .unwrap();
+            chain.apply_block(&draft.block, true)?;
+            *patch = Some(draft.patch);
+        }
+        Step::UpdateStates if outdated => {
+            if let Some(patch) = patch {
+                chain.update_states(patch)?;
+            }
+        }
+        Step::Rollback if chain.get_height()? > 1 => {
+            chain.rollback()?;
+            *patch = None;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn assert_consistent(chain: &FaultyChain, supply: Money) -> Result<(), BlockchainError> {
+    assert_eq!(verify_chain(chain, db::RamKvStore::new(), |_| {})?, None);
+    assert_eq!(circulated_money(chain)?, supply);
+    Ok(())
+}
+
+// Simulates a restart of the process, with the data persisted by the store
+fn reopen(chain: FaultyChain) -> Result<FaultyChain, BlockchainError> {
+    let KvStoreChain {
+        database, config, ..
+    } = chain;
+    KvStoreChain::new(FaultyKvStore::new(database.into_inner()), config)
+}
+
+fn run_with_fault(
+    steps: Vec<Step>,
+    point: FaultPoint,
+    fault: Fault,
+) -> Result<(), BlockchainError> {
+    let mut chain = KvStoreChain::new(FaultyKvStore::new(db::RamKvStore::new()), easy_config())?;
+    let supply = circulated_money(&chain)?;
+    let KvStoreChain {
+        database,
+        config,
+        proofs,
+    } = chain;
+    let database = match point {
+        FaultPoint::Update(n) => database.fail_update_at(n, fault),
+        FaultPoint::Get(n) => database.fail_get_at(n, fault),
+    };
+    chain = KvStoreChain {
+        database,
+        config,
+        proofs,
+    };
+
+    let mut patch = None;
+    for step in steps.iter() {
+        let checksum = chain.database.checksum()?;
+        let height = match catch_unwind(AssertUnwindSafe(|| chain.get_height())) {
+            Ok(Ok(height)) => height,
+            // The fault may also hit the reads made before the step
+            _ if chain.database.triggered() => {
+                chain = reopen(chain)?;
+                assert_eq!(chain.database.checksum()?, checksum);
+                chain.get_height()?
+            }
+            result => result.unwrap()?,
+        };
+        let result = catch_unwind(AssertUnwindSafe(|| run_step(&mut chain, step, &mut patch)));
+        if chain.database.triggered() && !matches!(result, Ok(Ok(()))) {
+            // Nothing of the failed step is persisted
+            chain = reopen(chain)?;
+            assert_eq!(chain.database.checksum()?, checksum);
+            assert_eq!(chain.get_height()?, height);
+            assert_consistent(&chain, supply)?;
+        } else {
+            result.unwrap()?;
+        }
+    }
+    assert_consistent(&reopen(chain)?, supply)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn test_chain_survives_store_faults(
+        steps in prop::collection::vec(step(), 1..12),
+        point in fault_point(),
+        panic in any::<bool>(),
+    ) {
+        let fault = if panic { Fault::Panic } else { Fault::Error };
+        run_with_fault(steps, point, fault).unwrap();
+    }
+}
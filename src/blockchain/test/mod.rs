@@ -1,10 +1,13 @@
 use super::*;
 use crate::config::blockchain;
-use crate::core::{Address, Hasher, Signature, Signer, TransactionData};
+use crate::core::{Address, Signature, Signer, TransactionData};
 use crate::crypto::SignatureScheme;
 use crate::db;
 
 mod contract;
+mod crash;
+#[cfg(feature = "prover")]
+mod prover;
 
 fn easy_config() -> BlockchainConfig {
     let mut conf = blockchain::get_test_blockchain_config();
@@ -30,17 +33,20 @@ fn rollback_till_empty<K: KvStore>(b: &mut KvStoreChain<K>) -> Result<(), Blockc
         b.rollback(),
         Err(BlockchainError::NoBlocksToRollback)
     ));
-    assert!(b.database.pairs("".into())?.is_empty());
+    assert_eq!(
+        b.database.pairs("".into())?.into_keys().collect::<Vec<_>>(),
+        vec![keys::schema_version()]
+    );
     Ok(())
 }
 
 fn circulated_money<K: KvStore>(b: &KvStoreChain<K>) -> Result<Money, BlockchainError> {
     let mut money_sum = Money(0);
-    for (_, v) in b.database.pairs("ACC-".into())? {
+    for (_, v) in b.database.pairs(keys::account_prefix())? {
         let acc: Account = v.try_into().unwrap();
         money_sum += acc.balance;
     }
-    for (_, v) in b.database.pairs("CONT-ACC".into())? {
+    for (_, v) in b.database.pairs(keys::contract_account_prefix())? {
         let acc: ContractAccount = v.try_into().unwrap();
         money_sum += acc.balance;
     }
@@ -74,7 +80,7 @@ fn test_get_header_and_get_block() -> Result<(), BlockchainError> {
 
     broken_chain
         .database
-        .update(&vec![WriteOp::Put("HGT".into(), 3u64.into())])?;
+        .update(&vec![WriteOp::Put(keys::height(), 3u64.into())])?;
 
     assert!(matches!(
         broken_chain.get_block(2),
@@ -392,17 +398,25 @@ fn test_block_number_correctness_check() -> Result<(), BlockchainError> {
     let mut fork3 = chain.fork_on_ram();
     let mut blk1_wrong_num = blk1.clone();
     blk1_wrong_num.block.header.number += 1;
+    let err = fork3
+        .extend(1, &[blk1_wrong_num.block, blk2.block.clone()])
+        .unwrap_err();
+    assert_eq!(err.block_height(), Some(1));
     assert!(matches!(
-        fork3.extend(1, &[blk1_wrong_num.block, blk2.block.clone()]),
-        Err(BlockchainError::InvalidBlockNumber)
+        err.root_cause(),
+        BlockchainError::InvalidBlockNumber
     ));
 
     let mut fork4 = chain.fork_on_ram();
     let mut blk2_wrong_num = blk2.clone();
     blk2_wrong_num.block.header.number += 1;
+    let err = fork4
+        .extend(1, &[blk1.block, blk2_wrong_num.block.clone()])
+        .unwrap_err();
+    assert_eq!(err.block_height(), Some(2));
     assert!(matches!(
-        fork4.extend(1, &[blk1.block, blk2_wrong_num.block.clone()]),
-        Err(BlockchainError::InvalidBlockNumber)
+        err.root_cause(),
+        BlockchainError::InvalidBlockNumber
     ));
 
     rollback_till_empty(&mut fork1)?;
@@ -437,17 +451,25 @@ fn test_parent_hash_correctness_check() -> Result<(), BlockchainError> {
     let mut fork3 = chain.fork_on_ram();
     let mut blk1_wrong = blk1.clone();
     blk1_wrong.block.header.parent_hash = Default::default();
+    let err = fork3
+        .extend(1, &[blk1_wrong.block, blk2.block.clone()])
+        .unwrap_err();
+    assert_eq!(err.block_height(), Some(1));
     assert!(matches!(
-        fork3.extend(1, &[blk1_wrong.block, blk2.block.clone()]),
-        Err(BlockchainError::InvalidParentHash)
+        err.root_cause(),
+        BlockchainError::InvalidParentHash
     ));
 
     let mut fork4 = chain.fork_on_ram();
     let mut blk2_wrong = blk2.clone();
     blk2_wrong.block.header.parent_hash = Default::default();
+    let err = fork4
+        .extend(1, &[blk1.block, blk2_wrong.block.clone()])
+        .unwrap_err();
+    assert_eq!(err.block_height(), Some(2));
     assert!(matches!(
-        fork4.extend(1, &[blk1.block, blk2_wrong.block.clone()]),
-        Err(BlockchainError::InvalidParentHash)
+        err.root_cause(),
+        BlockchainError::InvalidParentHash
     ));
 
     rollback_till_empty(&mut fork1)?;
@@ -639,7 +661,10 @@ fn test_cant_apply_unsigned_tx() -> Result<(), BlockchainError> {
     // Ensure apply_tx will raise
     match chain.draft_block(1, &with_dummy_stats(&[unsigned_tx.clone()]), &miner, false) {
         Ok(_) => assert!(false, "Unsigned transaction shall not be applied"),
-        Err(e) => assert!(matches!(e, BlockchainError::SignatureError)),
+        Err(e) => {
+            assert_eq!(e.transaction_index(), Some(1));
+            assert!(matches!(e.root_cause(), BlockchainError::SignatureError));
+        }
     }
 
     // Ensure tx is not included in block and bob has not received funds
@@ -690,7 +715,10 @@ fn test_cant_apply_invalid_signed_tx() -> Result<(), BlockchainError> {
     // Ensure apply_tx will raise
     match chain.draft_block(1, &with_dummy_stats(&[tx.clone()]), &miner, false) {
         Ok(_) => assert!(false, "Invalid signed transaction shall not be applied"),
-        Err(e) => assert!(matches!(e, BlockchainError::SignatureError)),
+        Err(e) => {
+            assert_eq!(e.transaction_index(), Some(1));
+            assert!(matches!(e.root_cause(), BlockchainError::SignatureError));
+        }
     }
 
     // Ensure tx is not included in block and bob has not received funds
@@ -854,6 +882,99 @@ fn test_balances_are_correct_after_tx() -> Result<(), BlockchainError> {
     Ok(())
 }
 
+#[test]
+fn test_simulate_transaction() -> Result<(), BlockchainError> {
+    let alice = Wallet::new(Vec::from("ABC"));
+    let bob = Wallet::new(Vec::from("CBA"));
+
+    let chain = KvStoreChain::new(db::RamKvStore::new(), easy_config())?;
+    let checksum = chain.db_checksum()?;
+
+    // Alice -> 2700 -> Bob (Fee 300)
+    let sim = chain.simulate_transaction(&alice.create_transaction(
+        bob.get_address(),
+        Money(2700),
+        Money(300),
+        1,
+    ))?;
+    assert_eq!(sim.fee, Money(300));
+    assert_eq!(sim.state_size_delta, 0);
+    assert!(matches!(sim.side_effect, TxSideEffect::Nothing));
+    assert_eq!(sim.balances[&alice.get_address()], Money(7000));
+    assert_eq!(sim.balances[&bob.get_address()], Money(2700));
+
+    // Nothing is persisted
+    assert_eq!(chain.db_checksum()?, checksum);
+    assert_eq!(
+        chain.get_account(alice.get_address())?.balance,
+        Money(10000)
+    );
+
+    // Bob -> 2600 -> Alice (Fee 200) (BALANCE INSUFFICIENT!)
+    let err = chain
+        .simulate_transaction(&bob.create_transaction(
+            alice.get_address(),
+            Money(2600),
+            Money(200),
+            1,
+        ))
+        .unwrap_err();
+    assert!(matches!(err, BlockchainError::BalanceInsufficient));
+    let info = BlockchainErrorInfo::from(&err);
+    assert_eq!(info.code, BlockchainErrorCode::BalanceInsufficient);
+    assert_eq!(info.block_height, None);
+
+    let err = BlockchainError::InvalidBlock {
+        height: 12,
+        source: Box::new(BlockchainError::InvalidTransaction {
+            index: 3,
+            hash: Default::default(),
+            source: Box::new(BlockchainError::InvalidContractUpdate {
+                index: 1,
+                source: Box::new(BlockchainError::IncorrectZkProof),
+            }),
+        }),
+    };
+    let info: BlockchainErrorInfo =
+        bincode::deserialize(&bincode::serialize(&BlockchainErrorInfo::from(&err)).unwrap())
+            .unwrap();
+    assert_eq!(info.code, BlockchainErrorCode::IncorrectZkProof);
+    assert_eq!(info.message, err.to_string());
+    assert_eq!(info.block_height, Some(12));
+    assert_eq!(info.transaction_index, Some(3));
+    assert_eq!(info.contract_update_index, Some(1));
+
+    Ok(())
+}
+
+#[test]
+fn test_unsupported_upgrade_halts_chain() -> Result<(), BlockchainError> {
+    let miner = Wallet::new(Vec::from("MINER"));
+    let mut conf = easy_config();
+    conf.fork_schedule.upgrades.push(ScheduledUpgrade {
+        name: "unknown-upgrade".into(),
+        activation_height: 2,
+    });
+    assert!(!conf.fork_schedule.is_active("unknown-upgrade", 1));
+    assert!(conf.fork_schedule.is_active("unknown-upgrade", 2));
+
+    let mut chain = KvStoreChain::new(db::RamKvStore::new(), conf)?;
+    let blk1 = chain
+        .draft_block(1, &HashMap::new(), &miner, true)?
+        .unwrap();
+    chain.extend(1, &[blk1.block])?;
+    assert_eq!(chain.get_height()?, 2);
+
+    assert!(matches!(
+        chain.draft_block(2, &HashMap::new(), &miner, true),
+        Err(BlockchainError::UnsupportedUpgrade(_))
+    ));
+
+    rollback_till_empty(&mut chain)?;
+
+    Ok(())
+}
+
 #[test]
 fn test_genesis_is_not_replaceable() -> Result<(), BlockchainError> {
     let conf = blockchain::get_blockchain_config();
@@ -1078,7 +1199,7 @@ fn test_chain_should_rollback_applied_block() -> Result<(), BlockchainError> {
 
     mine_block(&chain, &mut draft)?;
 
-    let prev_checksum = chain.database.checksum::<Hasher>()?;
+    let prev_checksum = chain.database.checksum()?;
 
     chain.apply_block(&draft.block, true)?;
 
@@ -1094,11 +1215,11 @@ fn test_chain_should_rollback_applied_block() -> Result<(), BlockchainError> {
         }
     );
 
-    let after_checksum = chain.database.checksum::<Hasher>()?;
+    let after_checksum = chain.database.checksum()?;
 
     chain.rollback()?;
 
-    let rollbacked_checksum = chain.database.checksum::<Hasher>()?;
+    let rollbacked_checksum = chain.database.checksum()?;
 
     assert_ne!(prev_checksum, after_checksum);
     assert_eq!(prev_checksum, rollbacked_checksum);
@@ -1115,6 +1236,96 @@ fn test_chain_should_rollback_applied_block() -> Result<(), BlockchainError> {
     Ok(())
 }
 
+#[test]
+fn test_verify_chain() -> Result<(), BlockchainError> {
+    let wallet_miner = Wallet::new(Vec::from("MINER"));
+    let wallet1 = Wallet::new(Vec::from("ABC"));
+    let wallet2 = Wallet::new(Vec::from("CBA"));
+
+    let mut conf = easy_config();
+    conf.genesis.block.body = vec![Transaction {
+        src: Address::Treasury,
+        data: TransactionData::RegularSend {
+            dst: wallet1.get_address(),
+            amount: Money(10_000_000),
+        },
+        nonce: 1,
+        fee: Money(0),
+        sig: Signature::Unsigned,
+    }];
+    let mut chain = KvStoreChain::new(db::RamKvStore::new(), conf)?;
+    for nonce in 1..4 {
+        let tx = wallet1.create_transaction(wallet2.get_address(), Money(100), Money(0), nonce);
+        let mut draft = chain
+            .draft_block(nonce, &with_dummy_stats(&[tx]), &wallet_miner, true)?
+            .unwrap();
+        mine_block(&chain, &mut draft)?;
+        chain.apply_block(&draft.block, true)?;
+    }
+
+    let mut verified = Vec::new();
+    assert_eq!(
+        verify_chain(&chain, db::RamKvStore::new(), |i| verified.push(i))?,
+        None
+    );
+    assert_eq!(verified, vec![0, 1, 2, 3]);
+
+    let diverged = |ops: &[WriteOp]| -> Result<Option<ChainDivergence>, BlockchainError> {
+        let mut tampered = chain.fork_on_ram();
+        tampered.database.update(ops)?;
+        verify_chain(&tampered, db::RamKvStore::new(), |_| {})
+    };
+
+    let mut block = chain.get_block(2)?;
+    block.body[1].data = TransactionData::RegularSend {
+        dst: wallet2.get_address(),
+        amount: Money(200),
+    };
+    assert!(matches!(
+        diverged(&[WriteOp::Put(keys::block(2), (&block).into())])?,
+        Some(ChainDivergence {
+            height: 2,
+            divergence: Divergence::InvalidBlock(_)
+        })
+    ));
+
+    let mut header = chain.get_header(2)?;
+    header.proof_of_work.timestamp += 1;
+    assert_eq!(
+        diverged(&[WriteOp::Put(keys::header(2), header.into())])?,
+        Some(ChainDivergence {
+            height: 2,
+            divergence: Divergence::Header
+        })
+    );
+
+    assert_eq!(
+        diverged(&[WriteOp::Put(
+            keys::rollback(2),
+            Vec::<WriteOp>::new().into()
+        )])?,
+        Some(ChainDivergence {
+            height: 2,
+            divergence: Divergence::PriorState
+        })
+    );
+
+    let mut account = chain.get_account(wallet2.get_address())?;
+    account.balance += Money(1);
+    assert_eq!(
+        diverged(&[WriteOp::Put(
+            keys::account(&wallet2.get_address()),
+            account.into()
+        )])?,
+        Some(ChainDivergence {
+            height: 3,
+            divergence: Divergence::Account(wallet2.get_address())
+        })
+    );
+
+    Ok(())
+}
+
 fn mine_block<B: Blockchain>(chain: &B, draft: &mut BlockAndPatch) -> Result<(), BlockchainError> {
     let pow_key = chain.pow_key(draft.block.header.number)?;
 
@@ -0,0 +1,241 @@
+use super::*;
+use crate::core::ZkSigner;
+use crate::crypto::{jubjub, ZkSignatureScheme};
+use zk::groth16::prover::*;
+
+const LOG4_SIZE: u8 = 2;
+
+lazy_static! {
+    // A list of slots, each holding an owner and a counter
+    static ref COUNTERS_STATE_MODEL: zk::ZkStateModel = zk::ZkStateModel::List {
+        item_type: Box::new(zk::ZkStateModel::Struct {
+            field_types: vec![
+                zk::ZkStateModel::Scalar, // Owner X
+                zk::ZkStateModel::Scalar, // Owner Y
+                zk::ZkStateModel::Scalar, // Counter
+            ],
+        }),
+        log4_size: LOG4_SIZE,
+    };
+}
+
+fn bump_message(index: u32, counter: zk::ZkScalar) -> zk::ZkScalar {
+    <ZkHasher as zk::ZkHasher>::hash(&[zk::ZkScalar::from(index as u64), counter])
+}
+
+// Increments the counter of a slot, with the signature of its owner
+#[derive(Default)]
+struct BumpCircuit {
+    prev_height: Option<u64>,
+    prev_state: Option<zk::ZkScalar>,
+    aux_data: Option<zk::ZkScalar>,
+    next_state: Option<zk::ZkScalar>,
+    index: Option<u32>,
+    owner: Option<jubjub::PointAffine>,
+    counter: Option<zk::ZkScalar>,
+    proof: Option<Vec<[zk::ZkScalar; 3]>>,
+    sig: Option<jubjub::Signature>,
+}
+
+impl Circuit<BellmanFr> for BumpCircuit {
+    fn synthesize<CS: ConstraintSystem<BellmanFr>>(
+        self,
+        cs: &mut CS,
+    ) -> Result<(), SynthesisError> {
+        let inputs = PublicInputs::alloc(
+            cs,
+            self.prev_height,
+            self.prev_state,
+            self.aux_data,
+            self.next_state,
+        )?;
+        let index = alloc_scalar(
+            cs.namespace(|| "index"),
+            self.index.map(|i| zk::ZkScalar::from(i as u64)),
+        )?;
+        let index_bits = alloc_index_bits(cs.namespace(|| "index bits"), &index, LOG4_SIZE)?;
+        let owner = AllocatedPoint::alloc(cs.namespace(|| "owner"), self.owner)?;
+        let counter = alloc_scalar(cs.namespace(|| "counter"), self.counter)?;
+        let mut proof = Vec::new();
+        for layer in 0..LOG4_SIZE as usize {
+            let mut cs = cs.namespace(|| format!("layer {}", layer));
+            let mut sibling = |i: usize| {
+                alloc_scalar(
+                    cs.namespace(|| format!("sibling {}", i)),
+                    self.proof.as_ref().map(|p| p[layer][i]),
+                )
+            };
+            proof.push([sibling(0)?, sibling(1)?, sibling(2)?]);
+        }
+
+        let slot = poseidon(
+            cs.namespace(|| "slot"),
+            &[owner.x().clone(), owner.y().clone(), counter.clone()],
+        )?;
+        let root = merkle_root(cs.namespace(|| "root"), &slot, &index_bits, &proof)?;
+        enforce_equal_nums(cs.namespace(|| "prev state"), &inputs.prev_state, &root)?;
+
+        let message = poseidon(cs.namespace(|| "message"), &[index, counter.clone()])?;
+        let sig = AllocatedSignature::alloc(cs.namespace(|| "sig"), self.sig.as_ref())?;
+        verify_signature(cs.namespace(|| "verify"), &owner, &message, &sig)?;
+
+        let next_counter = AllocatedNum::alloc(cs.namespace(|| "next counter"), || {
+            counter
+                .get_value()
+                .map(|c| c + BellmanFr::one())
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        cs.enforce(
+            || "increment",
+            |lc| lc + counter.get_variable() + CS::one(),
+            |lc| lc + CS::one(),
+            |lc| lc + next_counter.get_variable(),
+        );
+        let next_slot = poseidon(
+            cs.namespace(|| "next slot"),
+            &[owner.x().clone(), owner.y().clone(), next_counter],
+        )?;
+        let next_root = merkle_root(
+            cs.namespace(|| "next root"),
+            &next_slot,
+            &index_bits,
+            &proof,
+        )?;
+        enforce_equal_nums(
+            cs.namespace(|| "next state"),
+            &inputs.next_state,
+            &next_root,
+        )
+    }
+}
+
+#[test]
+fn test_contract_proven_in_crate() -> Result<(), BlockchainError> {
+    let miner = Wallet::new(Vec::from("MINER"));
+    let alice = Wallet::new(Vec::from("ABC"));
+    let (owner_pk, owner_sk) = ZkSigner::generate_keys(b"OWNER");
+    let owner = owner_pk.0.decompress();
+    let mut chain = KvStoreChain::new(db::RamKvStore::new(), easy_config())?;
+    let params = Groth16Params::setup(BumpCircuit::default()).unwrap();
+
+    let state_model = COUNTERS_STATE_MODEL.clone();
+    let mut full_state = zk::ZkState {
+        rollbacks: vec![],
+        data: zk::ZkDataPairs(
+            [
+                (zk::ZkDataLocator(vec![1, 0]), owner.0),
+                (zk::ZkDataLocator(vec![1, 1]), owner.1),
+            ]
+            .into_iter()
+            .collect(),
+        ),
+    };
+    let tx = alice.create_contract(
+        zk::ZkContract {
+            state_model: state_model.clone(),
+            initial_state: state_model.compress::<ZkHasher>(&full_state.data)?,
+            payment_functions: Vec::new(),
+            functions: vec![zk::ZkVerifierKey::Groth16(Box::new(params.verifying_key()))],
+        },
+        full_state.data.clone(),
+        Money(0),
+        1,
+    );
+    let cid = ContractId::new(&tx.tx);
+    let draft = chain
+        .draft_block(1, &with_dummy_stats(&[tx]), &miner, false)?
+        .unwrap();
+    chain.apply_block(&draft.block, true)?;
+    chain.update_states(&draft.patch)?;
+
+    let mut fee_builder = zk::ZkStateBuilder::<ZkHasher>::new(zk::ZkStateModel::Scalar);
+    fee_builder.batch_set(&zk::ZkDeltaPairs(
+        [(zk::ZkDataLocator(vec![]), Some(zk::ZkScalar::from(0)))].into(),
+    ))?;
+    let aux_data = fee_builder.compress()?;
+
+    let counter = chain.read_state(cid, zk::ZkDataLocator(vec![1, 2]))?;
+    let delta = zk::ZkDeltaPairs(
+        [(
+            zk::ZkDataLocator(vec![1, 2]),
+            Some(counter + zk::ZkScalar::from(1)),
+        )]
+        .into(),
+    );
+    full_state.apply_delta(&delta);
+    let next_state = state_model.compress::<ZkHasher>(&full_state.data)?;
+
+    let account = chain.get_contract_account(cid)?;
+    let proof = chain
+        .prove_state(cid, zk::ZkDataLocator(vec![1]))?
+        .0
+        .chunks(3)
+        .map(|siblings| siblings.try_into().unwrap())
+        .collect::<Vec<_>>();
+    let proof = params
+        .prove(BumpCircuit {
+            prev_height: Some(account.height),
+            prev_state: Some(account.compressed_state.state_hash),
+            aux_data: Some(aux_data.state_hash),
+            next_state: Some(next_state.state_hash),
+            index: Some(1),
+            owner: Some(owner),
+            counter: Some(counter),
+            proof: Some(proof),
+            sig: Some(ZkSigner::sign(&owner_sk, bump_message(1, counter))),
+        })
+        .unwrap();
+    let bump = |delta: &zk::ZkDeltaPairs, next_state: zk::ZkCompressedState| {
+        alice.call_function(
+            cid,
+            0,
+            delta.clone(),
+            next_state,
+            zk::ZkProof::Groth16(Box::new(proof.clone())),
+            Money(0),
+            Money(0),
+            2,
+        )
+    };
+
+    // The proof doesn't hold for other transitions
+    let mut forged_state = full_state.clone();
+    let forged_delta = zk::ZkDeltaPairs(
+        [(
+            zk::ZkDataLocator(vec![1, 2]),
+            Some(counter + zk::ZkScalar::from(2)),
+        )]
+        .into(),
+    );
+    forged_state.apply_delta(&forged_delta);
+    let forged = bump(
+        &forged_delta,
+        state_model.compress::<ZkHasher>(&forged_state.data)?,
+    );
+    let err = chain.fork_on_ram().apply_tx(&forged.tx, false).unwrap_err();
+    assert!(matches!(
+        err.root_cause(),
+        BlockchainError::IncorrectZkProof
+    ));
+
+    let draft = chain
+        .draft_block(
+            2,
+            &with_dummy_stats(&[bump(&delta, next_state)]),
+            &miner,
+            false,
+        )?
+        .unwrap();
+    assert_eq!(draft.block.body.len(), 2);
+    chain.apply_block(&draft.block, true)?;
+    chain.update_states(&draft.patch)?;
+    assert_eq!(
+        chain.get_contract_account(cid)?.compressed_state,
+        next_state
+    );
+    assert_eq!(
+        chain.read_state(cid, zk::ZkDataLocator(vec![1, 2]))?,
+        zk::ZkScalar::from(1)
+    );
+    Ok(())
+}
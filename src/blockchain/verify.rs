@@ -0,0 +1,130 @@
+use super::*;
+use crate::db::{DbKey, ScanQuery};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Divergence {
+    /// The stored block is rejected by the consensus rules
+    InvalidBlock(String),
+    Header,
+    /// The state the block was applied to is different, i.e. the values
+    /// written by the block were different before it
+    PriorState,
+    Account(Address),
+    ContractAccount(ContractId),
+    /// The contract state does not match its compressed-state
+    ContractState(ContractId),
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Divergence::InvalidBlock(e) => write!(f, "block is invalid: {}", e),
+            Divergence::Header => write!(f, "header is different"),
+            Divergence::PriorState => write!(f, "block was applied on a different state"),
+            Divergence::Account(addr) => write!(f, "account {} is different", addr),
+            Divergence::ContractAccount(cid) => {
+                write!(f, "contract-account {} is different", cid)
+            }
+            Divergence::ContractState(cid) => {
+                write!(f, "state of contract {} does not match its root", cid)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChainDivergence {
+    pub height: u64,
+    pub divergence: Divergence,
+}
+
+// First key of the given prefix, whose value is different in the two stores
+fn first_different_key<K1: KvStore, K2: KvStore>(
+    a: &K1,
+    b: &K2,
+    prefix: DbKey,
+) -> Result<Option<DbKey>, BlockchainError> {
+    let mut a = a.scan(ScanQuery::prefix(prefix.clone()))?;
+    let mut b = b.scan(ScanQuery::prefix(prefix))?;
+    let (mut pa, mut pb) = (a.next().transpose()?, b.next().transpose()?);
+    loop {
+        match (&pa, &pb) {
+            (None, None) => {
+                return Ok(None);
+            }
+            (Some((k, _)), None) | (None, Some((k, _))) => {
+                return Ok(Some(k.clone()));
+            }
+            (Some((ka, va)), Some((kb, vb))) => {
+                if ka != kb {
+                    return Ok(Some(std::cmp::min(ka, kb).clone()));
+                } else if va != vb {
+                    return Ok(Some(ka.clone()));
+                }
+            }
+        }
+        pa = a.next().transpose()?;
+        pb = b.next().transpose()?;
+    }
+}
+
+/// Replays the blocks of `chain` on a fresh chain backed by `store`, with
+/// proof-of-work checks, and compares the results with the stored ones.
+/// Returns the first height where `chain` diverges from the replay.
+pub fn verify_chain<K: KvStore, R: KvStore, F: FnMut(u64)>(
+    chain: &KvStoreChain<K>,
+    store: R,
+    mut progress: F,
+) -> Result<Option<ChainDivergence>, BlockchainError> {
+    let diverged = |height, divergence| Ok(Some(ChainDivergence { height, divergence }));
+    let mut replay = KvStoreChain::new(store, chain.config.clone())?;
+    let height = chain.get_height()?;
+    for index in 0..height {
+        if index > 0 {
+            let block = chain.get_block(index)?;
+            if let Err(e) = replay.apply_block(&block, true) {
+                return diverged(index, Divergence::InvalidBlock(e.to_string()));
+            }
+        }
+        if replay.get_header(index)? != chain.get_header(index)? {
+            return diverged(index, Divergence::Header);
+        }
+        if replay.database.get(keys::rollback(index))?
+            != chain.database.get(keys::rollback(index))?
+        {
+            return diverged(index, Divergence::PriorState);
+        }
+        progress(index);
+    }
+
+    // Changes not made by any block can only be noticed in the final state
+    let tip = height.saturating_sub(1);
+    if let Some(k) = first_different_key(&replay.database, &chain.database, keys::account_prefix())?
+    {
+        let addr = keys::account_address(&k).ok_or(BlockchainError::Inconsistency)?;
+        return diverged(tip, Divergence::Account(addr));
+    }
+    if let Some(k) = first_different_key(
+        &replay.database,
+        &chain.database,
+        keys::contract_account_prefix(),
+    )? {
+        let cid = keys::contract_account_id(&k).ok_or(BlockchainError::Inconsistency)?;
+        return diverged(tip, Divergence::ContractAccount(cid));
+    }
+    let outdated_contracts = chain.get_outdated_contracts()?;
+    for item in chain
+        .database
+        .scan(ScanQuery::prefix(keys::contract_account_prefix()))?
+    {
+        let (k, _) = item?;
+        let cid = keys::contract_account_id(&k).ok_or(BlockchainError::Inconsistency)?;
+        if !outdated_contracts.contains(&cid)
+            && zk::KvStoreStateManager::<ZkHasher>::root(&chain.database, cid)?
+                != chain.get_contract_account(cid)?.compressed_state
+        {
+            return diverged(tip, Divergence::ContractState(cid));
+        }
+    }
+    Ok(None)
+}
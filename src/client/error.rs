@@ -1,4 +1,4 @@
-use crate::blockchain::BlockchainError;
+use crate::blockchain::{BlockchainError, BlockchainErrorInfo};
 use crate::zk::ZkError;
 use thiserror::Error;
 
@@ -12,6 +12,8 @@ pub enum NodeError {
     NodeIsClientOnly,
     #[error("blockchain error happened: {0}")]
     BlockchainError(#[from] BlockchainError),
+    #[error("kvstore error happened: {0}")]
+    KvStoreError(#[from] crate::db::KvStoreError),
     #[error("server error happened: {0}")]
     ServerError(#[from] hyper::Error),
     #[error("client error happened: {0}")]
@@ -34,6 +36,8 @@ pub enum NodeError {
     NoWalletError,
     #[error("no block is currently being mined")]
     NoCurrentlyMiningBlockError,
+    #[error("background task failed: {0}")]
+    JoinError(#[from] tokio::task::JoinError),
     #[error("timeout reached: {0}")]
     TimeoutError(#[from] tokio::time::error::Elapsed),
     #[error("http body size limit error")]
@@ -54,4 +58,44 @@ pub enum NodeError {
     HandshakeClientMismatch,
     #[error("remote server error: {0}")]
     RemoteServerError(String),
+    #[error("remote blockchain error: {}", .0.message)]
+    RemoteBlockchainError(BlockchainErrorInfo),
+    #[error("chain-spec error: {0}")]
+    ChainSpecError(#[from] crate::config::chain_spec::ChainSpecError),
+    #[error("io error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("chain-file error: {0}")]
+    ChainFileError(#[from] crate::blockchain::ChainFileError),
+    #[error("groth16 error: {0}")]
+    Groth16Error(#[from] crate::zk::groth16::Groth16Error),
+    #[error("cannot parse contract id: {0}")]
+    ContractIdParseError(#[from] crate::core::ParseContractIdError),
+    #[error("cannot parse data locator: {0}")]
+    LocatorParseError(#[from] crate::zk::ParseZkDataLocatorError),
+    #[error("cannot parse zk public key: {0}")]
+    ZkPublicKeyParseError(#[from] crate::crypto::jubjub::ParsePublicKeyError),
+    #[error("node is not running the regtest network")]
+    RegtestNotEnabled,
+    #[error("no nonce meets the target after {0} attempts")]
+    RegtestNonceNotFound(u64),
+}
+
+impl NodeError {
+    /// Height of the block the error happened in, when caused by a block
+    /// rejected by the local or a remote blockchain
+    pub fn block_height(&self) -> Option<u64> {
+        match self {
+            NodeError::BlockchainError(e) => e.block_height(),
+            NodeError::RemoteBlockchainError(info) => info.block_height,
+            _ => None,
+        }
+    }
+    /// Index of the transaction the error happened in, within its block
+    pub fn transaction_index(&self) -> Option<usize> {
+        match self {
+            NodeError::BlockchainError(e) => e.transaction_index(),
+            NodeError::RemoteBlockchainError(info) => info.transaction_index,
+            _ => None,
+        }
+    }
 }
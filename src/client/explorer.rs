@@ -4,10 +4,11 @@ use crate::core::{
 };
 use crate::crypto::jubjub::*;
 use crate::zk::{
-    MpnAccount, ZkCompressedState, ZkContract, ZkPaymentVerifierKey, ZkProof, ZkStateModel,
-    ZkVerifierKey,
+    MpnAccount, ZkCompressedState, ZkContract, ZkPaymentVerifierKey, ZkProof, ZkScalar, ZkState,
+    ZkStateModel, ZkVerifierKey,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ExplorerMpnAccount {
@@ -131,6 +132,24 @@ impl From<&ZkCompressedState> for ExplorerCompressedState {
     }
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ExplorerContractState {
+    pub data: HashMap<String, ZkScalar>,
+}
+
+impl From<&ZkState> for ExplorerContractState {
+    fn from(obj: &ZkState) -> Self {
+        Self {
+            data: obj
+                .data
+                .0
+                .iter()
+                .map(|(loc, val)| (loc.to_string(), *val))
+                .collect(),
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub enum ExplorerPaymentDirection {
     Deposit,
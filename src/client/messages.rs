@@ -1,13 +1,16 @@
-use crate::blockchain::ZkBlockchainPatch;
+use crate::blockchain::{
+    BlockchainErrorInfo, MpnPaymentBatch, TransactionSimulation, ZkBlockchainPatch,
+};
 use crate::consensus::pow::Difficulty;
 use crate::core::{
     Account, Address, Block, ContractId, Header, Money, MpnPayment, TransactionAndDelta,
 };
+use crate::db::CacheStats;
 use crate::zk;
 use std::collections::HashMap;
 
 use super::{
-    explorer::{ExplorerBlock, ExplorerMpnAccount},
+    explorer::{ExplorerBlock, ExplorerContractState, ExplorerMpnAccount},
     Peer, PeerAddress,
 };
 use serde::{Deserialize, Serialize};
@@ -52,6 +55,17 @@ pub struct GetMpnAccountResponse {
     pub account: zk::MpnAccount,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GetMpnAccountIndexRequest {
+    pub address: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GetMpnAccountIndexResponse {
+    pub index: u32,
+    pub account: zk::MpnAccount,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct GetExplorerMpnAccountsRequest {
     pub page: usize,
@@ -63,6 +77,45 @@ pub struct GetExplorerMpnAccountsResponse {
     pub accounts: HashMap<u32, ExplorerMpnAccount>,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GetExplorerContractStateRequest {
+    pub contract_id: String,
+    pub locator: String,
+    pub height: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GetExplorerContractStateResponse {
+    pub value: zk::ZkScalar,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GetExplorerContractFullStateRequest {
+    pub contract_id: String,
+    pub height: u64,
+    pub page: usize,
+    pub page_size: usize,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GetExplorerContractFullStateResponse {
+    pub state: ExplorerContractState,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GetContractStateProofRequest {
+    pub contract: String,
+    pub locator: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GetContractStateProofResponse {
+    pub value: zk::ZkScalar,
+    pub proof: zk::ZkStateProof,
+    pub height: u64,
+    pub state: zk::ZkCompressedState,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct PostMinerSolutionRequest {
     pub nonce: String,
@@ -189,6 +242,16 @@ pub struct TransactRequest {
 #[derive(Deserialize, Serialize, Debug)]
 pub struct TransactResponse {}
 
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SimulateTransactionRequest {
+    pub tx_delta: TransactionAndDelta,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SimulateTransactionResponse {
+    pub result: Result<TransactionSimulation, BlockchainErrorInfo>,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct PostMpnTransactionRequest {
     pub tx: zk::MpnTransaction,
@@ -197,6 +260,27 @@ pub struct PostMpnTransactionRequest {
 #[derive(Deserialize, Serialize, Debug)]
 pub struct PostMpnTransactionResponse {}
 
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RegtestGenerateRequest {
+    pub blocks: usize,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RegtestGenerateResponse {
+    pub headers: Vec<Header>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AdminBackupRequest {
+    pub path: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AdminBackupResponse {
+    pub entries: usize,
+    pub db_checksum: String,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ShutdownRequest {}
 
@@ -212,6 +296,17 @@ pub struct GetZeroMempoolResponse {
     pub payments: Vec<MpnPayment>,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GetMpnPaymentBatchRequest {
+    pub executor: Address,
+    pub circuit_id: u32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GetMpnPaymentBatchResponse {
+    pub batch: MpnPaymentBatch,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct PostMpnPaymentRequest {
     pub tx: MpnPayment,
@@ -237,4 +332,5 @@ pub struct GetDebugDataRequest {}
 pub struct GetDebugDataResponse {
     pub logs: String,
     pub db_checksum: String,
+    pub db_cache: Option<CacheStats>,
 }
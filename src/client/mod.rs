@@ -1,7 +1,9 @@
-use crate::core::{Address, MpnPayment, Signer, TransactionAndDelta};
+use crate::blockchain::BlockchainErrorInfo;
+use crate::core::{Address, ContractId, MpnPayment, Signer, TransactionAndDelta};
 use crate::crypto::ed25519;
+use crate::crypto::jubjub;
 use crate::crypto::SignatureScheme;
-use crate::zk::MpnTransaction;
+use crate::zk::{self, MpnTransaction};
 use hyper::body::{Bytes, HttpBody};
 use hyper::header::HeaderValue;
 use hyper::{Body, Method, Request, Response, StatusCode};
@@ -116,9 +118,15 @@ impl OutgoingSender {
         let body_bytes = hyper::body::to_bytes(body).await?;
 
         if status != StatusCode::OK {
-            return Err(NodeError::RemoteServerError(
-                String::from_utf8_lossy(&body_bytes).to_string(),
-            ));
+            // Blockchain errors are answered with their context
+            return Err(
+                match serde_json::from_slice::<BlockchainErrorInfo>(&body_bytes) {
+                    Ok(info) => NodeError::RemoteBlockchainError(info),
+                    Err(_) => NodeError::RemoteServerError(
+                        String::from_utf8_lossy(&body_bytes).to_string(),
+                    ),
+                },
+            );
         }
 
         Ok(body_bytes)
@@ -264,6 +272,33 @@ impl BazukaClient {
             .await?;
         Ok(())
     }
+    pub async fn regtest_generate(
+        &self,
+        blocks: usize,
+    ) -> Result<RegtestGenerateResponse, NodeError> {
+        let req = RegtestGenerateRequest { blocks };
+        self.sender
+            .json_post::<RegtestGenerateRequest, RegtestGenerateResponse>(
+                format!(
+                    "{}/regtest/generate?{}",
+                    self.peer,
+                    serde_qs::to_string(&req)?
+                ),
+                req,
+                Limit::default(),
+            )
+            .await
+    }
+    pub async fn admin_backup(&self, path: String) -> Result<AdminBackupResponse, NodeError> {
+        let req = AdminBackupRequest { path };
+        self.sender
+            .json_post::<AdminBackupRequest, AdminBackupResponse>(
+                format!("{}/admin/backup?{}", self.peer, serde_qs::to_string(&req)?),
+                req,
+                Limit::default(),
+            )
+            .await
+    }
     pub async fn stats(&self) -> Result<GetStatsResponse, NodeError> {
         self.sender
             .json_get::<GetStatsRequest, GetStatsResponse>(
@@ -273,6 +308,15 @@ impl BazukaClient {
             )
             .await
     }
+    pub async fn debug_data(&self) -> Result<GetDebugDataResponse, NodeError> {
+        self.sender
+            .json_get::<GetDebugDataRequest, GetDebugDataResponse>(
+                format!("{}/debug", self.peer),
+                GetDebugDataRequest {},
+                Limit::default(),
+            )
+            .await
+    }
     pub async fn peers(&self) -> Result<GetPeersResponse, NodeError> {
         self.sender
             .json_get::<GetPeersRequest, GetPeersResponse>(
@@ -293,6 +337,23 @@ impl BazukaClient {
             .await
     }
 
+    pub async fn get_mpn_payment_batch(
+        &self,
+        executor: Address,
+        circuit_id: u32,
+    ) -> Result<GetMpnPaymentBatchResponse, NodeError> {
+        self.sender
+            .bincode_get::<GetMpnPaymentBatchRequest, GetMpnPaymentBatchResponse>(
+                format!("{}/bincode/mpn/payments/batch", self.peer),
+                GetMpnPaymentBatchRequest {
+                    executor,
+                    circuit_id,
+                },
+                Limit::default(),
+            )
+            .await
+    }
+
     pub async fn transact_contract_payment(
         &self,
         tx: MpnPayment,
@@ -316,6 +377,23 @@ impl BazukaClient {
             .await
     }
 
+    pub async fn get_contract_state_proof(
+        &self,
+        contract_id: ContractId,
+        locator: zk::ZkDataLocator,
+    ) -> Result<GetContractStateProofResponse, NodeError> {
+        self.sender
+            .json_get::<GetContractStateProofRequest, GetContractStateProofResponse>(
+                format!("{}/contract/state/proof", self.peer),
+                GetContractStateProofRequest {
+                    contract: contract_id.to_string(),
+                    locator: locator.to_string(),
+                },
+                Limit::default(),
+            )
+            .await
+    }
+
     pub async fn get_account(&self, address: Address) -> Result<GetAccountResponse, NodeError> {
         self.sender
             .json_get::<GetAccountRequest, GetAccountResponse>(
@@ -338,6 +416,21 @@ impl BazukaClient {
             .await
     }
 
+    pub async fn get_mpn_account_index(
+        &self,
+        address: jubjub::PublicKey,
+    ) -> Result<GetMpnAccountIndexResponse, NodeError> {
+        self.sender
+            .json_get::<GetMpnAccountIndexRequest, GetMpnAccountIndexResponse>(
+                format!("{}/mpn/account/index", self.peer),
+                GetMpnAccountIndexRequest {
+                    address: address.to_string(),
+                },
+                Limit::default(),
+            )
+            .await
+    }
+
     pub async fn transact(
         &self,
         tx_delta: TransactionAndDelta,
@@ -351,6 +444,19 @@ impl BazukaClient {
             .await
     }
 
+    pub async fn simulate_transaction(
+        &self,
+        tx_delta: TransactionAndDelta,
+    ) -> Result<SimulateTransactionResponse, NodeError> {
+        self.sender
+            .bincode_post::<SimulateTransactionRequest, SimulateTransactionResponse>(
+                format!("{}/bincode/transact/simulate", self.peer),
+                SimulateTransactionRequest { tx_delta },
+                Limit::default(),
+            )
+            .await
+    }
+
     pub async fn zero_transact(
         &self,
         tx: MpnTransaction,
@@ -1,6 +1,6 @@
 use super::UNIT;
 
-use crate::blockchain::{BlockAndPatch, BlockchainConfig, ZkBlockchainPatch};
+use crate::blockchain::{BlockAndPatch, BlockchainConfig, ForkSchedule, ZkBlockchainPatch};
 use crate::common::*;
 use crate::consensus::pow::Difficulty;
 use crate::core::{
@@ -27,14 +27,7 @@ lazy_static! {
 fn get_mpn_contract() -> TransactionAndDelta {
     let mpn_state_model = zk::ZkStateModel::List {
         log4_size: MPN_LOG4_ACCOUNT_CAPACITY,
-        item_type: Box::new(zk::ZkStateModel::Struct {
-            field_types: vec![
-                zk::ZkStateModel::Scalar, // Nonce
-                zk::ZkStateModel::Scalar, // Pub-key X
-                zk::ZkStateModel::Scalar, // Pub-key Y
-                zk::ZkStateModel::Scalar, // Balance
-            ],
-        }),
+        item_type: Box::new(zk::MPN_ACCOUNT_STATE_MODEL.clone()),
     };
     let mpn_contract = zk::ZkContract {
         state_model: mpn_state_model.clone(),
@@ -136,7 +129,7 @@ pub fn get_blockchain_config() -> BlockchainConfig {
         // 2112 4159 -> hash(blk#2048)
         // 4160 6207 -> hash(blk#4096)
         // ...
-        pow_base_key: b"BAZUKA BASE KEY",
+        pow_base_key: b"BAZUKA BASE KEY".to_vec(),
         pow_key_change_delay: 64,      // Blocks
         pow_key_change_interval: 2048, // Blocks
 
@@ -152,9 +145,32 @@ pub fn get_blockchain_config() -> BlockchainConfig {
         minimum_pow_difficulty: min_diff,
 
         testnet_height_limit: Some(TESTNET_HEIGHT_LIMIT),
+
+        fork_schedule: ForkSchedule::default(),
+
+        regtest: false,
+
+        // No ceremony is pinned yet, PLONK contracts can't be deployed
+        plonk_srs_x_g2: None,
     }
 }
 
+/// Local network for integration tests, blocks can be generated on demand
+/// since the difficulty stays at a target any nonce meets.
+pub fn get_regtest_blockchain_config() -> BlockchainConfig {
+    let min_diff = Difficulty(0x00ffffff);
+
+    let mut conf = get_blockchain_config();
+    conf.limited_miners = None;
+    conf.mpn_num_contract_payments = 0;
+    conf.mpn_num_function_calls = 0;
+    conf.minimum_pow_difficulty = min_diff;
+    conf.genesis.block.header.proof_of_work.target = min_diff;
+    conf.testnet_height_limit = None;
+    conf.regtest = true;
+    conf
+}
+
 #[cfg(test)]
 pub fn get_test_blockchain_config() -> BlockchainConfig {
     let mpn_tx_delta = get_test_mpn_contract();
@@ -0,0 +1,330 @@
+use crate::blockchain::{BlockAndPatch, BlockchainConfig, ForkSchedule, ZkBlockchainPatch};
+use crate::consensus::pow::Difficulty;
+use crate::core::{
+    Address, Block, ContractId, Header, Money, ProofOfWork, Signature, Transaction, TransactionData,
+};
+use crate::zk;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ChainSpecError {
+    #[error("io error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("yaml error: {0}")]
+    YamlError(#[from] serde_yaml::Error),
+    #[error("json error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("hex error: {0}")]
+    HexError(#[from] hex::FromHexError),
+    #[error("bincode error: {0}")]
+    BincodeError(#[from] bincode::Error),
+    #[error("invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("genesis has no contracts, the first contract is used as the MPN contract")]
+    NoMpnContract,
+    #[error("genesis transaction #{0} cannot be represented in a chain-spec")]
+    UnsupportedGenesisTransaction(usize),
+}
+
+/// A contract created in the genesis block. `contract` and `state` are
+/// hex-encoded bincode serializations of `ZkContract` and `ZkDeltaPairs`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GenesisContract {
+    pub contract: String,
+    pub state: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GenesisAllocation {
+    pub address: String,
+    pub amount: Money,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GenesisSpec {
+    pub timestamp: u32,
+    pub difficulty: Difficulty,
+    // The first contract is considered as the MPN contract
+    pub contracts: Vec<GenesisContract>,
+    pub allocations: Vec<GenesisAllocation>,
+}
+
+/// Human-editable description of a network, convertible to a `BlockchainConfig`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChainSpec {
+    pub network: String,
+    pub limited_miners: Option<Vec<String>>,
+    pub total_supply: Money,
+    pub reward_ratio: u64,
+    pub max_block_size: usize,
+    pub max_delta_count: usize,
+    pub block_time: usize,
+    pub difficulty_calc_interval: u64,
+    // Hex-encoded, the key is not necessarily valid UTF-8
+    pub pow_base_key: String,
+    pub pow_key_change_delay: u64,
+    pub pow_key_change_interval: u64,
+    pub median_timestamp_count: u64,
+    pub mpn_num_function_calls: usize,
+    pub mpn_num_contract_payments: usize,
+    pub minimum_pow_difficulty: Difficulty,
+    pub testnet_height_limit: Option<u64>,
+    #[serde(default)]
+    pub fork_schedule: ForkSchedule,
+    #[serde(default)]
+    pub regtest: bool,
+    // Hex-encoded compressed `[x]₂` of the PLONK ceremony
+    #[serde(default)]
+    pub plonk_srs_x_g2: Option<String>,
+    pub genesis: GenesisSpec,
+}
+
+fn parse_address(s: &str) -> Result<Address, ChainSpecError> {
+    s.parse()
+        .map_err(|_| ChainSpecError::InvalidAddress(s.into()))
+}
+
+impl ChainSpec {
+    /// Other network names than `regtest` get the mainnet config, labelled
+    /// with the given name, as they always did.
+    pub fn builtin(network: &str) -> Result<Self, ChainSpecError> {
+        match network {
+            "regtest" => {
+                Self::from_config(network, &super::blockchain::get_regtest_blockchain_config())
+            }
+            _ => Self::from_config(network, &super::blockchain::get_blockchain_config()),
+        }
+    }
+
+    /// Reads a chain-spec file, files with `.json` extension are parsed as
+    /// JSON and everything else as YAML.
+    pub fn load(path: &Path) -> Result<Self, ChainSpecError> {
+        let f = std::fs::File::open(path)?;
+        if path.extension().map(|e| e == "json").unwrap_or(false) {
+            Ok(serde_json::from_reader(f)?)
+        } else {
+            Ok(serde_yaml::from_reader(f)?)
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, ChainSpecError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn to_yaml(&self) -> Result<String, ChainSpecError> {
+        Ok(serde_yaml::to_string(self)?)
+    }
+
+    pub fn from_config(network: &str, conf: &BlockchainConfig) -> Result<Self, ChainSpecError> {
+        let mut contracts = Vec::new();
+        let mut allocations = Vec::new();
+        for (i, tx) in conf.genesis.block.body.iter().enumerate() {
+            if tx.src != Address::Treasury
+                || tx.nonce as usize != i + 1
+                || tx.fee != Money(0)
+                || tx.sig != Signature::Unsigned
+            {
+                return Err(ChainSpecError::UnsupportedGenesisTransaction(i));
+            }
+            match &tx.data {
+                TransactionData::CreateContract { contract } if allocations.is_empty() => {
+                    let state = match conf.genesis.patch.patches.get(&ContractId::new(tx)) {
+                        Some(zk::ZkStatePatch::Delta(delta)) => delta.clone(),
+                        None => zk::ZkDeltaPairs::default(),
+                        _ => {
+                            return Err(ChainSpecError::UnsupportedGenesisTransaction(i));
+                        }
+                    };
+                    contracts.push(GenesisContract {
+                        contract: hex::encode(bincode::serialize(contract)?),
+                        state: hex::encode(bincode::serialize(&state)?),
+                    });
+                }
+                TransactionData::RegularSend { dst, amount } => {
+                    allocations.push(GenesisAllocation {
+                        address: dst.to_string(),
+                        amount: *amount,
+                    });
+                }
+                _ => {
+                    return Err(ChainSpecError::UnsupportedGenesisTransaction(i));
+                }
+            }
+        }
+        Ok(Self {
+            network: network.into(),
+            limited_miners: conf
+                .limited_miners
+                .as_ref()
+                .map(|miners| miners.iter().map(|m| m.to_string()).collect()),
+            total_supply: conf.total_supply,
+            reward_ratio: conf.reward_ratio,
+            max_block_size: conf.max_block_size,
+            max_delta_count: conf.max_delta_count,
+            block_time: conf.block_time,
+            difficulty_calc_interval: conf.difficulty_calc_interval,
+            pow_base_key: hex::encode(&conf.pow_base_key),
+            pow_key_change_delay: conf.pow_key_change_delay,
+            pow_key_change_interval: conf.pow_key_change_interval,
+            median_timestamp_count: conf.median_timestamp_count,
+            mpn_num_function_calls: conf.mpn_num_function_calls,
+            mpn_num_contract_payments: conf.mpn_num_contract_payments,
+            minimum_pow_difficulty: conf.minimum_pow_difficulty,
+            testnet_height_limit: conf.testnet_height_limit,
+            fork_schedule: conf.fork_schedule.clone(),
+            regtest: conf.regtest,
+            plonk_srs_x_g2: conf.plonk_srs_x_g2.as_ref().map(hex::encode),
+            genesis: GenesisSpec {
+                timestamp: conf.genesis.block.header.proof_of_work.timestamp,
+                difficulty: conf.genesis.block.header.proof_of_work.target,
+                contracts,
+                allocations,
+            },
+        })
+    }
+
+    pub fn to_config(&self) -> Result<BlockchainConfig, ChainSpecError> {
+        let mut body = Vec::new();
+        let mut patches = std::collections::HashMap::new();
+        for contract in self.genesis.contracts.iter() {
+            let tx = Transaction {
+                src: Address::Treasury,
+                data: TransactionData::CreateContract {
+                    contract: bincode::deserialize(&hex::decode(&contract.contract)?)?,
+                },
+                nonce: body.len() as u32 + 1,
+                fee: Money(0),
+                sig: Signature::Unsigned,
+            };
+            let state: zk::ZkDeltaPairs = bincode::deserialize(&hex::decode(&contract.state)?)?;
+            patches.insert(ContractId::new(&tx), zk::ZkStatePatch::Delta(state));
+            body.push(tx);
+        }
+        let mpn_contract_id = body
+            .first()
+            .map(ContractId::new)
+            .ok_or(ChainSpecError::NoMpnContract)?;
+        for alloc in self.genesis.allocations.iter() {
+            body.push(Transaction {
+                src: Address::Treasury,
+                data: TransactionData::RegularSend {
+                    dst: parse_address(&alloc.address)?,
+                    amount: alloc.amount,
+                },
+                nonce: body.len() as u32 + 1,
+                fee: Money(0),
+                sig: Signature::Unsigned,
+            });
+        }
+
+        Ok(BlockchainConfig {
+            limited_miners: self
+                .limited_miners
+                .as_ref()
+                .map(|miners| miners.iter().map(|m| parse_address(m)).collect())
+                .transpose()?,
+            genesis: BlockAndPatch {
+                block: Block {
+                    header: Header {
+                        parent_hash: Default::default(),
+                        number: 0,
+                        block_root: Default::default(),
+                        proof_of_work: ProofOfWork {
+                            timestamp: self.genesis.timestamp,
+                            target: self.genesis.difficulty,
+                            nonce: 0,
+                        },
+                    },
+                    body,
+                },
+                patch: ZkBlockchainPatch { patches },
+            },
+            total_supply: self.total_supply,
+            reward_ratio: self.reward_ratio,
+            max_block_size: self.max_block_size,
+            max_delta_count: self.max_delta_count,
+            block_time: self.block_time,
+            difficulty_calc_interval: self.difficulty_calc_interval,
+            pow_base_key: hex::decode(&self.pow_base_key)?,
+            pow_key_change_delay: self.pow_key_change_delay,
+            pow_key_change_interval: self.pow_key_change_interval,
+            median_timestamp_count: self.median_timestamp_count,
+            mpn_contract_id,
+            mpn_num_function_calls: self.mpn_num_function_calls,
+            mpn_num_contract_payments: self.mpn_num_contract_payments,
+            minimum_pow_difficulty: self.minimum_pow_difficulty,
+            testnet_height_limit: self.testnet_height_limit,
+            fork_schedule: self.fork_schedule.clone(),
+            regtest: self.regtest,
+            plonk_srs_x_g2: self.plonk_srs_x_g2.as_ref().map(hex::decode).transpose()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::blockchain::{get_blockchain_config, get_test_blockchain_config};
+
+    fn assert_same_config(a: &BlockchainConfig, b: &BlockchainConfig) {
+        assert_eq!(a.genesis.block.header.hash(), b.genesis.block.header.hash());
+        assert_eq!(a.genesis.block.body, b.genesis.block.body);
+        assert_eq!(
+            bincode::serialize(&a.genesis.patch.patches.iter().collect::<Vec<_>>()).unwrap(),
+            bincode::serialize(&b.genesis.patch.patches.iter().collect::<Vec<_>>()).unwrap()
+        );
+        assert_eq!(a.mpn_contract_id, b.mpn_contract_id);
+        assert_eq!(a.limited_miners, b.limited_miners);
+        assert_eq!(a.pow_base_key, b.pow_base_key);
+        assert_eq!(a.total_supply, b.total_supply);
+        assert_eq!(a.testnet_height_limit, b.testnet_height_limit);
+        assert_eq!(a.fork_schedule, b.fork_schedule);
+        assert_eq!(a.regtest, b.regtest);
+        assert_eq!(a.plonk_srs_x_g2, b.plonk_srs_x_g2);
+    }
+
+    #[test]
+    fn test_mainnet_chain_spec_roundtrip() {
+        let spec = ChainSpec::builtin("mainnet").unwrap();
+        let from_yaml: ChainSpec = serde_yaml::from_str(&spec.to_yaml().unwrap()).unwrap();
+        let from_json: ChainSpec = serde_json::from_str(&spec.to_json().unwrap()).unwrap();
+        assert_same_config(&get_blockchain_config(), &from_yaml.to_config().unwrap());
+        assert_same_config(&get_blockchain_config(), &from_json.to_config().unwrap());
+        let regtest = ChainSpec::builtin("regtest").unwrap().to_config().unwrap();
+        assert!(regtest.limited_miners.is_none());
+        assert!(regtest.regtest);
+        let custom = ChainSpec::builtin("custom").unwrap();
+        assert_eq!(custom.network, "custom");
+        assert_same_config(&get_blockchain_config(), &custom.to_config().unwrap());
+    }
+
+    #[test]
+    fn test_chain_spec_genesis_allocations() {
+        let mut conf = get_test_blockchain_config();
+        conf.plonk_srs_x_g2 = Some(vec![0xab; 96]);
+        let spec = ChainSpec::from_config("devnet", &conf).unwrap();
+        assert_eq!(spec.genesis.contracts.len(), 1);
+        assert_eq!(spec.genesis.allocations.len(), 1);
+        assert_eq!(spec.genesis.allocations[0].amount, Money(10000));
+        assert_same_config(&conf, &spec.to_config().unwrap());
+    }
+
+    #[test]
+    fn test_chain_spec_binary_pow_key() {
+        let mut conf = get_test_blockchain_config();
+        conf.pow_base_key = vec![0xff, 0x00, 0xc3, 0x28];
+        let spec = ChainSpec::from_config("devnet", &conf).unwrap();
+        assert_eq!(spec.pow_base_key, "ff00c328");
+        let from_yaml: ChainSpec = serde_yaml::from_str(&spec.to_yaml().unwrap()).unwrap();
+        assert_same_config(&conf, &from_yaml.to_config().unwrap());
+
+        let mut invalid = spec;
+        invalid.pow_base_key = "not hex".into();
+        assert!(matches!(
+            invalid.to_config(),
+            Err(ChainSpecError::HexError(_))
+        ));
+    }
+}
@@ -1,6 +1,9 @@
 pub mod blockchain;
 mod chaos;
 
+#[cfg(feature = "client")]
+pub mod chain_spec;
+
 #[cfg(feature = "node")]
 pub mod node;
 
@@ -31,5 +31,6 @@ pub type Block = blocks::Block<Hasher, Signer, ZkSigner>;
 
 pub type ProofOfWork = header::ProofOfWork;
 pub type ContractId = transaction::ContractId<Hasher>;
+pub use transaction::ParseContractIdError;
 
 pub type TransactionAndDelta = transaction::TransactionAndDelta<Hasher, Signer, ZkSigner>;
@@ -24,6 +24,12 @@ impl<H: Hash> ContractId<H> {
     }
 }
 
+impl<H: Hash> AsRef<[u8]> for ContractId<H> {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
 impl<H: Hash> std::fmt::Display for ContractId<H> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{}", hex::encode(self.0))
@@ -34,7 +40,15 @@ impl<H: Hash> FromStr for ContractId<H> {
     type Err = ParseContractIdError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let bytes = hex::decode(s).map_err(|_| ParseContractIdError::Invalid)?;
-        let hash_output = H::Output::try_from(bytes).map_err(|_| ParseContractIdError::Invalid)?;
+        Self::try_from(bytes.as_slice())
+    }
+}
+
+impl<H: Hash> TryFrom<&[u8]> for ContractId<H> {
+    type Error = ParseContractIdError;
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let hash_output =
+            H::Output::try_from(bytes.to_vec()).map_err(|_| ParseContractIdError::Invalid)?;
         Ok(Self(hash_output))
     }
 }
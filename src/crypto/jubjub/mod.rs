@@ -75,10 +75,8 @@ impl FromStr for PublicKey {
             .map_err(|_| ParsePublicKeyError::Invalid)?;
         let mut repr = ZkScalar::zero().to_repr();
         repr.as_mut().clone_from_slice(&bytes);
-        Ok(PublicKey(PointCompressed(
-            ZkScalar::from_repr(repr).unwrap(),
-            oddity,
-        )))
+        let x = Option::from(ZkScalar::from_repr(repr)).ok_or(ParsePublicKeyError::Invalid)?;
+        Ok(PublicKey(PointCompressed(x, oddity)))
     }
 }
 
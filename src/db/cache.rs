@@ -0,0 +1,144 @@
+use super::*;
+use std::sync::Mutex;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub entries: usize,
+    pub size: u64,
+    pub capacity: u64,
+}
+
+struct CacheEntry {
+    value: Option<Blob>,
+    last_used: u64,
+}
+
+fn entry_size(k: &DbKey, v: &Option<Blob>) -> u64 {
+    (k.0.len() + v.as_ref().map(|b| b.0.len()).unwrap_or(0)) as u64
+}
+
+struct LruCache {
+    entries: HashMap<DbKey, CacheEntry>,
+    // Keys ordered by the last time they were used, least recent first
+    recency: BTreeMap<u64, DbKey>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl LruCache {
+    fn new(capacity: u64) -> Self {
+        Self {
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            stats: CacheStats {
+                capacity,
+                ..Default::default()
+            },
+        }
+    }
+    fn get(&mut self, k: &DbKey) -> Option<Option<Blob>> {
+        let entry = self.entries.get_mut(k)?;
+        self.tick += 1;
+        self.recency.remove(&entry.last_used);
+        self.recency.insert(self.tick, k.clone());
+        entry.last_used = self.tick;
+        Some(entry.value.clone())
+    }
+    fn insert(&mut self, k: DbKey, v: Option<Blob>) {
+        self.remove(&k);
+        let size = entry_size(&k, &v);
+        if size > self.stats.capacity {
+            return;
+        }
+        while self.stats.size + size > self.stats.capacity {
+            match self.recency.pop_first() {
+                Some((_, oldest)) => {
+                    self.remove(&oldest);
+                    self.stats.evictions += 1;
+                }
+                None => break,
+            }
+        }
+        self.tick += 1;
+        self.recency.insert(self.tick, k.clone());
+        self.stats.size += size;
+        self.entries.insert(
+            k,
+            CacheEntry {
+                value: v,
+                last_used: self.tick,
+            },
+        );
+    }
+    fn remove(&mut self, k: &DbKey) {
+        if let Some(entry) = self.entries.remove(k) {
+            self.recency.remove(&entry.last_used);
+            self.stats.size -= entry_size(k, &entry.value);
+        }
+    }
+}
+
+/// Keeps the most recently read values of a store in memory, evicting the
+/// least recently used ones once their total size exceeds `capacity` bytes.
+/// Absent keys are cached too. Scans are not cached.
+pub struct CachedKvStore<K: KvStore> {
+    store: K,
+    cache: Mutex<LruCache>,
+}
+
+impl<K: KvStore> CachedKvStore<K> {
+    pub fn new(store: K, capacity: u64) -> Self {
+        Self {
+            store,
+            cache: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+}
+
+impl<K: KvStore> KvStore for CachedKvStore<K> {
+    fn get(&self, k: DbKey) -> Result<Option<Blob>, KvStoreError> {
+        {
+            let mut cache = self.cache.lock().unwrap();
+            if let Some(v) = cache.get(&k) {
+                cache.stats.hits += 1;
+                return Ok(v);
+            }
+            cache.stats.misses += 1;
+        }
+        // Updates need a mutable reference, so the value can't change while
+        // it's being read without the lock.
+        let v = self.store.get(k.clone())?;
+        self.cache.lock().unwrap().insert(k, v.clone());
+        Ok(v)
+    }
+    fn update(&mut self, ops: &[WriteOp]) -> Result<(), KvStoreError> {
+        // Invalidate even if the update fails, the store may not be atomic
+        let cache = self.cache.get_mut().unwrap();
+        for op in ops.iter() {
+            match op {
+                WriteOp::Remove(k) | WriteOp::Put(k, _) => cache.remove(k),
+            }
+        }
+        self.store.update(ops)
+    }
+    fn scan(&self, query: ScanQuery) -> Result<KvIterator<'_>, KvStoreError> {
+        self.store.scan(query)
+    }
+    fn checksum(&self) -> Result<Checksum, KvStoreError> {
+        self.store.checksum()
+    }
+    fn backup_job(&self) -> Result<BackupJob, KvStoreError> {
+        self.store.backup_job()
+    }
+    fn cache_stats(&self) -> Option<CacheStats> {
+        let cache = self.cache.lock().unwrap();
+        Some(CacheStats {
+            entries: cache.entries.len(),
+            ..cache.stats.clone()
+        })
+    }
+}
@@ -0,0 +1,82 @@
+use super::*;
+
+/// Order-independent digest of the pairs of a store: the sum (mod 2^256) of
+/// the hashes of all key/value pairs. Writes add and subtract the digests of
+/// the pairs they touch, so it's maintained in O(1) per write.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checksum([u8; 32]);
+
+fn pair_digest(k: &DbKey, v: &Blob) -> [u8; 32] {
+    Hasher::hash(&bincode::serialize(&(k, v)).unwrap())
+}
+
+impl Checksum {
+    pub fn of_pairs<I: IntoIterator<Item = (DbKey, Blob)>>(pairs: I) -> Self {
+        let mut checksum = Self::default();
+        for (k, v) in pairs {
+            checksum.insert(&k, &v);
+        }
+        checksum
+    }
+    pub fn of_scan<I: IntoIterator<Item = Result<(DbKey, Blob), KvStoreError>>>(
+        pairs: I,
+    ) -> Result<Self, KvStoreError> {
+        let mut checksum = Self::default();
+        for pair in pairs {
+            let (k, v) = pair?;
+            checksum.insert(&k, &v);
+        }
+        Ok(checksum)
+    }
+    pub fn insert(&mut self, k: &DbKey, v: &Blob) {
+        let digest = pair_digest(k, v);
+        let mut carry = 0u16;
+        for i in (0..32).rev() {
+            let sum = self.0[i] as u16 + digest[i] as u16 + carry;
+            self.0[i] = sum as u8;
+            carry = sum >> 8;
+        }
+    }
+    pub fn remove(&mut self, k: &DbKey, v: &Blob) {
+        let digest = pair_digest(k, v);
+        let mut borrow = 0i16;
+        for i in (0..32).rev() {
+            let diff = self.0[i] as i16 - digest[i] as i16 - borrow;
+            self.0[i] = diff.rem_euclid(256) as u8;
+            borrow = (diff < 0) as i16;
+        }
+    }
+    /// Applies a batch of writes, `get` returns the values before the batch
+    pub fn update<F: Fn(&DbKey) -> Result<Option<Blob>, KvStoreError>>(
+        &mut self,
+        ops: &[WriteOp],
+        get: F,
+    ) -> Result<(), KvStoreError> {
+        // The same key may be written more than once in a batch
+        let mut written = HashMap::<&DbKey, Option<&Blob>>::new();
+        for op in ops.iter() {
+            let (k, v) = match op {
+                WriteOp::Remove(k) => (k, None),
+                WriteOp::Put(k, v) => (k, Some(v)),
+            };
+            let prev = match written.get(k) {
+                Some(prev) => prev.cloned(),
+                None => get(k)?,
+            };
+            if let Some(prev) = prev {
+                self.remove(k, &prev);
+            }
+            if let Some(v) = v {
+                self.insert(k, v);
+            }
+            written.insert(k, v);
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Checksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
@@ -6,19 +6,20 @@ use leveldb::database::{
     snapshots::{Snapshot, Snapshots},
     Database,
 };
-use leveldb::iterator::Iterable;
 use leveldb::iterator::LevelDBIterator;
+use leveldb::iterator::{Iterable, Iterator as LevelDbIterator};
 use leveldb::kv::KV;
 use leveldb::options::{Options, ReadOptions, WriteOptions};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tempdir::TempDir;
 
 pub struct ReadOnlyLevelDbKvStore {
     mirror_path: PathBuf,
-    db: Option<Database<StringKey>>,
+    db: Option<Database<DbKey>>,
 }
-pub struct LevelDbSnapshot<'a>(Snapshot<'a, StringKey>);
+pub struct LevelDbSnapshot<'a>(Snapshot<'a, DbKey>);
 impl ReadOnlyLevelDbKvStore {
     pub fn read_only(
         path: &Path,
@@ -50,19 +51,93 @@ impl Drop for ReadOnlyLevelDbKvStore {
     }
 }
 
-pub struct LevelDbKvStore(Database<StringKey>);
+// LevelDB has a single keyspace, so data about the store itself is kept under
+// keys starting with a byte no key of the blockchain starts with, hidden from
+// scans.
+const META_PREFIX: u8 = 0x00;
+
+fn checksum_key() -> DbKey {
+    DbKey(vec![META_PREFIX, b'C'])
+}
+
+fn stored_checksum<K: KvStore>(store: &K) -> Result<Option<Checksum>, KvStoreError> {
+    Ok(match store.get(checksum_key())? {
+        Some(v) => Some(bincode::deserialize(&v.0)?),
+        None => None,
+    })
+}
+
+fn leveldb_scan(it: LevelDbIterator<'_, DbKey>, query: ScanQuery) -> KvIterator<'_> {
+    let ScanQuery {
+        start,
+        end,
+        reverse,
+        limit,
+    } = query;
+    let it: KvIterator<'_> = if reverse {
+        let it = it.reverse();
+        if let Some(end) = &end {
+            it.seek(end);
+            if !it.valid() {
+                it.seek_to_last();
+            }
+        }
+        Box::new(
+            it.skip_while(move |(k, _)| end.as_ref().map(|e| k >= e).unwrap_or(false))
+                .take_while(move |(k, _)| start.as_ref().map(|s| k >= s).unwrap_or(true))
+                .filter(|(k, _)| k.0.first() != Some(&META_PREFIX))
+                .map(|(k, v)| Ok((k, Blob(v)))),
+        )
+    } else {
+        if let Some(start) = &start {
+            it.seek(start);
+        }
+        Box::new(
+            it.take_while(move |(k, _)| end.as_ref().map(|e| k < e).unwrap_or(true))
+                .filter(|(k, _)| k.0.first() != Some(&META_PREFIX))
+                .map(|(k, v)| Ok((k, Blob(v)))),
+        )
+    };
+    match limit {
+        Some(limit) => Box::new(it.take(limit)),
+        None => it,
+    }
+}
+
+pub struct LevelDbKvStore(Arc<Database<DbKey>>, Checksum);
 impl LevelDbKvStore {
     pub fn new(path: &Path, cache_size: usize) -> Result<LevelDbKvStore, KvStoreError> {
         fs::create_dir_all(&path)?;
         let mut options = Options::new();
         options.create_if_missing = true;
         options.cache = Some(Cache::new(cache_size));
-        Ok(LevelDbKvStore(Database::open(path, options)?))
+        let mut store = LevelDbKvStore(
+            Arc::new(Database::open(path, options)?),
+            Checksum::default(),
+        );
+        // The checksum is persisted along with each update, it's only
+        // calculated for databases created before it was.
+        match stored_checksum(&store)? {
+            Some(checksum) => store.1 = checksum,
+            None => {
+                let checksum = Checksum::of_scan(store.scan(ScanQuery::all())?)?;
+                store
+                    .0
+                    .put(
+                        WriteOptions::new(),
+                        checksum_key(),
+                        &bincode::serialize(&checksum)?,
+                    )
+                    .map_err(|_| KvStoreError::Failure)?;
+                store.1 = checksum;
+            }
+        }
+        Ok(store)
     }
 }
 
 impl KvStore for LevelDbKvStore {
-    fn get(&self, k: StringKey) -> Result<Option<Blob>, KvStoreError> {
+    fn get(&self, k: DbKey) -> Result<Option<Blob>, KvStoreError> {
         let read_opts = ReadOptions::new();
         match self.0.get(read_opts, k) {
             Ok(v) => Ok(v.map(Blob)),
@@ -70,6 +145,8 @@ impl KvStore for LevelDbKvStore {
         }
     }
     fn update(&mut self, ops: &[WriteOp]) -> Result<(), KvStoreError> {
+        let mut checksum = self.1;
+        checksum.update(ops, |k| self.get(k.clone()))?;
         let write_opts = WriteOptions::new();
         let mut batch = Writebatch::new();
         for op in ops.iter() {
@@ -78,25 +155,63 @@ impl KvStore for LevelDbKvStore {
                 WriteOp::Put(k, v) => batch.put(k.clone(), &v.0),
             }
         }
+        batch.put(checksum_key(), &bincode::serialize(&checksum)?);
         match self.0.write(write_opts, &batch) {
-            Ok(_) => Ok(()),
+            Ok(_) => {
+                self.1 = checksum;
+                Ok(())
+            }
             Err(_) => Err(KvStoreError::Failure),
         }
     }
-    fn pairs(&self, prefix: StringKey) -> Result<HashMap<StringKey, Blob>, KvStoreError> {
-        let it = self.0.iter(ReadOptions::new());
-        it.seek(&prefix);
-        Ok(it
-            .collect::<Vec<_>>()
-            .into_iter()
-            .take_while(|(k, _)| k.0.starts_with(&prefix.0))
-            .map(|(k, v)| (k, Blob(v)))
-            .collect())
+    fn scan(&self, query: ScanQuery) -> Result<KvIterator<'_>, KvStoreError> {
+        Ok(leveldb_scan(self.0.iter(ReadOptions::new()), query))
+    }
+    fn checksum(&self) -> Result<Checksum, KvStoreError> {
+        Ok(self.1)
+    }
+    fn backup_job(&self) -> Result<BackupJob, KvStoreError> {
+        let (snapshot, checksum) = (OwnedLevelDbSnapshot::new(&self.0), self.1);
+        Ok(Box::new(move |path: &Path| {
+            check_backup_destination(path)?;
+            backup_snapshot(&*snapshot, checksum, &mut LevelDbKvStore::new(path, 64)?)
+        }))
+    }
+}
+
+// A snapshot keeping its database open, so that it doesn't borrow the store
+struct OwnedLevelDbSnapshot {
+    // Declared first, so that it's released before the database is closed
+    snapshot: LevelDbSnapshot<'static>,
+    _db: Arc<Database<DbKey>>,
+}
+
+impl OwnedLevelDbSnapshot {
+    fn new(db: &Arc<Database<DbKey>>) -> Self {
+        let db = Arc::clone(db);
+        // SAFETY: the database lives on the heap as long as `_db` holds it,
+        // and the snapshot is dropped first
+        let snapshot = unsafe {
+            std::mem::transmute::<LevelDbSnapshot<'_>, LevelDbSnapshot<'static>>(LevelDbSnapshot(
+                db.snapshot(),
+            ))
+        };
+        Self { snapshot, _db: db }
     }
 }
 
+impl std::ops::Deref for OwnedLevelDbSnapshot {
+    type Target = LevelDbSnapshot<'static>;
+    fn deref(&self) -> &Self::Target {
+        &self.snapshot
+    }
+}
+
+// SAFETY: LevelDB snapshots are immutable, and safe to read from any thread
+unsafe impl Send for OwnedLevelDbSnapshot {}
+
 impl<'a> KvStore for LevelDbSnapshot<'a> {
-    fn get(&self, k: StringKey) -> Result<Option<Blob>, KvStoreError> {
+    fn get(&self, k: DbKey) -> Result<Option<Blob>, KvStoreError> {
         let read_opts = ReadOptions::new();
         match self.0.get(read_opts, k) {
             Ok(v) => Ok(v.map(Blob)),
@@ -106,17 +221,13 @@ impl<'a> KvStore for LevelDbSnapshot<'a> {
     fn update(&mut self, _: &[WriteOp]) -> Result<(), KvStoreError> {
         panic!("Cannot update!");
     }
-    fn pairs(&self, prefix: StringKey) -> Result<HashMap<StringKey, Blob>, KvStoreError> {
-        let it = self.0.iter(ReadOptions::new());
-        it.seek(&prefix);
-        let mut result = HashMap::new();
-        for (k, v) in it {
-            if k.0.starts_with(&prefix.0) {
-                result.insert(k, Blob(v));
-            } else {
-                break;
-            }
+    fn scan(&self, query: ScanQuery) -> Result<KvIterator<'_>, KvStoreError> {
+        Ok(leveldb_scan(self.0.iter(ReadOptions::new()), query))
+    }
+    fn checksum(&self) -> Result<Checksum, KvStoreError> {
+        match stored_checksum(self)? {
+            Some(checksum) => Ok(checksum),
+            None => Checksum::of_scan(self.scan(ScanQuery::all())?),
         }
-        Ok(result)
     }
 }
@@ -0,0 +1,104 @@
+use super::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// Return `KvStoreError::Failure` without touching the store
+    Error,
+    /// Panic without touching the store, like a process dying mid-write
+    Panic,
+}
+
+/// Wraps a store and injects a fault at the Nth (zero-based, counted from
+/// when the fault is set) call of `get` or `update`, or pair read by `scan`,
+/// for testing how callers cope with failing stores.
+pub struct FaultyKvStore<K: KvStore> {
+    store: K,
+    update_fault: Option<(usize, Fault)>,
+    get_fault: Option<(usize, Fault)>,
+    scan_fault: Option<(usize, Fault)>,
+    updates: usize,
+    gets: AtomicUsize,
+    scanned: AtomicUsize,
+}
+
+impl<K: KvStore> FaultyKvStore<K> {
+    pub fn new(store: K) -> Self {
+        Self {
+            store,
+            update_fault: None,
+            get_fault: None,
+            scan_fault: None,
+            updates: 0,
+            gets: AtomicUsize::new(0),
+            scanned: AtomicUsize::new(0),
+        }
+    }
+    pub fn fail_update_at(mut self, n: usize, fault: Fault) -> Self {
+        self.update_fault = Some((self.updates() + n, fault));
+        self
+    }
+    pub fn fail_get_at(mut self, n: usize, fault: Fault) -> Self {
+        self.get_fault = Some((self.gets() + n, fault));
+        self
+    }
+    pub fn fail_scan_at(mut self, n: usize, fault: Fault) -> Self {
+        self.scan_fault = Some((self.scanned() + n, fault));
+        self
+    }
+    pub fn updates(&self) -> usize {
+        self.updates
+    }
+    pub fn gets(&self) -> usize {
+        self.gets.load(Ordering::SeqCst)
+    }
+    pub fn scanned(&self) -> usize {
+        self.scanned.load(Ordering::SeqCst)
+    }
+    /// Whether any of the faults has been injected
+    pub fn triggered(&self) -> bool {
+        matches!(self.update_fault, Some((at, _)) if self.updates() > at)
+            || matches!(self.get_fault, Some((at, _)) if self.gets() > at)
+            || matches!(self.scan_fault, Some((at, _)) if self.scanned() > at)
+    }
+    pub fn into_inner(self) -> K {
+        self.store
+    }
+}
+
+fn inject(fault: Fault) -> KvStoreError {
+    match fault {
+        Fault::Error => KvStoreError::Failure,
+        Fault::Panic => panic!("injected kvstore fault"),
+    }
+}
+
+impl<K: KvStore> KvStore for FaultyKvStore<K> {
+    fn get(&self, k: DbKey) -> Result<Option<Blob>, KvStoreError> {
+        let n = self.gets.fetch_add(1, Ordering::SeqCst);
+        match self.get_fault {
+            Some((at, fault)) if at == n => Err(inject(fault)),
+            _ => self.store.get(k),
+        }
+    }
+    fn update(&mut self, ops: &[WriteOp]) -> Result<(), KvStoreError> {
+        let n = self.updates;
+        self.updates += 1;
+        match self.update_fault {
+            Some((at, fault)) if at == n => Err(inject(fault)),
+            _ => self.store.update(ops),
+        }
+    }
+    fn scan(&self, query: ScanQuery) -> Result<KvIterator<'_>, KvStoreError> {
+        Ok(Box::new(self.store.scan(query)?.map(move |item| {
+            let n = self.scanned.fetch_add(1, Ordering::SeqCst);
+            match self.scan_fault {
+                Some((at, fault)) if at == n => Err(inject(fault)),
+                _ => item,
+            }
+        })))
+    }
+    fn checksum(&self) -> Result<Checksum, KvStoreError> {
+        self.store.checksum()
+    }
+}
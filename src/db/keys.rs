@@ -1,100 +1,287 @@
 use super::*;
 use crate::core::{Address, ContractId};
+use crate::crypto::ed25519;
+use crate::crypto::jubjub::PointCompressed;
 use crate::zk::ZkDataLocator;
+use ff::PrimeField;
 
-pub fn height() -> StringKey {
-    "HGT".into()
+// Every key starts with a byte identifying its kind, followed by fixed-width
+// big-endian fields, so that keys of the same kind are ordered numerically.
+// Contract-local keys share the `LOCAL` prefix and the contract id, followed
+// by a byte identifying the kind of the local key.
+const HEIGHT: u8 = 0x01;
+const OUTDATED: u8 = 0x02;
+const BLOCK: u8 = 0x03;
+const HEADER: u8 = 0x04;
+const POWER: u8 = 0x05;
+const ROLLBACK: u8 = 0x06;
+const MERKLE: u8 = 0x07;
+const COMPRESSED_STATE_AT: u8 = 0x08;
+const ACCOUNT: u8 = 0x09;
+const CONTRACT_ACCOUNT: u8 = 0x0a;
+const CONTRACT: u8 = 0x0b;
+const CONTRACT_UPDATES: u8 = 0x0c;
+const LOCAL: u8 = 0x0d;
+const MPN_INDEXED: u8 = 0x0e;
+
+const LOCAL_HEIGHT: u8 = 0x01;
+const LOCAL_ROOT: u8 = 0x02;
+const LOCAL_TREE_AUX: u8 = 0x03;
+const LOCAL_ROLLBACK: u8 = 0x04;
+const LOCAL_SCALAR_VALUE: u8 = 0x05;
+const LOCAL_NON_SCALAR_VALUE: u8 = 0x06;
+const LOCAL_MAP_AUX: u8 = 0x07;
+const LOCAL_MPN_INDEX: u8 = 0x08;
+const LOCAL_MPN_USED: u8 = 0x09;
+
+const CONTRACT_ID_LEN: usize = 32;
+
+fn key(parts: &[&[u8]]) -> DbKey {
+    DbKey(parts.concat())
 }
 
-pub fn outdated() -> StringKey {
-    "OUT".into()
+fn locator_bytes(locator: &ZkDataLocator) -> Vec<u8> {
+    locator.0.iter().flat_map(|i| i.to_be_bytes()).collect()
 }
 
-pub fn block(index: u64) -> StringKey {
-    format!("BLK-{:010}", index).into()
+fn address_bytes(address: &Address) -> Vec<u8> {
+    match address {
+        Address::Treasury => vec![0],
+        Address::PublicKey(pk) => [&[1u8][..], pk.0.as_bytes()].concat(),
+    }
 }
 
-pub fn header(index: u64) -> StringKey {
-    format!("HDR-{:010}", index).into()
+/// The schema version is read before knowing the layout of the other keys,
+/// so its key is kept the same across all layouts.
+pub fn schema_version() -> DbKey {
+    "VER".into()
 }
 
-pub fn power(index: u64) -> StringKey {
-    format!("POW-{:010}", index).into()
+/// Last key rewritten by the migration step in progress, if any
+pub fn migration_progress() -> DbKey {
+    "MIG".into()
 }
 
-pub fn rollback(index: u64) -> StringKey {
-    format!("RLK-{:010}", index).into()
+pub fn height() -> DbKey {
+    key(&[&[HEIGHT]])
 }
 
-pub fn merkle(index: u64) -> StringKey {
-    format!("MRK-{:010}", index).into()
+pub fn outdated() -> DbKey {
+    key(&[&[OUTDATED]])
 }
 
-pub fn compressed_state_at(contract_id: &ContractId, at: u64) -> StringKey {
-    format!("CSA-{:010}-{}", at, contract_id).into()
+pub fn block(index: u64) -> DbKey {
+    key(&[&[BLOCK], &index.to_be_bytes()])
 }
 
-pub fn account(address: &Address) -> StringKey {
-    format!("ACC-{}", address).into()
+pub fn header(index: u64) -> DbKey {
+    key(&[&[HEADER], &index.to_be_bytes()])
 }
 
-pub fn contract_account(contract_id: &ContractId) -> StringKey {
-    format!("CAC-{}", contract_id).into()
+pub fn power(index: u64) -> DbKey {
+    key(&[&[POWER], &index.to_be_bytes()])
 }
 
-pub fn contract(contract_id: &ContractId) -> StringKey {
-    format!("CON-{}", contract_id).into()
+pub fn rollback(index: u64) -> DbKey {
+    key(&[&[ROLLBACK], &index.to_be_bytes()])
 }
 
-pub fn contract_updates() -> StringKey {
-    "CUP".into()
+pub fn merkle(index: u64) -> DbKey {
+    key(&[&[MERKLE], &index.to_be_bytes()])
 }
 
-pub fn local_prefix(contract_id: &ContractId) -> String {
-    format!("S-{}", contract_id)
+pub fn compressed_state_at(contract_id: &ContractId, at: u64) -> DbKey {
+    key(&[
+        &[COMPRESSED_STATE_AT],
+        &at.to_be_bytes(),
+        contract_id.as_ref(),
+    ])
 }
 
-pub fn local_height(contract_id: &ContractId) -> StringKey {
-    format!("{}-HGT", local_prefix(contract_id)).into()
+pub fn account_prefix() -> DbKey {
+    key(&[&[ACCOUNT]])
 }
 
-pub fn local_root(contract_id: &ContractId) -> StringKey {
-    format!("{}-RT", local_prefix(contract_id)).into()
+pub fn account(address: &Address) -> DbKey {
+    key(&[&[ACCOUNT], &address_bytes(address)])
 }
 
-pub fn local_tree_aux(
-    contract_id: &ContractId,
-    tree_loc: &ZkDataLocator,
-    aux_id: u32,
-) -> StringKey {
-    format!("{}-{}-T-{}", local_prefix(contract_id), tree_loc, aux_id).into()
+pub fn contract_account_prefix() -> DbKey {
+    key(&[&[CONTRACT_ACCOUNT]])
+}
+
+pub fn contract_account(contract_id: &ContractId) -> DbKey {
+    key(&[&[CONTRACT_ACCOUNT], contract_id.as_ref()])
+}
+
+/// Extracts the address of a key built by `account`
+pub fn account_address(key: &DbKey) -> Option<Address> {
+    match key.0.split_first()? {
+        (&ACCOUNT, [0]) => Some(Address::Treasury),
+        (&ACCOUNT, [1, pk @ ..]) => Some(Address::PublicKey(ed25519::PublicKey(
+            ed25519_dalek::PublicKey::from_bytes(pk).ok()?,
+        ))),
+        _ => None,
+    }
+}
+
+/// Extracts the contract id of a key built by `contract_account`
+pub fn contract_account_id(key: &DbKey) -> Option<ContractId> {
+    match key.0.split_first()? {
+        (&CONTRACT_ACCOUNT, id) => ContractId::try_from(id).ok(),
+        _ => None,
+    }
+}
+
+pub fn contract(contract_id: &ContractId) -> DbKey {
+    key(&[&[CONTRACT], contract_id.as_ref()])
+}
+
+pub fn contract_prefix() -> DbKey {
+    key(&[&[CONTRACT]])
+}
+
+/// Extracts the contract id of a key built by `contract`
+pub fn contract_id(key: &DbKey) -> Option<ContractId> {
+    match key.0.split_first()? {
+        (&CONTRACT, id) => ContractId::try_from(id).ok(),
+        _ => None,
+    }
+}
+
+pub fn contract_updates() -> DbKey {
+    key(&[&[CONTRACT_UPDATES]])
+}
+
+/// Marks a contract whose accounts are indexed by their public keys. Kept
+/// outside of the local keys of the contract, so that it survives resets.
+pub fn mpn_indexed(contract_id: &ContractId) -> DbKey {
+    key(&[&[MPN_INDEXED], contract_id.as_ref()])
+}
+
+pub fn local_prefix(contract_id: &ContractId) -> DbKey {
+    key(&[&[LOCAL], contract_id.as_ref()])
+}
+
+fn local(contract_id: &ContractId, kind: u8, parts: &[&[u8]]) -> DbKey {
+    let mut k = local_prefix(contract_id);
+    k.0.push(kind);
+    for part in parts {
+        k.0.extend_from_slice(part);
+    }
+    k
 }
 
-pub fn local_rollback_to_height(contract_id: &ContractId, height: u64) -> StringKey {
-    format!("{}-RLK-{}", local_prefix(contract_id), height).into()
+pub fn local_height(contract_id: &ContractId) -> DbKey {
+    local(contract_id, LOCAL_HEIGHT, &[])
 }
 
-pub fn local_scalar_value_prefix(contract_id: &ContractId) -> String {
-    format!("{}-S", local_prefix(contract_id),).into()
+pub fn local_root(contract_id: &ContractId) -> DbKey {
+    local(contract_id, LOCAL_ROOT, &[])
 }
 
-pub fn local_non_scalar_value_prefix(contract_id: &ContractId) -> String {
-    format!("{}", local_prefix(contract_id),).into()
+pub fn local_tree_aux(contract_id: &ContractId, tree_loc: &ZkDataLocator, aux_id: u32) -> DbKey {
+    // Length of the locator comes first, so that the locator and the aux-id
+    // can't be confused when locators of different length exist.
+    local(
+        contract_id,
+        LOCAL_TREE_AUX,
+        &[
+            &[tree_loc.0.len() as u8],
+            &locator_bytes(tree_loc),
+            &aux_id.to_be_bytes(),
+        ],
+    )
 }
 
-pub fn local_value(
+/// Key of a node of the tree of a `Map`, the node being identified by its
+/// depth and the path leading to it, zero-padded
+pub fn local_map_aux(
     contract_id: &ContractId,
-    locator: &ZkDataLocator,
-    is_scalar: bool,
-) -> StringKey {
-    format!(
-        "{}-{}",
+    map_loc: &ZkDataLocator,
+    depth: u8,
+    path: &[u8; 32],
+) -> DbKey {
+    local(
+        contract_id,
+        LOCAL_MAP_AUX,
+        &[
+            &[map_loc.0.len() as u8],
+            &locator_bytes(map_loc),
+            &[depth],
+            path,
+        ],
+    )
+}
+
+fn point_bytes(point: &PointCompressed) -> Vec<u8> {
+    [point.0.to_repr().as_ref(), &[point.1 as u8]].concat()
+}
+
+pub fn local_mpn_index_prefix(contract_id: &ContractId, address: &PointCompressed) -> DbKey {
+    local(contract_id, LOCAL_MPN_INDEX, &[&point_bytes(address)])
+}
+
+/// Key marking the account at `index` as owned by `address`, the account
+/// index being also the value
+pub fn local_mpn_index(contract_id: &ContractId, address: &PointCompressed, index: u32) -> DbKey {
+    local(
+        contract_id,
+        LOCAL_MPN_INDEX,
+        &[&point_bytes(address), &index.to_be_bytes()],
+    )
+}
+
+/// Number of used accounts among the `4^level` accounts starting at
+/// `node * 4^level`, i.e. below a node of the 4-ary tree over the accounts
+pub fn local_mpn_used(contract_id: &ContractId, level: u8, node: u32) -> DbKey {
+    local(
+        contract_id,
+        LOCAL_MPN_USED,
+        &[&[level], &node.to_be_bytes()],
+    )
+}
+
+pub fn local_rollback_to_height(contract_id: &ContractId, height: u64) -> DbKey {
+    local(contract_id, LOCAL_ROLLBACK, &[&height.to_be_bytes()])
+}
+
+pub fn local_scalar_value_prefix(contract_id: &ContractId) -> DbKey {
+    local(contract_id, LOCAL_SCALAR_VALUE, &[])
+}
+
+pub fn local_non_scalar_value_prefix(contract_id: &ContractId) -> DbKey {
+    local(contract_id, LOCAL_NON_SCALAR_VALUE, &[])
+}
+
+pub fn local_value(contract_id: &ContractId, locator: &ZkDataLocator, is_scalar: bool) -> DbKey {
+    local(
+        contract_id,
         if is_scalar {
-            local_scalar_value_prefix(contract_id)
+            LOCAL_SCALAR_VALUE
         } else {
-            local_non_scalar_value_prefix(contract_id)
+            LOCAL_NON_SCALAR_VALUE
         },
-        locator
+        &[&locator_bytes(locator)],
     )
-    .into()
+}
+
+/// Extracts the locator of a key built by `local_value`
+pub fn local_value_locator(key: &DbKey) -> Option<ZkDataLocator> {
+    let kind_index = 1 + CONTRACT_ID_LEN;
+    if key.0.len() <= kind_index
+        || key.0[0] != LOCAL
+        || ![LOCAL_SCALAR_VALUE, LOCAL_NON_SCALAR_VALUE].contains(&key.0[kind_index])
+    {
+        return None;
+    }
+    let chunks = key.0[kind_index + 1..].chunks_exact(4);
+    if !chunks.remainder().is_empty() {
+        return None;
+    }
+    Some(ZkDataLocator(
+        chunks
+            .map(|c| u32::from_be_bytes(c.try_into().unwrap()))
+            .collect(),
+    ))
 }
@@ -0,0 +1,337 @@
+use super::*;
+use crate::core::{Address, ContractId};
+use crate::zk::{
+    mpn_used_keys, ZkContract, ZkDataLocator, ZkScalar, ZkStateModel, MPN_ACCOUNT_STATE_MODEL,
+};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Version of the key layout and `Blob` encodings written by this software
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// Number of pairs a migration step rewrites in a single update
+pub const MIGRATION_BATCH_SIZE: usize = 10000;
+
+/// Writes of a batch of a migration step, and whether it was the last one
+pub struct MigrationBatch {
+    pub ops: Vec<WriteOp>,
+    pub done: bool,
+}
+
+/// A step migrating a database of schema version `from` to `from + 1`.
+/// Steps only read the store and return the writes of their next batch of
+/// at most `batch_size` pairs. Each batch is applied atomically along with
+/// the progress of the step, so that an interrupted step is resumed from
+/// its last completed batch, and the version is only bumped along with the
+/// last batch.
+pub struct Migration {
+    pub from: u32,
+    pub description: &'static str,
+    pub run: fn(&dyn KvStore, usize) -> Result<MigrationBatch, KvStoreError>,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "Record the schema version of databases created before versioning",
+        run: |_, _| {
+            Ok(MigrationBatch {
+                ops: vec![],
+                done: true,
+            })
+        },
+    },
+    Migration {
+        from: 1,
+        description: "Convert formatted string keys to binary keys",
+        run: binary_keys,
+    },
+    Migration {
+        from: 2,
+        description: "Index the accounts of MPN contracts",
+        run: mpn_accounts_index,
+    },
+];
+
+fn parse_legacy_locator(s: &str) -> Option<ZkDataLocator> {
+    if s.is_empty() {
+        Some(ZkDataLocator(vec![]))
+    } else {
+        ZkDataLocator::from_str(s).ok()
+    }
+}
+
+/// Parses a key of the schema v1 layout, e.g. `BLK-0000000012` or
+/// `S-{contract-id}-{locator}-T-{aux-id}`
+fn parse_legacy_key(key: &str) -> Option<DbKey> {
+    let height = |s: &str| s.parse::<u64>().ok();
+    let contract_id = |s: &str| ContractId::from_str(s).ok();
+    match key {
+        "HGT" => return Some(keys::height()),
+        "OUT" => return Some(keys::outdated()),
+        "CUP" => return Some(keys::contract_updates()),
+        _ => {}
+    }
+    let (kind, rest) = key.split_once('-')?;
+    Some(match kind {
+        "BLK" => keys::block(height(rest)?),
+        "HDR" => keys::header(height(rest)?),
+        "POW" => keys::power(height(rest)?),
+        "RLK" => keys::rollback(height(rest)?),
+        "MRK" => keys::merkle(height(rest)?),
+        "CSA" => {
+            let (at, id) = rest.split_once('-')?;
+            keys::compressed_state_at(&contract_id(id)?, height(at)?)
+        }
+        "ACC" => keys::account(&if rest == "Treasury" {
+            Address::Treasury
+        } else {
+            Address::from_str(rest).ok()?
+        }),
+        "CAC" => keys::contract_account(&contract_id(rest)?),
+        "CON" => keys::contract(&contract_id(rest)?),
+        "S" => {
+            let (id, rest) = rest.split_once('-')?;
+            let id = contract_id(id)?;
+            if rest == "HGT" {
+                keys::local_height(&id)
+            } else if rest == "RT" {
+                keys::local_root(&id)
+            } else if let Some(h) = rest.strip_prefix("RLK-") {
+                keys::local_rollback_to_height(&id, height(h)?)
+            } else if let Some(loc) = rest.strip_prefix("S-") {
+                keys::local_value(&id, &parse_legacy_locator(loc)?, true)
+            } else if let Some((loc, aux)) = rest.split_once("-T-") {
+                keys::local_tree_aux(&id, &parse_legacy_locator(loc)?, aux.parse().ok()?)
+            } else {
+                keys::local_value(&id, &parse_legacy_locator(rest)?, false)
+            }
+        }
+        _ => {
+            return None;
+        }
+    })
+}
+
+fn convert_legacy_key(key: &DbKey) -> Result<DbKey, KvStoreError> {
+    std::str::from_utf8(&key.0)
+        .ok()
+        .and_then(parse_legacy_key)
+        .ok_or_else(|| {
+            KvStoreError::MigrationFailed(format!(
+                "unrecognized key {}",
+                String::from_utf8_lossy(&key.0)
+            ))
+        })
+}
+
+fn binary_keys(db: &dyn KvStore, batch_size: usize) -> Result<MigrationBatch, KvStoreError> {
+    // Keys of the legacy layout are printable, so they come after the
+    // binary keys already written by the previous batches
+    let start = match db.get(keys::migration_progress())? {
+        // Smallest key after the last migrated key
+        Some(Blob(mut last)) => {
+            last.push(0);
+            DbKey(last)
+        }
+        None => DbKey(vec![b' ']),
+    };
+    let mut ops = Vec::new();
+    let mut last = None;
+    let mut count = 0;
+    for item in db.scan(ScanQuery {
+        start: Some(start),
+        limit: Some(batch_size),
+        ..Default::default()
+    })? {
+        let (k, v) = item?;
+        count += 1;
+        last = Some(k.clone());
+        if k == keys::schema_version() || k == keys::migration_progress() {
+            continue;
+        }
+        let new_key = convert_legacy_key(&k)?;
+        // Block rollbacks are lists of writes on keys of the old layout
+        let v = if k.0.starts_with(b"RLK-") {
+            let rollback: Vec<WriteOp> = v.try_into()?;
+            rollback
+                .into_iter()
+                .map(|op| {
+                    Ok(match op {
+                        WriteOp::Remove(k) => WriteOp::Remove(convert_legacy_key(&k)?),
+                        WriteOp::Put(k, v) => WriteOp::Put(convert_legacy_key(&k)?, v),
+                    })
+                })
+                .collect::<Result<Vec<_>, KvStoreError>>()?
+                .into()
+        } else {
+            v
+        };
+        ops.push(WriteOp::Remove(k));
+        ops.push(WriteOp::Put(new_key, v));
+    }
+    let done = count < batch_size;
+    ops.push(match last {
+        Some(last) if !done => WriteOp::Put(keys::migration_progress(), Blob(last.0)),
+        _ => WriteOp::Remove(keys::migration_progress()),
+    });
+    Ok(MigrationBatch { ops, done })
+}
+
+fn migration_failed(msg: &str) -> KvStoreError {
+    KvStoreError::MigrationFailed(msg.into())
+}
+
+// Batches end on account boundaries, the progress being the contract and the
+// account the next batch starts from
+fn mpn_accounts_index(db: &dyn KvStore, batch_size: usize) -> Result<MigrationBatch, KvStoreError> {
+    let mut contracts = ScanQuery::prefix(keys::contract_prefix());
+    let mut start_index = 0;
+    if let Some(Blob(progress)) = db.get(keys::migration_progress())? {
+        if progress.len() != 36 {
+            return Err(migration_failed("invalid progress"));
+        }
+        let id = ContractId::try_from(&progress[..32])
+            .map_err(|_| migration_failed("invalid progress"))?;
+        contracts.start = Some(keys::contract(&id));
+        start_index = u32::from_be_bytes(progress[32..].try_into().unwrap());
+    }
+
+    let mut ops = Vec::new();
+    let mut count = 0;
+    for item in db.scan(contracts)? {
+        let (k, v) = item?;
+        let id = keys::contract_id(&k).ok_or_else(|| migration_failed("invalid contract key"))?;
+        let contract: ZkContract = v.try_into()?;
+        let log4_size = match contract.state_model {
+            ZkStateModel::List {
+                item_type,
+                log4_size,
+            } if *item_type == *MPN_ACCOUNT_STATE_MODEL => log4_size,
+            _ => {
+                start_index = 0;
+                continue;
+            }
+        };
+        if start_index == 0 {
+            ops.push(WriteOp::Put(keys::mpn_indexed(&id), 0u32.into()));
+        }
+
+        let mut used = HashMap::<DbKey, u64>::new();
+        let mut account: Option<(u32, [ZkScalar; 4])> = None;
+        let mut add_account = |ops: &mut Vec<WriteOp>, (index, cells): (u32, [ZkScalar; 4])| {
+            for k in mpn_used_keys(&id, log4_size, index) {
+                *used.entry(k).or_default() += 1;
+            }
+            let address = crate::crypto::jubjub::PointAffine(cells[1], cells[2]);
+            if address != Default::default() {
+                ops.push(WriteOp::Put(
+                    keys::local_mpn_index(&id, &address.compress(), index),
+                    index.into(),
+                ));
+            }
+        };
+        let mut values = ScanQuery::prefix(keys::local_scalar_value_prefix(&id));
+        values.start = Some(keys::local_value(
+            &id,
+            &ZkDataLocator(vec![start_index]),
+            true,
+        ));
+        let mut next = None;
+        for item in db.scan(values)? {
+            let (k, v) = item?;
+            let (index, field) = match keys::local_value_locator(&k).map(|loc| loc.0) {
+                Some(loc) if loc.len() == 2 && loc[1] < 4 => (loc[0], loc[1] as usize),
+                _ => {
+                    return Err(migration_failed("invalid MPN account locator"));
+                }
+            };
+            if account.map(|(i, _)| i) != Some(index) {
+                if let Some(acc) = account.take() {
+                    add_account(&mut ops, acc);
+                }
+                if count >= batch_size {
+                    next = Some(index);
+                    break;
+                }
+                account = Some((index, Default::default()));
+            }
+            if let Some((_, cells)) = account.as_mut() {
+                cells[field] = v.try_into()?;
+            }
+            count += 1;
+        }
+        if let Some(acc) = account.take() {
+            add_account(&mut ops, acc);
+        }
+
+        // Counters may already hold the accounts of the previous batches
+        for (k, n) in used {
+            let prev: u64 = match db.get(k.clone())? {
+                Some(v) => v.try_into()?,
+                None => 0,
+            };
+            ops.push(WriteOp::Put(k, (prev + n).into()));
+        }
+        if let Some(index) = next {
+            let progress = [id.as_ref(), &index.to_be_bytes()].concat();
+            ops.push(WriteOp::Put(keys::migration_progress(), Blob(progress)));
+            return Ok(MigrationBatch { ops, done: false });
+        }
+        start_index = 0;
+    }
+    ops.push(WriteOp::Remove(keys::migration_progress()));
+    Ok(MigrationBatch { ops, done: true })
+}
+
+/// Schema version of the database, `None` if the database is empty
+pub fn schema_version<K: KvStore>(db: &K) -> Result<Option<u32>, KvStoreError> {
+    if let Some(v) = db.get(keys::schema_version())? {
+        Ok(Some(v.try_into()?))
+    } else if db.get("HGT".into())?.is_some() {
+        // Height key of databases created before versioning
+        Ok(Some(0))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn pending_migrations<K: KvStore>(db: &K) -> Result<Vec<&'static Migration>, KvStoreError> {
+    match schema_version(db)? {
+        Some(version) if version > SCHEMA_VERSION => Err(KvStoreError::SchemaTooNew(version)),
+        Some(version) => Ok(MIGRATIONS.iter().filter(|m| m.from >= version).collect()),
+        None => Ok(vec![]),
+    }
+}
+
+/// Brings the database to `SCHEMA_VERSION`, returns the steps that were run
+pub fn migrate<K: KvStore>(db: &mut K) -> Result<Vec<&'static Migration>, KvStoreError> {
+    migrate_in_batches(db, MIGRATION_BATCH_SIZE)
+}
+
+pub fn migrate_in_batches<K: KvStore>(
+    db: &mut K,
+    batch_size: usize,
+) -> Result<Vec<&'static Migration>, KvStoreError> {
+    let pending = pending_migrations(db)?;
+    for step in pending.iter() {
+        log::info!(
+            "Migrating database from schema v{} to v{}: {}",
+            step.from,
+            step.from + 1,
+            step.description
+        );
+        loop {
+            let MigrationBatch { mut ops, done } = (step.run)(db, batch_size)?;
+            if done {
+                ops.push(WriteOp::Put(keys::schema_version(), (step.from + 1).into()));
+            }
+            db.update(&ops)?;
+            if done {
+                break;
+            }
+        }
+    }
+    db.update(&[WriteOp::Put(keys::schema_version(), SCHEMA_VERSION.into())])?;
+    Ok(pending)
+}
@@ -1,4 +1,5 @@
 pub mod keys;
+pub mod migrations;
 
 use crate::blockchain::{ZkBlockchainPatch, ZkCompressedStateChange};
 use crate::core::{hash::Hash, Account, Block, ContractAccount, ContractId, Hasher, Header};
@@ -8,7 +9,10 @@ use crate::zk::{
 };
 use db_key::Key;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::iter::Peekable;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -19,29 +23,53 @@ pub enum KvStoreError {
     Corrupted(#[from] bincode::Error),
     #[error("io error: {0}")]
     IO(#[from] std::io::Error),
+    #[error(
+        "database schema v{0} is newer than the supported v{}",
+        migrations::SCHEMA_VERSION
+    )]
+    SchemaTooNew(u32),
+    #[error("database migration failed: {0}")]
+    MigrationFailed(String),
+    #[error("backups are not supported by this store")]
+    BackupUnsupported,
+    #[error("backup destination {} is not empty", .0.display())]
+    BackupDestinationNotEmpty(PathBuf),
+    #[error("backup checksum does not match the database")]
+    BackupChecksumMismatch,
     #[cfg(feature = "db")]
     #[error("leveldb error: {0}")]
     LevelDb(#[from] leveldb::error::Error),
+    #[cfg(feature = "redb-db")]
+    #[error("redb error: {0}")]
+    Redb(Box<redb::Error>),
+}
+
+// Boxed since the error of redb is large, enlarging every result of the store
+#[cfg(feature = "redb-db")]
+impl From<redb::Error> for KvStoreError {
+    fn from(e: redb::Error) -> Self {
+        Self::Redb(Box::new(e))
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, std::hash::Hash)]
-pub struct StringKey(pub String);
+pub struct DbKey(pub Vec<u8>);
 
-impl PartialOrd for StringKey {
+impl PartialOrd for DbKey {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl Ord for StringKey {
+impl Ord for DbKey {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         self.0.cmp(&other.0)
     }
 }
 
-impl StringKey {
-    pub fn new(s: &str) -> StringKey {
-        StringKey(s.to_string())
+impl DbKey {
+    pub fn new(bytes: &[u8]) -> DbKey {
+        DbKey(bytes.to_vec())
     }
 }
 
@@ -120,41 +148,133 @@ gen_from!(
     &ZkDeltaPairs
 );
 
-impl Key for StringKey {
-    fn from_u8(key: &[u8]) -> StringKey {
-        StringKey(std::str::from_utf8(key).unwrap().to_string())
+impl Key for DbKey {
+    fn from_u8(key: &[u8]) -> DbKey {
+        DbKey::new(key)
     }
 
     fn as_slice<T, F: Fn(&[u8]) -> T>(&self, f: F) -> T {
-        f(self.0.as_bytes())
+        f(&self.0)
     }
 }
 
-impl From<String> for StringKey {
+impl From<Vec<u8>> for DbKey {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+impl From<String> for DbKey {
     fn from(s: String) -> Self {
-        Self::new(&s)
+        Self(s.into_bytes())
     }
 }
-impl From<&str> for StringKey {
+impl From<&str> for DbKey {
     fn from(s: &str) -> Self {
-        Self::new(s)
+        Self::new(s.as_bytes())
     }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub enum WriteOp {
-    Remove(StringKey),
-    Put(StringKey, Blob),
+    Remove(DbKey),
+    Put(DbKey, Blob),
+}
+
+/// Smallest key that is greater than all keys starting with `prefix`,
+/// `None` when there is no such key.
+fn prefix_end(prefix: &[u8]) -> Option<DbKey> {
+    let mut bytes = prefix.to_vec();
+    while let Some(last) = bytes.pop() {
+        if last < u8::MAX {
+            bytes.push(last + 1);
+            return Some(DbKey(bytes));
+        }
+    }
+    None
 }
 
+/// An ordered scan over the keys in `[start, end)`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScanQuery {
+    pub start: Option<DbKey>,
+    pub end: Option<DbKey>,
+    pub reverse: bool,
+    pub limit: Option<usize>,
+}
+
+impl ScanQuery {
+    pub fn all() -> Self {
+        Self::default()
+    }
+    pub fn prefix(prefix: DbKey) -> Self {
+        Self {
+            end: prefix_end(&prefix.0),
+            start: Some(prefix),
+            ..Default::default()
+        }
+    }
+    pub fn range(start: DbKey, end: DbKey) -> Self {
+        Self {
+            start: Some(start),
+            end: Some(end),
+            ..Default::default()
+        }
+    }
+    pub fn reverse(mut self) -> Self {
+        self.reverse = true;
+        self
+    }
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+    pub fn contains(&self, k: &DbKey) -> bool {
+        self.start.as_ref().map(|s| k >= s).unwrap_or(true)
+            && self.end.as_ref().map(|e| k < e).unwrap_or(true)
+    }
+    fn bounds(&self) -> (Bound<DbKey>, Bound<DbKey>) {
+        (
+            self.start
+                .as_ref()
+                .map(|s| Bound::Included(s.clone()))
+                .unwrap_or(Bound::Unbounded),
+            self.end
+                .as_ref()
+                .map(|e| Bound::Excluded(e.clone()))
+                .unwrap_or(Bound::Unbounded),
+        )
+    }
+}
+
+/// Pairs that can't be read from the underlying storage end up as errors
+pub type KvIterator<'a> = Box<dyn Iterator<Item = Result<(DbKey, Blob), KvStoreError>> + 'a>;
+
 pub trait KvStore {
-    fn get(&self, k: StringKey) -> Result<Option<Blob>, KvStoreError>;
+    fn get(&self, k: DbKey) -> Result<Option<Blob>, KvStoreError>;
     fn update(&mut self, ops: &[WriteOp]) -> Result<(), KvStoreError>;
-    fn pairs(&self, prefix: StringKey) -> Result<HashMap<StringKey, Blob>, KvStoreError>;
-    fn checksum<H: Hash>(&self) -> Result<H::Output, KvStoreError> {
-        let mut kvs: Vec<_> = self.pairs("".into())?.into_iter().collect();
-        kvs.sort_by_key(|(k, _)| k.clone());
-        Ok(H::hash(&bincode::serialize(&kvs).unwrap()))
+    /// Lazily iterates over the pairs matching the query, ordered by key
+    fn scan(&self, query: ScanQuery) -> Result<KvIterator<'_>, KvStoreError>;
+    fn pairs(&self, prefix: DbKey) -> Result<HashMap<DbKey, Blob>, KvStoreError> {
+        self.scan(ScanQuery::prefix(prefix))?.collect()
+    }
+    /// Stores that maintain their checksum incrementally override this
+    fn checksum(&self) -> Result<Checksum, KvStoreError> {
+        Checksum::of_scan(self.scan(ScanQuery::all())?)
+    }
+    /// Hit/miss statistics of stores that cache reads
+    fn cache_stats(&self) -> Option<CacheStats> {
+        None
+    }
+    /// Takes a snapshot of the store, which the returned job writes as a new
+    /// database. The job doesn't borrow the store, so the store may be
+    /// updated while the job runs, e.g. on another thread.
+    fn backup_job(&self) -> Result<BackupJob, KvStoreError> {
+        Err(KvStoreError::BackupUnsupported)
+    }
+    /// Writes a consistent copy of the store as a new database at `path`,
+    /// returns the number of pairs copied
+    fn backup(&self, path: &Path) -> Result<usize, KvStoreError> {
+        self.backup_job()?(path)
     }
     fn mirror(&self) -> RamMirrorKvStore<'_, Self>
     where
@@ -164,15 +284,96 @@ pub trait KvStore {
     }
 }
 
+impl<K: KvStore + ?Sized> KvStore for Box<K> {
+    fn get(&self, k: DbKey) -> Result<Option<Blob>, KvStoreError> {
+        (**self).get(k)
+    }
+    fn update(&mut self, ops: &[WriteOp]) -> Result<(), KvStoreError> {
+        (**self).update(ops)
+    }
+    fn scan(&self, query: ScanQuery) -> Result<KvIterator<'_>, KvStoreError> {
+        (**self).scan(query)
+    }
+    fn checksum(&self) -> Result<Checksum, KvStoreError> {
+        (**self).checksum()
+    }
+    fn cache_stats(&self) -> Option<CacheStats> {
+        (**self).cache_stats()
+    }
+    fn backup_job(&self) -> Result<BackupJob, KvStoreError> {
+        (**self).backup_job()
+    }
+}
+
+/// Copies every pair of `src` into `dst`, writing `batch_size` pairs at a
+/// time. Returns the number of pairs copied.
+pub fn copy_store(
+    src: &dyn KvStore,
+    dst: &mut dyn KvStore,
+    batch_size: usize,
+) -> Result<usize, KvStoreError> {
+    let mut count = 0;
+    let mut batch = Vec::with_capacity(batch_size);
+    for item in src.scan(ScanQuery::all())? {
+        let (k, v) = item?;
+        batch.push(WriteOp::Put(k, v));
+        if batch.len() >= batch_size {
+            count += batch.len();
+            dst.update(&batch)?;
+            batch.clear();
+        }
+    }
+    count += batch.len();
+    dst.update(&batch)?;
+    Ok(count)
+}
+
+/// Backups are only written to new directories, never over existing data
+fn check_backup_destination(path: &Path) -> Result<(), KvStoreError> {
+    if path.exists() && std::fs::read_dir(path)?.next().is_some() {
+        return Err(KvStoreError::BackupDestinationNotEmpty(path.into()));
+    }
+    Ok(())
+}
+
+/// Copies a snapshot of a store into a newly created one, making sure
+/// nothing is lost on the way
+/// Writes a snapshot of a store as a new database at the given path, returns
+/// the number of pairs copied
+pub type BackupJob = Box<dyn FnOnce(&Path) -> Result<usize, KvStoreError> + Send>;
+
+fn backup_snapshot<S: KvStore, D: KvStore>(
+    snapshot: &S,
+    expected: Checksum,
+    dst: &mut D,
+) -> Result<usize, KvStoreError> {
+    let count = copy_store(snapshot, dst, 10000)?;
+    if dst.checksum()? != expected {
+        return Err(KvStoreError::BackupChecksumMismatch);
+    }
+    Ok(count)
+}
+
+/// Makes sure the checksum maintained by the store matches its pairs, e.g.
+/// before trusting a backup, returns the number of pairs
+pub fn verify_checksum<K: KvStore>(store: &K) -> Result<usize, KvStoreError> {
+    let mut count = 0;
+    let checksum = Checksum::of_scan(store.scan(ScanQuery::all())?.inspect(|_| count += 1))?;
+    if store.checksum()? != checksum {
+        return Err(KvStoreError::BackupChecksumMismatch);
+    }
+    Ok(count)
+}
+
 pub struct RamMirrorKvStore<'a, K: KvStore> {
     store: &'a K,
-    overwrite: HashMap<StringKey, Option<Blob>>,
+    overwrite: BTreeMap<DbKey, Option<Blob>>,
 }
 impl<'a, K: KvStore> RamMirrorKvStore<'a, K> {
     pub fn new(store: &'a K) -> Self {
         Self {
             store,
-            overwrite: HashMap::new(),
+            overwrite: BTreeMap::new(),
         }
     }
     pub fn rollback(&self) -> Result<Vec<WriteOp>, KvStoreError> {
@@ -198,7 +399,7 @@ impl<'a, K: KvStore> RamMirrorKvStore<'a, K> {
 }
 
 impl<'a, K: KvStore> KvStore for RamMirrorKvStore<'a, K> {
-    fn get(&self, k: StringKey) -> Result<Option<Blob>, KvStoreError> {
+    fn get(&self, k: DbKey) -> Result<Option<Blob>, KvStoreError> {
         if self.overwrite.contains_key(&k) {
             Ok(self.overwrite.get(&k).cloned().unwrap())
         } else {
@@ -214,28 +415,98 @@ impl<'a, K: KvStore> KvStore for RamMirrorKvStore<'a, K> {
         }
         Ok(())
     }
-    fn pairs(&self, prefix: StringKey) -> Result<HashMap<StringKey, Blob>, KvStoreError> {
-        let mut res = self.store.pairs(prefix.clone())?;
-        for (k, v) in self.overwrite.iter() {
-            if let Some(v) = v {
-                if k.0.starts_with(&prefix.0) {
-                    res.insert(k.clone(), v.clone());
+    fn scan(&self, query: ScanQuery) -> Result<KvIterator<'_>, KvStoreError> {
+        let base = self.store.scan(ScanQuery {
+            limit: None,
+            ..query.clone()
+        })?;
+        let overlay: OverlayIterator<'_> = {
+            let range = self.overwrite.range(query.bounds());
+            if query.reverse {
+                Box::new(range.rev())
+            } else {
+                Box::new(range)
+            }
+        };
+        let merged = MergedIterator {
+            base: base.peekable(),
+            overlay: overlay.peekable(),
+            reverse: query.reverse,
+        };
+        Ok(match query.limit {
+            Some(limit) => Box::new(merged.take(limit)),
+            None => Box::new(merged),
+        })
+    }
+    fn checksum(&self) -> Result<Checksum, KvStoreError> {
+        let mut checksum = self.store.checksum()?;
+        checksum.update(&self.to_ops(), |k| self.store.get(k.clone()))?;
+        Ok(checksum)
+    }
+}
+
+type OverlayIterator<'a> = Box<dyn Iterator<Item = (&'a DbKey, &'a Option<Blob>)> + 'a>;
+
+/// Merges the pairs of a store with the (ordered) changes applied on top of it
+struct MergedIterator<'a> {
+    base: Peekable<KvIterator<'a>>,
+    overlay: Peekable<OverlayIterator<'a>>,
+    reverse: bool,
+}
+
+impl<'a> Iterator for MergedIterator<'a> {
+    type Item = Result<(DbKey, Blob), KvStoreError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let from_overlay = match (self.base.peek(), self.overlay.peek()) {
+                (None, None) => return None,
+                (Some(Err(_)), _) => return self.base.next(),
+                (Some(_), None) => false,
+                (None, Some(_)) => true,
+                (Some(Ok((base_k, _))), Some((overlay_k, _))) => {
+                    if base_k == *overlay_k {
+                        self.base.next();
+                        true
+                    } else {
+                        (base_k > *overlay_k) != self.reverse
+                    }
+                }
+            };
+            if from_overlay {
+                let (k, v) = self.overlay.next().unwrap();
+                if let Some(v) = v {
+                    return Some(Ok((k.clone(), v.clone())));
                 }
             } else {
-                res.remove(k);
+                return self.base.next();
             }
         }
-        Ok(res)
     }
 }
 
+mod checksum;
+pub use checksum::*;
+
 mod ram;
 pub use ram::*;
 
+mod cache;
+pub use cache::*;
+
 #[cfg(feature = "db")]
 mod disk;
 #[cfg(feature = "db")]
 pub use disk::*;
 
+#[cfg(feature = "redb-db")]
+mod redb_disk;
+#[cfg(feature = "redb-db")]
+pub use redb_disk::*;
+
+#[cfg(test)]
+mod faulty;
+#[cfg(test)]
+pub use faulty::*;
+
 #[cfg(test)]
 mod test;
@@ -1,10 +1,10 @@
 use super::*;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
-pub struct RamKvStore(HashMap<String, Blob>);
+pub struct RamKvStore(BTreeMap<DbKey, Blob>, Checksum);
 impl RamKvStore {
     pub fn new() -> RamKvStore {
-        RamKvStore(HashMap::new())
+        RamKvStore(BTreeMap::new(), Checksum::default())
     }
 }
 
@@ -15,30 +15,35 @@ impl Default for RamKvStore {
 }
 
 impl KvStore for RamKvStore {
-    fn get(&self, k: StringKey) -> Result<Option<Blob>, KvStoreError> {
-        Ok(self.0.get(&k.0).cloned())
+    fn get(&self, k: DbKey) -> Result<Option<Blob>, KvStoreError> {
+        Ok(self.0.get(&k).cloned())
     }
     fn update(&mut self, ops: &[WriteOp]) -> Result<(), KvStoreError> {
+        self.1.update(ops, |k| Ok(self.0.get(k).cloned()))?;
         for op in ops.iter() {
             match op {
-                WriteOp::Remove(k) => self.0.remove(&k.0),
-                WriteOp::Put(k, v) => self.0.insert(k.0.clone(), v.clone()),
+                WriteOp::Remove(k) => self.0.remove(k),
+                WriteOp::Put(k, v) => self.0.insert(k.clone(), v.clone()),
             };
         }
         Ok(())
     }
-    fn pairs(&self, prefix: StringKey) -> Result<HashMap<StringKey, Blob>, KvStoreError> {
-        Ok(self
+    fn scan(&self, query: ScanQuery) -> Result<KvIterator<'_>, KvStoreError> {
+        let range = self
             .0
-            .clone()
-            .into_iter()
-            .filter_map(|(k, v)| {
-                if k.starts_with(&prefix.0) {
-                    Some((StringKey::new(&k), v))
-                } else {
-                    None
-                }
-            })
-            .collect())
+            .range(query.bounds())
+            .map(|(k, v)| Ok((k.clone(), v.clone())));
+        let it: KvIterator<'_> = if query.reverse {
+            Box::new(range.rev())
+        } else {
+            Box::new(range)
+        };
+        Ok(match query.limit {
+            Some(limit) => Box::new(it.take(limit)),
+            None => it,
+        })
+    }
+    fn checksum(&self) -> Result<Checksum, KvStoreError> {
+        Ok(self.1)
     }
 }
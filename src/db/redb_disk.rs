@@ -0,0 +1,141 @@
+use super::*;
+use redb::{Database, ReadTransaction, ReadableTable, TableDefinition};
+use std::fs;
+use std::path::Path;
+
+const TABLE: TableDefinition<&[u8], &[u8]> = TableDefinition::new("kv");
+// Data about the store itself, kept out of the keyspace of `TABLE`
+const META: TableDefinition<&str, &[u8]> = TableDefinition::new("meta");
+const CHECKSUM: &str = "checksum";
+const DATABASE_FILE: &str = "bazuka.redb";
+
+fn redb_error<E: Into<redb::Error>>(e: E) -> KvStoreError {
+    KvStoreError::from(e.into())
+}
+
+fn redb_get(txn: &ReadTransaction, k: DbKey) -> Result<Option<Blob>, KvStoreError> {
+    let table = txn.open_table(TABLE).map_err(redb_error)?;
+    let v = table.get(k.0.as_slice()).map_err(redb_error)?;
+    Ok(v.map(|v| Blob(v.value().to_vec())))
+}
+
+fn redb_checksum(txn: &ReadTransaction) -> Result<Checksum, KvStoreError> {
+    let meta = txn.open_table(META).map_err(redb_error)?;
+    let checksum = meta.get(CHECKSUM).map_err(redb_error)?;
+    Ok(match checksum {
+        Some(v) => bincode::deserialize(v.value())?,
+        None => Checksum::default(),
+    })
+}
+
+fn redb_scan(txn: &ReadTransaction, query: ScanQuery) -> Result<KvIterator<'static>, KvStoreError> {
+    let table = txn.open_table(TABLE).map_err(redb_error)?;
+    let (start, end) = query.bounds();
+    let range = table
+        .range::<&[u8]>((
+            start.as_ref().map(|k| k.0.as_slice()),
+            end.as_ref().map(|k| k.0.as_slice()),
+        ))
+        .map_err(redb_error)?
+        .map(|item| {
+            let (k, v) = item.map_err(redb_error)?;
+            Ok((DbKey(k.value().to_vec()), Blob(v.value().to_vec())))
+        });
+    let it: KvIterator<'static> = if query.reverse {
+        Box::new(range.rev())
+    } else {
+        Box::new(range)
+    };
+    Ok(match query.limit {
+        Some(limit) => Box::new(it.take(limit)),
+        None => it,
+    })
+}
+
+/// A pure-Rust alternative to `LevelDbKvStore`, stored in a single file
+/// inside the given directory.
+pub struct RedbKvStore(Database, Checksum);
+
+/// A consistent read-only view of a `RedbKvStore`, unaffected by later updates
+pub struct RedbSnapshot(ReadTransaction);
+
+impl RedbKvStore {
+    pub fn new(path: &Path) -> Result<RedbKvStore, KvStoreError> {
+        fs::create_dir_all(path)?;
+        let db = Database::create(path.join(DATABASE_FILE)).map_err(redb_error)?;
+        // Reading a table that was never written fails, so create them upfront
+        let txn = db.begin_write().map_err(redb_error)?;
+        txn.open_table(TABLE).map_err(redb_error)?;
+        txn.open_table(META).map_err(redb_error)?;
+        txn.commit().map_err(redb_error)?;
+        let checksum = redb_checksum(&db.begin_read().map_err(redb_error)?)?;
+        Ok(RedbKvStore(db, checksum))
+    }
+    pub fn snapshot(&self) -> Result<RedbSnapshot, KvStoreError> {
+        Ok(RedbSnapshot(self.0.begin_read().map_err(redb_error)?))
+    }
+}
+
+impl KvStore for RedbKvStore {
+    fn get(&self, k: DbKey) -> Result<Option<Blob>, KvStoreError> {
+        redb_get(&self.0.begin_read().map_err(redb_error)?, k)
+    }
+    fn update(&mut self, ops: &[WriteOp]) -> Result<(), KvStoreError> {
+        // Dropping the transaction without committing aborts it
+        let txn = self.0.begin_write().map_err(redb_error)?;
+        let mut checksum = self.1;
+        {
+            let mut table = txn.open_table(TABLE).map_err(redb_error)?;
+            checksum.update(ops, |k| {
+                let v = table.get(k.0.as_slice()).map_err(redb_error)?;
+                Ok(v.map(|v| Blob(v.value().to_vec())))
+            })?;
+            for op in ops.iter() {
+                match op {
+                    WriteOp::Remove(k) => {
+                        table.remove(k.0.as_slice()).map_err(redb_error)?;
+                    }
+                    WriteOp::Put(k, v) => {
+                        table
+                            .insert(k.0.as_slice(), v.0.as_slice())
+                            .map_err(redb_error)?;
+                    }
+                }
+            }
+            let mut meta = txn.open_table(META).map_err(redb_error)?;
+            meta.insert(CHECKSUM, bincode::serialize(&checksum)?.as_slice())
+                .map_err(redb_error)?;
+        }
+        txn.commit().map_err(redb_error)?;
+        self.1 = checksum;
+        Ok(())
+    }
+    fn scan(&self, query: ScanQuery) -> Result<KvIterator<'_>, KvStoreError> {
+        redb_scan(&self.0.begin_read().map_err(redb_error)?, query)
+    }
+    fn checksum(&self) -> Result<Checksum, KvStoreError> {
+        Ok(self.1)
+    }
+    fn backup_job(&self) -> Result<BackupJob, KvStoreError> {
+        let (snapshot, checksum) = (self.snapshot()?, self.1);
+        Ok(Box::new(move |path: &Path| {
+            check_backup_destination(path)?;
+            backup_snapshot(&snapshot, checksum, &mut RedbKvStore::new(path)?)
+        }))
+    }
+}
+
+impl KvStore for RedbSnapshot {
+    fn get(&self, k: DbKey) -> Result<Option<Blob>, KvStoreError> {
+        redb_get(&self.0, k)
+    }
+    fn update(&mut self, _: &[WriteOp]) -> Result<(), KvStoreError> {
+        panic!("Cannot update!");
+    }
+    fn scan(&self, query: ScanQuery) -> Result<KvIterator<'_>, KvStoreError> {
+        redb_scan(&self.0, query)
+    }
+    fn checksum(&self) -> Result<Checksum, KvStoreError> {
+        redb_checksum(&self.0)
+    }
+}
@@ -1,6 +1,9 @@
 use super::*;
+use crate::core::{Address, ContractId};
+use crate::zk::ZkDataLocator;
+use std::str::FromStr;
 
-#[cfg(feature = "db")]
+#[cfg(any(feature = "db", feature = "redb-db"))]
 use tempdir::TempDir;
 
 #[cfg(feature = "db")]
@@ -14,7 +17,7 @@ fn test_ram_and_disk_pair_prefix() -> Result<(), KvStoreError> {
     let mut ram = RamKvStore::default();
     let mut disk = temp_disk_store()?;
 
-    assert_eq!(ram.checksum::<Hasher>()?, disk.checksum::<Hasher>()?);
+    assert_eq!(ram.checksum()?, disk.checksum()?);
 
     let ops = &[
         WriteOp::Put("bc".into(), Blob(vec![0, 1, 2, 3])),
@@ -43,13 +46,162 @@ fn test_ram_and_disk_pair_prefix() -> Result<(), KvStoreError> {
     Ok(())
 }
 
+fn scan_keys<K: KvStore>(db: &K, query: ScanQuery) -> Result<Vec<String>, KvStoreError> {
+    db.scan(query)?
+        .map(|item| item.map(|(k, _)| String::from_utf8(k.0).unwrap()))
+        .collect()
+}
+
+fn assert_scans<K: KvStore>(db: &K) -> Result<(), KvStoreError> {
+    assert_eq!(
+        scan_keys(db, ScanQuery::all())?,
+        vec!["a0a", "aa", "bc", "bge", "def"]
+    );
+    assert_eq!(
+        scan_keys(db, ScanQuery::all().reverse())?,
+        vec!["def", "bge", "bc", "aa", "a0a"]
+    );
+    assert_eq!(
+        scan_keys(db, ScanQuery::prefix("b".into()))?,
+        vec!["bc", "bge"]
+    );
+    assert_eq!(
+        scan_keys(db, ScanQuery::prefix("b".into()).reverse())?,
+        vec!["bge", "bc"]
+    );
+    assert_eq!(
+        scan_keys(db, ScanQuery::prefix("".into()).limit(2))?,
+        vec!["a0a", "aa"]
+    );
+    assert_eq!(
+        scan_keys(db, ScanQuery::range("aa".into(), "bge".into()))?,
+        vec!["aa", "bc"]
+    );
+    assert_eq!(
+        scan_keys(
+            db,
+            ScanQuery::range("ab".into(), "zz".into())
+                .reverse()
+                .limit(2)
+        )?,
+        vec!["def", "bge"]
+    );
+    assert_eq!(scan_keys(db, ScanQuery::prefix("de".into()))?, vec!["def"]);
+    assert!(scan_keys(db, ScanQuery::prefix("x".into()))?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_ram_and_mirror_scan() -> Result<(), KvStoreError> {
+    let mut ram = RamKvStore::default();
+    ram.update(&[
+        WriteOp::Put("bc".into(), Blob(vec![0, 1, 2, 3])),
+        WriteOp::Put("aa".into(), Blob(vec![3, 2, 1, 0])),
+        WriteOp::Put("a0a".into(), Blob(vec![])),
+        WriteOp::Put("ccc".into(), Blob(vec![])),
+        WriteOp::Put("def".into(), Blob(vec![])),
+    ])?;
+
+    let mut mirror = ram.mirror();
+    mirror.update(&[
+        WriteOp::Remove("ccc".into()),
+        WriteOp::Put("bge".into(), Blob(vec![])),
+        WriteOp::Put("aa".into(), Blob(vec![1])),
+        WriteOp::Remove("zzz".into()),
+    ])?;
+    assert_scans(&mirror)?;
+    assert_eq!(mirror.get("aa".into())?, Some(Blob(vec![1])));
+    assert_eq!(
+        mirror
+            .scan(ScanQuery::prefix("aa".into()))?
+            .collect::<Result<Vec<_>, _>>()?,
+        vec![("aa".into(), Blob(vec![1]))]
+    );
+
+    ram.update(&[
+        WriteOp::Remove("ccc".into()),
+        WriteOp::Put("bge".into(), Blob(vec![])),
+    ])?;
+    assert_scans(&ram)?;
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "db")]
+fn test_disk_scan() -> Result<(), KvStoreError> {
+    let mut disk = temp_disk_store()?;
+    disk.update(&[
+        WriteOp::Put("bc".into(), Blob(vec![0, 1, 2, 3])),
+        WriteOp::Put("aa".into(), Blob(vec![3, 2, 1, 0])),
+        WriteOp::Put("a0a".into(), Blob(vec![])),
+        WriteOp::Put("bge".into(), Blob(vec![])),
+        WriteOp::Put("def".into(), Blob(vec![])),
+    ])?;
+    assert_scans(&disk)?;
+    Ok(())
+}
+
+#[cfg(feature = "redb-db")]
+fn temp_redb_store() -> Result<RedbKvStore, KvStoreError> {
+    RedbKvStore::new(TempDir::new("bazuka_test").unwrap().path())
+}
+
+#[test]
+#[cfg(feature = "redb-db")]
+fn test_redb_scan_and_snapshot() -> Result<(), KvStoreError> {
+    let mut redb = temp_redb_store()?;
+    redb.update(&[
+        WriteOp::Put("bc".into(), Blob(vec![0, 1, 2, 3])),
+        WriteOp::Put("aa".into(), Blob(vec![3, 2, 1, 0])),
+        WriteOp::Put("a0a".into(), Blob(vec![])),
+        WriteOp::Put("bge".into(), Blob(vec![])),
+        WriteOp::Put("def".into(), Blob(vec![])),
+    ])?;
+    assert_scans(&redb)?;
+
+    let snapshot = redb.snapshot()?;
+    redb.update(&[
+        WriteOp::Remove("aa".into()),
+        WriteOp::Put("bc".into(), Blob(vec![1])),
+    ])?;
+    assert_scans(&snapshot)?;
+    assert_eq!(snapshot.get("aa".into())?, Some(Blob(vec![3, 2, 1, 0])));
+    assert_eq!(redb.get("aa".into())?, None);
+    assert_eq!(redb.get("bc".into())?, Some(Blob(vec![1])));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "redb-db")]
+fn test_redb_copy_store() -> Result<(), KvStoreError> {
+    let mut ram = RamKvStore::default();
+    let mut redb = temp_redb_store()?;
+    assert_eq!(ram.checksum()?, redb.checksum()?);
+
+    ram.update(
+        &(0..25u32)
+            .map(|i| WriteOp::Put(format!("k{}", i).into(), i.into()))
+            .collect::<Vec<_>>(),
+    )?;
+    assert_eq!(copy_store(&ram, &mut redb, 10)?, 25);
+    assert_eq!(ram.checksum()?, redb.checksum()?);
+
+    let mut copy = RamKvStore::default();
+    assert_eq!(copy_store(&redb, &mut copy, 7)?, 25);
+    assert_eq!(ram.checksum()?, copy.checksum()?);
+
+    Ok(())
+}
+
 #[test]
 #[cfg(feature = "db")]
 fn test_ram_and_disk_db_consistency() -> Result<(), KvStoreError> {
     let mut ram = RamKvStore::default();
     let mut disk = temp_disk_store()?;
 
-    assert_eq!(ram.checksum::<Hasher>()?, disk.checksum::<Hasher>()?);
+    assert_eq!(ram.checksum()?, disk.checksum()?);
 
     let ops = &[
         WriteOp::Put("bc".into(), Blob(vec![0, 1, 2, 3])),
@@ -60,7 +212,7 @@ fn test_ram_and_disk_db_consistency() -> Result<(), KvStoreError> {
     ram.update(ops)?;
     disk.update(ops)?;
 
-    assert_eq!(ram.checksum::<Hasher>()?, disk.checksum::<Hasher>()?);
+    assert_eq!(ram.checksum()?, disk.checksum()?);
 
     let new_ops = &[
         WriteOp::Remove("aa".into()),
@@ -71,7 +223,7 @@ fn test_ram_and_disk_db_consistency() -> Result<(), KvStoreError> {
     ram.update(new_ops)?;
     disk.update(new_ops)?;
 
-    assert_eq!(ram.checksum::<Hasher>()?, disk.checksum::<Hasher>()?);
+    assert_eq!(ram.checksum()?, disk.checksum()?);
 
     Ok(())
 }
@@ -88,7 +240,7 @@ fn test_mirror_kv_store() -> Result<(), KvStoreError> {
 
     ram.update(ops)?;
 
-    let prev_ram_checksum = ram.checksum::<Hasher>()?;
+    let prev_ram_checksum = ram.checksum()?;
 
     let mut mirror = RamMirrorKvStore::new(&ram);
 
@@ -100,15 +252,15 @@ fn test_mirror_kv_store() -> Result<(), KvStoreError> {
 
     mirror.update(ops_on_mirror)?;
 
-    let mirror_checksum = mirror.checksum::<Hasher>()?;
+    let mirror_checksum = mirror.checksum()?;
 
     let mirror_ops = mirror.to_ops();
 
-    assert_eq!(ram.checksum::<Hasher>()?, prev_ram_checksum);
+    assert_eq!(ram.checksum()?, prev_ram_checksum);
 
     ram.update(&mirror_ops)?;
 
-    assert_eq!(ram.checksum::<Hasher>()?, mirror_checksum);
+    assert_eq!(ram.checksum()?, mirror_checksum);
 
     Ok(())
 }
@@ -156,3 +308,436 @@ fn test_mirror_rollback() -> Result<(), KvStoreError> {
 
     Ok(())
 }
+
+#[test]
+fn test_scan_errors() -> Result<(), KvStoreError> {
+    let mut ram = RamKvStore::default();
+    ram.update(&[
+        WriteOp::Put("aa".into(), Blob(vec![1])),
+        WriteOp::Put("bb".into(), Blob(vec![2])),
+        WriteOp::Put("cc".into(), Blob(vec![3])),
+    ])?;
+    let store = FaultyKvStore::new(ram).fail_scan_at(1, Fault::Error);
+
+    // Unreadable pairs are errors of the iteration, also through a mirror
+    let mut mirror = store.mirror();
+    mirror.update(&[WriteOp::Put("ab".into(), Blob(vec![4]))])?;
+    let pairs = mirror.scan(ScanQuery::all())?.collect::<Vec<_>>();
+    assert_eq!(pairs.len(), 4);
+    assert!(matches!(pairs[1], Err(KvStoreError::Failure)));
+    assert_eq!(pairs.iter().filter(|p| p.is_err()).count(), 1);
+    assert!(store.triggered());
+
+    let store = store.fail_scan_at(2, Fault::Error);
+    assert!(matches!(
+        copy_store(&store, &mut RamKvStore::default(), 10),
+        Err(KvStoreError::Failure)
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_schema_migrations() -> Result<(), KvStoreError> {
+    let mut fresh = RamKvStore::default();
+    assert_eq!(migrations::schema_version(&fresh)?, None);
+    assert!(migrations::migrate(&mut fresh)?.is_empty());
+    assert_eq!(
+        migrations::schema_version(&fresh)?,
+        Some(migrations::SCHEMA_VERSION)
+    );
+
+    let mut legacy = RamKvStore::default();
+    legacy.update(&[WriteOp::Put("HGT".into(), 10u64.into())])?;
+    assert_eq!(migrations::schema_version(&legacy)?, Some(0));
+    assert_eq!(
+        migrations::pending_migrations(&legacy)?.len(),
+        migrations::MIGRATIONS.len()
+    );
+    assert_eq!(
+        migrations::migrate(&mut legacy)?.len(),
+        migrations::MIGRATIONS.len()
+    );
+    assert_eq!(
+        migrations::schema_version(&legacy)?,
+        Some(migrations::SCHEMA_VERSION)
+    );
+    assert!(migrations::pending_migrations(&legacy)?.is_empty());
+    assert_eq!(legacy.get(keys::height())?, Some(10u64.into()));
+
+    let mut newer = RamKvStore::default();
+    newer.update(&[WriteOp::Put(
+        keys::schema_version(),
+        (migrations::SCHEMA_VERSION + 1).into(),
+    )])?;
+    assert!(matches!(
+        migrations::migrate(&mut newer),
+        Err(KvStoreError::SchemaTooNew(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_binary_keys() {
+    let id = ContractId::from_str(&"ab".repeat(32)).unwrap();
+    assert!(keys::block(9) < keys::block(10));
+    assert!(keys::block(255) < keys::block(256));
+    assert!(keys::local_rollback_to_height(&id, 9) < keys::local_rollback_to_height(&id, 10));
+    assert_eq!(keys::account(&Address::Treasury).0.len(), 2);
+    assert_ne!(
+        keys::local_tree_aux(&id, &ZkDataLocator(vec![1]), 2),
+        keys::local_tree_aux(&id, &ZkDataLocator(vec![1, 2]), 2)
+    );
+
+    let loc = ZkDataLocator(vec![3, 0x1234]);
+    for is_scalar in [true, false] {
+        let k = keys::local_value(&id, &loc, is_scalar);
+        assert_eq!(keys::local_value_locator(&k), Some(loc.clone()));
+        assert!(k.0.starts_with(&keys::local_prefix(&id).0));
+    }
+    assert!(ScanQuery::prefix(keys::local_scalar_value_prefix(&id))
+        .contains(&keys::local_value(&id, &loc, true)));
+    assert!(!ScanQuery::prefix(keys::local_scalar_value_prefix(&id))
+        .contains(&keys::local_value(&id, &loc, false)));
+    assert_eq!(keys::local_value_locator(&keys::local_root(&id)), None);
+
+    let (pk, _) = <crate::core::Signer as crate::crypto::SignatureScheme>::generate_keys(b"ABC");
+    for addr in [Address::Treasury, Address::PublicKey(pk)] {
+        assert_eq!(keys::account_address(&keys::account(&addr)), Some(addr));
+    }
+    assert_eq!(
+        keys::contract_account_id(&keys::contract_account(&id)),
+        Some(id)
+    );
+    assert_eq!(keys::account_address(&keys::contract_account(&id)), None);
+    assert_eq!(keys::contract_account_id(&keys::contract(&id)), None);
+    assert_eq!(keys::contract_id(&keys::contract(&id)), Some(id));
+    assert_eq!(keys::contract_id(&keys::contract_account(&id)), None);
+}
+
+fn legacy_string_keys_db(id: &ContractId) -> Result<RamKvStore, KvStoreError> {
+    let mut legacy = RamKvStore::default();
+    legacy.update(&[
+        WriteOp::Put(keys::schema_version(), 1u32.into()),
+        WriteOp::Put("HGT".into(), 2u64.into()),
+        WriteOp::Put("POW-0000000001".into(), 5u128.into()),
+        WriteOp::Put("ACC-Treasury".into(), 7u64.into()),
+        WriteOp::Put(format!("CSA-0000000001-{}", id).into(), 8u64.into()),
+        WriteOp::Put(format!("S-{}-HGT", id).into(), 1u64.into()),
+        WriteOp::Put(format!("S-{}-S-3_1a", id).into(), 9u64.into()),
+        WriteOp::Put(format!("S-{}-3-T-12", id).into(), 10u64.into()),
+        WriteOp::Put(format!("S-{}-RLK-11", id).into(), 11u64.into()),
+        WriteOp::Put(
+            "RLK-0000000001".into(),
+            vec![
+                WriteOp::Put("HGT".into(), 1u64.into()),
+                WriteOp::Remove("ACC-Treasury".into()),
+            ]
+            .into(),
+        ),
+    ])?;
+    Ok(legacy)
+}
+
+#[test]
+fn test_string_keys_migration() -> Result<(), KvStoreError> {
+    let id = ContractId::from_str(&"ab".repeat(32)).unwrap();
+    let mut legacy = legacy_string_keys_db(&id)?;
+    assert_eq!(migrations::migrate(&mut legacy)?.len(), 2);
+
+    let migrated = legacy.pairs("".into())?;
+    assert_eq!(migrated.len(), 10);
+    assert_eq!(migrated[&keys::height()], 2u64.into());
+    assert_eq!(migrated[&keys::power(1)], 5u128.into());
+    assert_eq!(migrated[&keys::account(&Address::Treasury)], 7u64.into());
+    assert_eq!(migrated[&keys::compressed_state_at(&id, 1)], 8u64.into());
+    assert_eq!(migrated[&keys::local_height(&id)], 1u64.into());
+    assert_eq!(
+        migrated[&keys::local_value(&id, &ZkDataLocator(vec![3, 0x1a]), true)],
+        9u64.into()
+    );
+    assert_eq!(
+        migrated[&keys::local_tree_aux(&id, &ZkDataLocator(vec![3]), 12)],
+        10u64.into()
+    );
+    assert_eq!(
+        migrated[&keys::local_rollback_to_height(&id, 11)],
+        11u64.into()
+    );
+    let rollback: Vec<WriteOp> = migrated[&keys::rollback(1)].clone().try_into()?;
+    assert_eq!(
+        rollback,
+        vec![
+            WriteOp::Put(keys::height(), 1u64.into()),
+            WriteOp::Remove(keys::account(&Address::Treasury)),
+        ]
+    );
+
+    let mut unknown = RamKvStore::default();
+    unknown.update(&[
+        WriteOp::Put(keys::schema_version(), 1u32.into()),
+        WriteOp::Put("XYZ-1".into(), 1u64.into()),
+    ])?;
+    assert!(matches!(
+        migrations::migrate(&mut unknown),
+        Err(KvStoreError::MigrationFailed(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_string_keys_migration_resumes() -> Result<(), KvStoreError> {
+    let id = ContractId::from_str(&"ab".repeat(32)).unwrap();
+    let mut expected = legacy_string_keys_db(&id)?;
+    migrations::migrate(&mut expected)?;
+
+    let mut interrupted =
+        FaultyKvStore::new(legacy_string_keys_db(&id)?).fail_update_at(2, Fault::Error);
+    assert!(matches!(
+        migrations::migrate_in_batches(&mut interrupted, 3),
+        Err(KvStoreError::Failure)
+    ));
+    let mut resumed = interrupted.into_inner();
+    assert_eq!(migrations::schema_version(&resumed)?, Some(1));
+    assert!(resumed.get(keys::migration_progress())?.is_some());
+    assert_eq!(resumed.get(keys::height())?, Some(2u64.into()));
+
+    assert_eq!(migrations::migrate_in_batches(&mut resumed, 3)?.len(), 2);
+    assert_eq!(resumed.get(keys::migration_progress())?, None);
+    assert_eq!(resumed.pairs("".into())?, expected.pairs("".into())?);
+
+    Ok(())
+}
+
+#[test]
+fn test_cached_kv_store() -> Result<(), KvStoreError> {
+    let mut ram = RamKvStore::default();
+    ram.update(&[
+        WriteOp::Put("aa".into(), Blob(vec![1, 2, 3])),
+        WriteOp::Put("bb".into(), Blob(vec![4, 5, 6])),
+        WriteOp::Put("cc".into(), Blob(vec![7, 8, 9])),
+    ])?;
+    let mut cached = CachedKvStore::new(ram, 10);
+
+    assert_eq!(cached.get("aa".into())?, Some(Blob(vec![1, 2, 3])));
+    assert_eq!(cached.get("aa".into())?, Some(Blob(vec![1, 2, 3])));
+    assert_eq!(cached.get("xx".into())?, None);
+    assert_eq!(cached.get("xx".into())?, None);
+    let stats = cached.cache_stats().unwrap();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (2, 2, 2));
+    assert_eq!(stats.size, 7);
+
+    // Reading "bb" evicts the least recently used entry ("aa")
+    cached.get("xx".into())?;
+    cached.get("bb".into())?;
+    let stats = cached.cache_stats().unwrap();
+    assert_eq!((stats.evictions, stats.entries, stats.size), (1, 2, 7));
+    cached.get("xx".into())?;
+    assert_eq!(cached.cache_stats().unwrap().hits, 4);
+
+    cached.update(&[
+        WriteOp::Put("xx".into(), Blob(vec![0])),
+        WriteOp::Remove("bb".into()),
+    ])?;
+    assert_eq!(cached.get("xx".into())?, Some(Blob(vec![0])));
+    assert_eq!(cached.get("bb".into())?, None);
+
+    // Values bigger than the whole cache are never cached
+    cached.update(&[WriteOp::Put("big".into(), Blob(vec![0; 20]))])?;
+    cached.get("big".into())?;
+    cached.get("big".into())?;
+    assert_eq!(cached.cache_stats().unwrap().misses, 7);
+
+    Ok(())
+}
+
+fn assert_checksum_consistent<K: KvStore>(db: &mut K) -> Result<(), KvStoreError> {
+    let batches = vec![
+        vec![
+            WriteOp::Put("aa".into(), Blob(vec![1])),
+            WriteOp::Put("bb".into(), Blob(vec![2])),
+            WriteOp::Put("aa".into(), Blob(vec![3])),
+        ],
+        vec![
+            WriteOp::Remove("bb".into()),
+            WriteOp::Remove("cc".into()),
+            WriteOp::Put("bb".into(), Blob(vec![4])),
+        ],
+        vec![
+            WriteOp::Put("aa".into(), Blob(vec![3])),
+            WriteOp::Remove("bb".into()),
+        ],
+    ];
+    for batch in batches {
+        db.update(&batch)?;
+        assert_eq!(
+            db.checksum()?,
+            Checksum::of_scan(db.scan(ScanQuery::all())?)?
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_incremental_checksum() -> Result<(), KvStoreError> {
+    let mut ram = RamKvStore::default();
+    assert_checksum_consistent(&mut ram)?;
+    assert_eq!(
+        ram.checksum()?,
+        Checksum::of_pairs(vec![("aa".into(), Blob(vec![3]))])
+    );
+
+    let mut mirror = ram.mirror();
+    mirror.update(&[
+        WriteOp::Remove("aa".into()),
+        WriteOp::Put("dd".into(), Blob(vec![5])),
+    ])?;
+    assert_eq!(
+        mirror.checksum()?,
+        Checksum::of_pairs(vec![("dd".into(), Blob(vec![5]))])
+    );
+    assert_checksum_consistent(&mut mirror)?;
+
+    let mut cached = CachedKvStore::new(RamKvStore::default(), 1024);
+    assert_checksum_consistent(&mut cached)?;
+    assert_eq!(cached.checksum()?, ram.checksum()?);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "db")]
+fn test_disk_incremental_checksum() -> Result<(), KvStoreError> {
+    let dir = TempDir::new("bazuka_test").unwrap();
+    let checksum = {
+        let mut disk = LevelDbKvStore::new(dir.path(), 64)?;
+        assert_checksum_consistent(&mut disk)?;
+        disk.checksum()?
+    };
+    // Persisted along with the data, and hidden from scans
+    let disk = LevelDbKvStore::new(dir.path(), 64)?;
+    assert_eq!(disk.checksum()?, checksum);
+    assert_eq!(
+        Checksum::of_scan(disk.scan(ScanQuery::all().reverse())?)?,
+        checksum
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "redb-db")]
+fn test_redb_incremental_checksum() -> Result<(), KvStoreError> {
+    let dir = TempDir::new("bazuka_test").unwrap();
+    let checksum = {
+        let mut redb = RedbKvStore::new(dir.path())?;
+        assert_checksum_consistent(&mut redb)?;
+        assert_eq!(redb.snapshot()?.checksum()?, redb.checksum()?);
+        redb.checksum()?
+    };
+    // Persisted along with the data
+    assert_eq!(RedbKvStore::new(dir.path())?.checksum()?, checksum);
+    Ok(())
+}
+
+#[test]
+fn test_ram_backup_unsupported() {
+    assert!(matches!(
+        RamKvStore::default().backup(Path::new("/nonexistent")),
+        Err(KvStoreError::BackupUnsupported)
+    ));
+}
+
+#[test]
+#[cfg(feature = "db")]
+fn test_disk_backup() -> Result<(), KvStoreError> {
+    let mut disk = temp_disk_store()?;
+    disk.update(&[
+        WriteOp::Put("aa".into(), Blob(vec![1, 2])),
+        WriteOp::Put("bb".into(), Blob(vec![3])),
+    ])?;
+    let dir = TempDir::new("bazuka_test").unwrap();
+    let backup_path = dir.path().join("backup");
+    assert_eq!(disk.backup(&backup_path)?, 2);
+    assert!(matches!(
+        disk.backup(&backup_path),
+        Err(KvStoreError::BackupDestinationNotEmpty(_))
+    ));
+
+    let backup = LevelDbKvStore::new(&backup_path, 64)?;
+    assert_eq!(backup.checksum()?, disk.checksum()?);
+    assert_eq!(backup.get("aa".into())?, Some(Blob(vec![1, 2])));
+
+    // The job copies the snapshot, whatever happens to the store meanwhile
+    let checksum = disk.checksum()?;
+    let job = disk.backup_job()?;
+    disk.update(&[WriteOp::Remove("aa".into())])?;
+    drop(disk);
+    let other_path = dir.path().join("other");
+    let copy_path = other_path.clone();
+    assert_eq!(
+        std::thread::spawn(move || job(&copy_path))
+            .join()
+            .unwrap()?,
+        2
+    );
+    assert_eq!(LevelDbKvStore::new(&other_path, 64)?.checksum()?, checksum);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "redb-db")]
+fn test_redb_backup() -> Result<(), KvStoreError> {
+    let mut redb = temp_redb_store()?;
+    redb.update(&[
+        WriteOp::Put("aa".into(), Blob(vec![1, 2])),
+        WriteOp::Put("bb".into(), Blob(vec![3])),
+    ])?;
+    let dir = TempDir::new("bazuka_test").unwrap();
+    let backup_path = dir.path().join("backup");
+    assert_eq!(redb.backup(&backup_path)?, 2);
+    assert!(matches!(
+        redb.backup(&backup_path),
+        Err(KvStoreError::BackupDestinationNotEmpty(_))
+    ));
+
+    let backup = RedbKvStore::new(&backup_path)?;
+    assert_eq!(backup.checksum()?, redb.checksum()?);
+    assert_eq!(backup.get("bb".into())?, Some(Blob(vec![3])));
+
+    // The job copies the snapshot, whatever happens to the store meanwhile
+    let checksum = redb.checksum()?;
+    let job = redb.backup_job()?;
+    redb.update(&[WriteOp::Remove("bb".into())])?;
+    let other_path = dir.path().join("other");
+    let copy_path = other_path.clone();
+    assert_eq!(
+        std::thread::spawn(move || job(&copy_path))
+            .join()
+            .unwrap()?,
+        2
+    );
+    assert_eq!(RedbKvStore::new(&other_path)?.checksum()?, checksum);
+    Ok(())
+}
+
+#[test]
+fn test_faulty_kv_store() -> Result<(), KvStoreError> {
+    let mut store = FaultyKvStore::new(RamKvStore::default())
+        .fail_update_at(1, Fault::Error)
+        .fail_get_at(2, Fault::Panic);
+    store.update(&[WriteOp::Put("a".into(), 1u64.into())])?;
+    assert!(matches!(
+        store.update(&[WriteOp::Put("b".into(), 2u64.into())]),
+        Err(KvStoreError::Failure)
+    ));
+    assert!(store.triggered());
+    store.update(&[WriteOp::Put("c".into(), 3u64.into())])?;
+    assert!(store.get("a".into())?.is_some());
+    assert!(store.get("b".into())?.is_none());
+    assert!(std::panic::catch_unwind(|| store.get("c".into())).is_err());
+    let inner = store.into_inner();
+    assert_eq!(inner.pairs("".into())?.len(), 2);
+    assert!(inner.get("c".into())?.is_some());
+    Ok(())
+}
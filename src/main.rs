@@ -11,29 +11,52 @@ use {
 
 #[cfg(feature = "node")]
 use {
-    bazuka::blockchain::KvStoreChain,
+    bazuka::blockchain::{
+        export_chain, import_chain, verify_chain, Blockchain, BlockchainConfig,
+        BlockchainErrorInfo, KvStoreChain,
+    },
     bazuka::client::{messages::SocialProfiles, Limit, NodeRequest, PeerAddress},
     bazuka::common::*,
     bazuka::config,
-    bazuka::db::LevelDbKvStore,
+    bazuka::db::{
+        copy_store, migrations, verify_checksum, CachedKvStore, KvStore, KvStoreError, RamKvStore,
+        ScanQuery,
+    },
     bazuka::node::{node_create, Firewall},
     colored::Colorize,
     hyper::server::conn::AddrStream,
     hyper::service::{make_service_fn, service_fn},
     hyper::{Body, Client, Request, Response, Server, StatusCode},
-    std::path::{Path, PathBuf},
+    std::io::Write,
+    std::path::Path,
+    std::str::FromStr,
     std::sync::Arc,
     tokio::sync::mpsc,
     tokio::try_join,
 };
 
+#[cfg(feature = "db")]
+use bazuka::db::LevelDbKvStore;
+
+#[cfg(feature = "redb-db")]
+use bazuka::db::RedbKvStore;
+
+#[cfg(all(feature = "node", not(any(feature = "db", feature = "redb-db"))))]
+compile_error!("The node feature needs a database backend, enable `db` or `redb-db`");
+
 #[cfg(feature = "client")]
 use {
     bazuka::client::{BazukaClient, NodeError},
+    bazuka::config::chain_spec::ChainSpec,
     bazuka::core::{ContractId, Money, Signer, ZkSigner},
     bazuka::crypto::{SignatureScheme, ZkSignatureScheme},
+    bazuka::zk::{
+        groth16::{Groth16Format, Groth16Proof, Groth16VerifyingKey},
+        ZkProof, ZkVerifierKey,
+    },
     serde::{Deserialize, Serialize},
     std::net::SocketAddr,
+    std::path::PathBuf,
     structopt::StructOpt,
 };
 
@@ -74,13 +97,47 @@ enum CliOptions {
         client_only: bool,
         #[structopt(long, parse(from_os_str))]
         db: Option<PathBuf>,
+        /// Storage engine of the database: leveldb or redb
+        #[structopt(long)]
+        db_backend: Option<DbBackend>,
         #[structopt(long)]
         bootstrap: Vec<String>,
-        #[structopt(long, default_value = "mainnet")]
-        network: String,
+        /// Name of the network, defaults to the one in the chain-spec
+        #[structopt(long)]
+        network: Option<String>,
+        /// Path to a YAML/JSON chain-spec file, defaults to the built-in mainnet spec
+        #[structopt(long, parse(from_os_str))]
+        chain_spec: Option<PathBuf>,
         #[structopt(long)]
         discord_handle: Option<String>,
     },
+    /// Manage chain-spec files
+    ChainSpec(ChainSpecOptions),
+    /// Work with zero-knowledge verifying keys and proofs
+    Zk(ZkOptions),
+    #[cfg(feature = "node")]
+    /// Manage the node database
+    Db(DbOptions),
+    #[cfg(feature = "node")]
+    /// Export/import blocks of the node database
+    Chain(ChainOptions),
+    #[cfg(feature = "node")]
+    /// Replay the blocks of the node database and check the stored state, while the node is stopped
+    VerifyChain {
+        #[structopt(long, parse(from_os_str))]
+        db: Option<PathBuf>,
+        #[structopt(long)]
+        db_backend: Option<DbBackend>,
+        /// Network of the database, defaults to the one in the chain-spec
+        #[structopt(long)]
+        network: Option<String>,
+        /// Path to a YAML/JSON chain-spec file, defaults to the built-in mainnet spec
+        #[structopt(long, parse(from_os_str))]
+        chain_spec: Option<PathBuf>,
+        /// Replay in memory instead of a temporary database
+        #[structopt(long)]
+        in_memory: bool,
+    },
     /// Get status of a node
     Status {},
     /// Get wallet info
@@ -89,8 +146,10 @@ enum CliOptions {
     Deposit {
         #[structopt(long)]
         contract: String,
+        /// Index of the account, looked up by the zk-address of the wallet if
+        /// not given
         #[structopt(long)]
-        index: u32,
+        index: Option<u32>,
         #[structopt(long)]
         amount: Money,
         #[structopt(long, default_value = "0")]
@@ -100,8 +159,10 @@ enum CliOptions {
     Withdraw {
         #[structopt(long)]
         contract: String,
+        /// Index of the account, looked up by the zk-address of the wallet if
+        /// not given
         #[structopt(long)]
-        index: u32,
+        index: Option<u32>,
         #[structopt(long)]
         amount: Money,
         #[structopt(long, default_value = "0")]
@@ -118,10 +179,12 @@ enum CliOptions {
     },
     /// Send funds through a zero-transaction
     Zsend {
+        /// Looked up by the zk-address of the wallet if not given
         #[structopt(long)]
-        from_index: u32,
+        from_index: Option<u32>,
+        /// Looked up by the destination zk-address if not given
         #[structopt(long)]
-        to_index: u32,
+        to_index: Option<u32>,
         #[structopt(long)]
         to: String,
         #[structopt(long)]
@@ -131,6 +194,206 @@ enum CliOptions {
     },
 }
 
+#[derive(StructOpt)]
+#[cfg(feature = "client")]
+enum ChainSpecOptions {
+    /// Print the chain-spec of a built-in network
+    Export {
+        #[structopt(long, default_value = "mainnet")]
+        network: String,
+        #[structopt(long)]
+        json: bool,
+    },
+}
+
+#[derive(StructOpt)]
+#[cfg(feature = "client")]
+enum ZkOptions {
+    /// Convert a Groth16 verifying key (or proof) made by other tooling to the encoding of Bazuka
+    Convert {
+        /// Format of the file: snarkjs, arkworks or bellman
+        #[structopt(long)]
+        format: Groth16Format,
+        /// Convert a proof instead of a verifying key
+        #[structopt(long)]
+        proof: bool,
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+        /// Print as JSON instead of hex-encoded bytes
+        #[structopt(long)]
+        json: bool,
+    },
+}
+
+#[derive(StructOpt)]
+#[cfg(feature = "node")]
+enum DbOptions {
+    /// Upgrade the database to the latest schema version
+    Migrate {
+        #[structopt(long, parse(from_os_str))]
+        db: Option<PathBuf>,
+        #[structopt(long)]
+        db_backend: Option<DbBackend>,
+        /// Only print the pending migration steps
+        #[structopt(long)]
+        dry_run: bool,
+    },
+    /// Copy a database to a new one with a different storage engine
+    Convert {
+        #[structopt(long, parse(from_os_str))]
+        src: PathBuf,
+        #[structopt(long)]
+        src_backend: DbBackend,
+        #[structopt(long, parse(from_os_str))]
+        dst: PathBuf,
+        #[structopt(long)]
+        dst_backend: DbBackend,
+    },
+    /// Ask the running node for a consistent copy of its database
+    Backup {
+        #[structopt(parse(from_os_str))]
+        dir: PathBuf,
+    },
+    /// Replace the database with a backup, while the node is stopped
+    Restore {
+        #[structopt(parse(from_os_str))]
+        backup: PathBuf,
+        #[structopt(long, parse(from_os_str))]
+        db: Option<PathBuf>,
+        #[structopt(long)]
+        db_backend: Option<DbBackend>,
+        /// Network of the backup, defaults to the one in the chain-spec
+        #[structopt(long)]
+        network: Option<String>,
+        /// Path to a YAML/JSON chain-spec file, defaults to the built-in mainnet spec
+        #[structopt(long, parse(from_os_str))]
+        chain_spec: Option<PathBuf>,
+    },
+}
+
+#[derive(StructOpt)]
+#[cfg(feature = "node")]
+enum ChainOptions {
+    /// Write blocks of the database to a file, while the node is stopped
+    Export {
+        #[structopt(long, default_value = "0")]
+        from: u64,
+        /// Height to stop at (exclusive), defaults to the current height
+        #[structopt(long)]
+        to: Option<u64>,
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+        #[structopt(long, parse(from_os_str))]
+        db: Option<PathBuf>,
+        #[structopt(long)]
+        db_backend: Option<DbBackend>,
+        /// Network of the database, defaults to the one in the chain-spec
+        #[structopt(long)]
+        network: Option<String>,
+        /// Path to a YAML/JSON chain-spec file, defaults to the built-in mainnet spec
+        #[structopt(long, parse(from_os_str))]
+        chain_spec: Option<PathBuf>,
+    },
+    /// Apply the blocks of an exported file, resuming from the current height
+    Import {
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+        #[structopt(long, parse(from_os_str))]
+        db: Option<PathBuf>,
+        #[structopt(long)]
+        db_backend: Option<DbBackend>,
+        /// Network of the database, defaults to the one in the chain-spec
+        #[structopt(long)]
+        network: Option<String>,
+        /// Path to a YAML/JSON chain-spec file, defaults to the built-in mainnet spec
+        #[structopt(long, parse(from_os_str))]
+        chain_spec: Option<PathBuf>,
+    },
+}
+
+#[cfg(feature = "node")]
+#[derive(Clone, Copy, Debug)]
+enum DbBackend {
+    #[cfg(feature = "db")]
+    LevelDb,
+    #[cfg(feature = "redb-db")]
+    Redb,
+}
+
+#[cfg(feature = "node")]
+impl Default for DbBackend {
+    #[cfg(feature = "db")]
+    fn default() -> Self {
+        DbBackend::LevelDb
+    }
+    #[cfg(not(feature = "db"))]
+    fn default() -> Self {
+        DbBackend::Redb
+    }
+}
+
+#[cfg(feature = "node")]
+impl FromStr for DbBackend {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            #[cfg(feature = "db")]
+            "leveldb" => Ok(DbBackend::LevelDb),
+            #[cfg(feature = "redb-db")]
+            "redb" => Ok(DbBackend::Redb),
+            _ => Err(format!("database backend {} is not available", s)),
+        }
+    }
+}
+
+// Number of blocks between progress reports of chain export/import
+#[cfg(feature = "node")]
+const PROGRESS_INTERVAL: u64 = 1000;
+
+#[cfg(feature = "node")]
+fn open_db(
+    backend: DbBackend,
+    path: &Path,
+) -> Result<Box<dyn KvStore + Send + Sync>, KvStoreError> {
+    Ok(match backend {
+        #[cfg(feature = "db")]
+        DbBackend::LevelDb => Box::new(LevelDbKvStore::new(path, 64)?),
+        #[cfg(feature = "redb-db")]
+        DbBackend::Redb => Box::new(RedbKvStore::new(path)?),
+    })
+}
+
+#[cfg(feature = "node")]
+fn load_chain_spec(
+    network: Option<&str>,
+    chain_spec: Option<PathBuf>,
+) -> Result<ChainSpec, NodeError> {
+    Ok(match chain_spec {
+        Some(path) => ChainSpec::load(&path)?,
+        None => ChainSpec::builtin(network.unwrap_or("mainnet"))?,
+    })
+}
+
+/// Copies the files of a database directory, e.g. a backup
+#[cfg(feature = "node")]
+fn copy_dir(src: &Path, dst: &Path) -> Result<(), std::io::Error> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &dst.join(entry.file_name()))?;
+        } else {
+            std::fs::copy(entry.path(), dst.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "node")]
+fn bazuka_dir(db: Option<PathBuf>) -> PathBuf {
+    db.unwrap_or_else(|| home::home_dir().unwrap().join(Path::new(".bazuka")))
+}
+
 #[cfg(feature = "node")]
 async fn run_node(
     bazuka_config: BazukaConfig,
@@ -138,9 +401,10 @@ async fn run_node(
     listen: Option<SocketAddr>,
     external: Option<SocketAddr>,
     client_only: bool,
-    db: Option<PathBuf>,
+    database: Box<dyn KvStore + Send + Sync>,
     bootstrap: Vec<String>,
     network: String,
+    blockchain_config: BlockchainConfig,
 ) -> Result<(), NodeError> {
     let (_pub_key, priv_key) = Signer::generate_keys(&bazuka_config.seed.as_bytes());
 
@@ -195,8 +459,6 @@ async fn run_node(
         }
     };
 
-    let bazuka_dir = db.unwrap_or_else(|| home::home_dir().unwrap().join(Path::new(".bazuka")));
-
     // 60 request per minute / 4GB per 15min
     let firewall = Firewall::new(60, 4 * GB);
 
@@ -208,11 +470,7 @@ async fn run_node(
         address,
         priv_key,
         bootstrap_nodes,
-        KvStoreChain::new(
-            LevelDbKvStore::new(&bazuka_dir, 64).unwrap(),
-            config::blockchain::get_blockchain_config(),
-        )
-        .unwrap(),
+        KvStoreChain::new(CachedKvStore::new(database, 64 * MB), blockchain_config).unwrap(),
         0,
         Some(wallet),
         social_profiles,
@@ -248,8 +506,14 @@ async fn run_node(
                                 match resp_rcv.recv().await.ok_or(NodeError::NotAnsweringError)? {
                                     Ok(resp) => resp,
                                     Err(e) => {
-                                        let mut resp =
-                                            Response::new(Body::from(format!("Error: {}", e)));
+                                        let body = match &e {
+                                            // Answered with the rejected block/transaction
+                                            NodeError::BlockchainError(e) => serde_json::to_string(
+                                                &BlockchainErrorInfo::from(e),
+                                            )?,
+                                            _ => format!("Error: {}", e),
+                                        };
+                                        let mut resp = Response::new(Body::from(body));
                                         *resp.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
                                         resp
                                     }
@@ -297,7 +561,7 @@ async fn deposit_withdraw(
     conf: BazukaConfig,
     mpn_contract_id: ContractId,
     contract: String,
-    index: u32,
+    index: Option<u32>,
     amount: Money,
     fee: Money,
     withdraw: bool,
@@ -308,6 +572,16 @@ async fn deposit_withdraw(
     try_join!(
         async move {
             let acc = client.get_account(wallet.get_address()).await?.account;
+            let index = match index {
+                Some(index) => index,
+                None if contract == "mpn" => {
+                    client
+                        .get_mpn_account_index(wallet.get_zk_address())
+                        .await?
+                        .index
+                }
+                None => panic!("Account index is required for contracts other than mpn!"),
+            };
             let pay = wallet.pay_contract(
                 if contract == "mpn" {
                     mpn_contract_id
@@ -351,12 +625,16 @@ async fn main() -> Result<(), NodeError> {
             listen,
             external,
             db,
+            db_backend,
             bootstrap,
             network,
+            chain_spec,
             discord_handle,
             client_only,
         } => {
             let conf = conf.expect("Bazuka is not initialized!");
+            let chain_spec = load_chain_spec(network.as_deref(), chain_spec)?;
+            let blockchain_config = chain_spec.to_config()?;
             run_node(
                 conf.clone(),
                 SocialProfiles {
@@ -365,9 +643,10 @@ async fn main() -> Result<(), NodeError> {
                 listen,
                 external,
                 client_only,
-                db,
+                open_db(db_backend.unwrap_or_default(), &bazuka_dir(db))?,
                 bootstrap,
-                network,
+                network.unwrap_or(chain_spec.network),
+                blockchain_config,
             )
             .await?;
         }
@@ -400,6 +679,278 @@ async fn main() -> Result<(), NodeError> {
         CliOptions::Init { .. } => {
             println!("Client feature not turned on!");
         }
+        CliOptions::ChainSpec(ChainSpecOptions::Export { network, json }) => {
+            let spec = ChainSpec::builtin(&network)?;
+            println!(
+                "{}",
+                if json {
+                    spec.to_json()?
+                } else {
+                    spec.to_yaml()?
+                }
+            );
+        }
+        CliOptions::Zk(ZkOptions::Convert {
+            format,
+            proof,
+            file,
+            json,
+        }) => {
+            let bytes = std::fs::read(file)?;
+            println!(
+                "{}",
+                if proof {
+                    let proof = ZkProof::Groth16(Box::new(Groth16Proof::import(format, &bytes)?));
+                    if json {
+                        serde_json::to_string(&proof)?
+                    } else {
+                        hex::encode(bincode::serialize(&proof)?)
+                    }
+                } else {
+                    let vk = ZkVerifierKey::Groth16(Box::new(Groth16VerifyingKey::import(
+                        format, &bytes,
+                    )?));
+                    if json {
+                        serde_json::to_string(&vk)?
+                    } else {
+                        hex::encode(bincode::serialize(&vk)?)
+                    }
+                }
+            );
+        }
+        #[cfg(feature = "node")]
+        CliOptions::Db(DbOptions::Migrate {
+            db,
+            db_backend,
+            dry_run,
+        }) => {
+            let mut store = open_db(db_backend.unwrap_or_default(), &bazuka_dir(db))?;
+            match migrations::schema_version(&store)? {
+                Some(version) => println!(
+                    "{} v{} (Latest: v{})",
+                    "Schema version:".bright_yellow(),
+                    version,
+                    migrations::SCHEMA_VERSION
+                ),
+                None => println!("{}", "Database is empty!".bright_yellow()),
+            }
+            let pending = if dry_run {
+                migrations::pending_migrations(&store)?
+            } else {
+                migrations::migrate(&mut store)?
+            };
+            for step in pending {
+                println!("v{} -> v{}: {}", step.from, step.from + 1, step.description);
+            }
+            if dry_run {
+                println!("Dry run, no changes were made.");
+            }
+        }
+        #[cfg(feature = "node")]
+        CliOptions::Db(DbOptions::Convert {
+            src,
+            src_backend,
+            dst,
+            dst_backend,
+        }) => {
+            let src = open_db(src_backend, &src)?;
+            let mut dst = open_db(dst_backend, &dst)?;
+            if dst
+                .scan(ScanQuery::all().limit(1))?
+                .next()
+                .transpose()?
+                .is_some()
+            {
+                println!("{}", "Destination database is not empty!".bright_red());
+            } else {
+                let count = copy_store(&src, &mut dst, 10000)?;
+                println!("{} {}", "Copied entries:".bright_yellow(), count);
+            }
+        }
+        #[cfg(feature = "node")]
+        CliOptions::Db(DbOptions::Backup { dir }) => {
+            let conf = conf.expect("Bazuka is not initialized!");
+            // Relative paths would be resolved by the node
+            let dir = std::env::current_dir()?.join(dir);
+            let sk = Signer::generate_keys(conf.seed.as_bytes()).1; // Secret-key of client, not wallet!
+            let (req_loop, client) =
+                BazukaClient::connect(sk, PeerAddress(conf.node), conf.network);
+            try_join!(
+                async move {
+                    let resp = client.admin_backup(dir.to_string_lossy().into()).await?;
+                    println!("{} {}", "Copied entries:".bright_yellow(), resp.entries);
+                    println!("{} {}", "Checksum:".bright_yellow(), resp.db_checksum);
+                    Ok::<(), NodeError>(())
+                },
+                req_loop
+            )
+            .unwrap();
+        }
+        #[cfg(feature = "node")]
+        CliOptions::Db(DbOptions::Restore {
+            backup,
+            db,
+            db_backend,
+            network,
+            chain_spec,
+        }) => {
+            let backend = db_backend.unwrap_or_default();
+            let blockchain_config = load_chain_spec(network.as_deref(), chain_spec)?.to_config()?;
+            let target = bazuka_dir(db);
+            let replaced = target.with_extension("replaced");
+            if target.exists() && replaced.exists() {
+                println!(
+                    "{} {}",
+                    "Please remove the previously replaced database:".bright_red(),
+                    replaced.display()
+                );
+                return Ok(());
+            }
+
+            // The backup is left untouched, it's validated on a copy which
+            // is only moved into place once accepted. Opening it as a chain
+            // runs the migrations it would need anyway.
+            let scratch = target.with_extension("restoring");
+            if scratch.exists() {
+                // Left by an interrupted restore
+                std::fs::remove_dir_all(&scratch)?;
+            }
+            copy_dir(&backup, &scratch)?;
+            let validated = (|| -> Result<Option<(u64, usize)>, NodeError> {
+                let store = open_db(backend, &scratch)?;
+                if migrations::schema_version(&store)?.is_none() {
+                    return Ok(None);
+                }
+                let count = verify_checksum(&store)?;
+                let height = KvStoreChain::new(store, blockchain_config)?.get_height()?;
+                Ok(Some((height, count)))
+            })();
+            let (height, count) = match validated {
+                Ok(Some(validated)) => validated,
+                res => {
+                    std::fs::remove_dir_all(&scratch)?;
+                    if res?.is_none() {
+                        println!("{}", "Backup is empty!".bright_red());
+                    }
+                    return Ok(());
+                }
+            };
+            println!("{} {}", "Backup height:".bright_yellow(), height);
+
+            if target.exists() {
+                std::fs::rename(&target, &replaced)?;
+                println!(
+                    "{} {}",
+                    "Previous database moved to:".bright_yellow(),
+                    replaced.display()
+                );
+            }
+            std::fs::rename(&scratch, &target)?;
+            println!("{} {}", "Restored entries:".bright_yellow(), count);
+        }
+        #[cfg(feature = "node")]
+        CliOptions::Chain(ChainOptions::Export {
+            from,
+            to,
+            file,
+            db,
+            db_backend,
+            network,
+            chain_spec,
+        }) => {
+            let blockchain_config = load_chain_spec(network.as_deref(), chain_spec)?.to_config()?;
+            let chain = KvStoreChain::new(
+                open_db(db_backend.unwrap_or_default(), &bazuka_dir(db))?,
+                blockchain_config,
+            )?;
+            let mut writer = std::io::BufWriter::new(std::fs::File::create(&file)?);
+            let count = export_chain(&chain, from, to.unwrap_or(u64::MAX), &mut writer, |i| {
+                if (i + 1).is_multiple_of(PROGRESS_INTERVAL) {
+                    println!("{} {}", "Exported up to block:".bright_yellow(), i);
+                }
+            })?;
+            writer.flush()?;
+            println!("{} {}", "Exported blocks:".bright_yellow(), count);
+        }
+        #[cfg(feature = "node")]
+        CliOptions::Chain(ChainOptions::Import {
+            file,
+            db,
+            db_backend,
+            network,
+            chain_spec,
+        }) => {
+            let blockchain_config = load_chain_spec(network.as_deref(), chain_spec)?.to_config()?;
+            let mut chain = KvStoreChain::new(
+                open_db(db_backend.unwrap_or_default(), &bazuka_dir(db))?,
+                blockchain_config,
+            )?;
+            println!(
+                "{} {}",
+                "Current height:".bright_yellow(),
+                chain.get_height()?
+            );
+            let mut reader = std::io::BufReader::new(std::fs::File::open(&file)?);
+            let summary = import_chain(&mut chain, &mut reader, |i| {
+                if (i + 1).is_multiple_of(PROGRESS_INTERVAL) {
+                    println!("{} {}", "Processed up to block:".bright_yellow(), i);
+                }
+            })?;
+            println!("{} {}", "Skipped blocks:".bright_yellow(), summary.skipped);
+            println!(
+                "{} {}",
+                "Imported blocks:".bright_yellow(),
+                summary.imported
+            );
+            println!("{} {}", "Height:".bright_yellow(), chain.get_height()?);
+            let outdated = chain.get_outdated_contracts()?;
+            if !outdated.is_empty() {
+                println!(
+                    "{} {}",
+                    "Contracts with outdated states:".bright_red(),
+                    outdated.len()
+                );
+            }
+        }
+        #[cfg(feature = "node")]
+        CliOptions::VerifyChain {
+            db,
+            db_backend,
+            network,
+            chain_spec,
+            in_memory,
+        } => {
+            let backend = db_backend.unwrap_or_default();
+            let blockchain_config = load_chain_spec(network.as_deref(), chain_spec)?.to_config()?;
+            let chain = KvStoreChain::new(open_db(backend, &bazuka_dir(db))?, blockchain_config)?;
+            println!("{} {}", "Height:".bright_yellow(), chain.get_height()?);
+            let progress = |i: u64| {
+                if (i + 1).is_multiple_of(PROGRESS_INTERVAL) {
+                    println!("{} {}", "Verified up to block:".bright_yellow(), i);
+                }
+            };
+            let divergence = if in_memory {
+                verify_chain(&chain, RamKvStore::new(), progress)?
+            } else {
+                let replay_dir =
+                    std::env::temp_dir().join(format!("bazuka-verify-{}", std::process::id()));
+                let result = verify_chain(&chain, open_db(backend, &replay_dir)?, progress);
+                std::fs::remove_dir_all(&replay_dir)?;
+                result?
+            };
+            match divergence {
+                Some(d) => println!(
+                    "{} {}: {}",
+                    "Diverged at height".bright_red(),
+                    d.height,
+                    d.divergence
+                ),
+                None => println!(
+                    "{}",
+                    "Database is consistent with the rules!".bright_green()
+                ),
+            }
+        }
         CliOptions::Status {} => {
             let conf = conf.expect("Bazuka is not initialized!");
             let sk = Signer::generate_keys(conf.seed.as_bytes()).1; // Secret-key of client, not wallet!
@@ -465,6 +1016,19 @@ async fn main() -> Result<(), NodeError> {
             try_join!(
                 async move {
                     let to: <ZkSigner as ZkSignatureScheme>::Pub = to.parse().unwrap();
+                    let from_index = match from_index {
+                        Some(index) => index,
+                        None => {
+                            client
+                                .get_mpn_account_index(wallet.get_zk_address())
+                                .await?
+                                .index
+                        }
+                    };
+                    let to_index = match to_index {
+                        Some(index) => index,
+                        None => client.get_mpn_account_index(to.clone()).await?.index,
+                    };
                     let acc = client.get_mpn_account(from_index).await?.account;
                     let tx = wallet
                         .create_mpn_transaction(from_index, to_index, to, amount, fee, acc.nonce);
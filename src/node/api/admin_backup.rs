@@ -0,0 +1,26 @@
+use super::messages::{AdminBackupRequest, AdminBackupResponse};
+use super::{NodeContext, NodeError};
+use crate::blockchain::Blockchain;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+pub async fn admin_backup<B: Blockchain>(
+    context: Arc<RwLock<NodeContext<B>>>,
+    req: AdminBackupRequest,
+) -> Result<AdminBackupResponse, NodeError> {
+    // The snapshot and the checksum are taken together, while blocks can't
+    // be applied, the copy is made without holding the node
+    let (job, db_checksum) = {
+        let context = context.read().await;
+        (
+            context.blockchain.db_backup_job()?,
+            context.blockchain.db_checksum()?,
+        )
+    };
+    let entries = tokio::task::spawn_blocking(move || job(Path::new(&req.path))).await??;
+    Ok(AdminBackupResponse {
+        entries,
+        db_checksum,
+    })
+}
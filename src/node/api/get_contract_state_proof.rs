@@ -0,0 +1,32 @@
+use super::messages::{GetContractStateProofRequest, GetContractStateProofResponse};
+use super::{NodeContext, NodeError};
+use crate::blockchain::Blockchain;
+use crate::zk;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+pub async fn get_contract_state_proof<B: Blockchain>(
+    context: Arc<RwLock<NodeContext<B>>>,
+    req: GetContractStateProofRequest,
+) -> Result<GetContractStateProofResponse, NodeError> {
+    let context = context.read().await;
+    let contract_id = req.contract.parse()?;
+    let locator: zk::ZkDataLocator = req.locator.parse()?;
+    // The local state of an outdated contract is behind its compressed state
+    if context
+        .blockchain
+        .get_outdated_contracts()?
+        .contains(&contract_id)
+    {
+        return Err(NodeError::StatesOutdated);
+    }
+    let account = context.blockchain.get_contract_account(contract_id)?;
+    Ok(GetContractStateProofResponse {
+        value: context
+            .blockchain
+            .read_state(contract_id, locator.clone())?,
+        proof: context.blockchain.prove_state(contract_id, locator)?,
+        height: account.height,
+        state: account.compressed_state,
+    })
+}
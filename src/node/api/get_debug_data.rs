@@ -12,5 +12,6 @@ pub async fn get_debug_data<B: Blockchain>(
     Ok(GetDebugDataResponse {
         logs: "".into(),
         db_checksum: context.blockchain.db_checksum()?,
+        db_cache: context.blockchain.db_cache_stats(),
     })
 }
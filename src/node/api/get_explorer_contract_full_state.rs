@@ -0,0 +1,28 @@
+use super::messages::{GetExplorerContractFullStateRequest, GetExplorerContractFullStateResponse};
+use super::{NodeContext, NodeError};
+use crate::blockchain::Blockchain;
+use crate::client::explorer::ExplorerContractState;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+pub async fn get_explorer_contract_full_state<B: Blockchain>(
+    context: Arc<RwLock<NodeContext<B>>>,
+    req: GetExplorerContractFullStateRequest,
+) -> Result<GetExplorerContractFullStateResponse, NodeError> {
+    let context = context.read().await;
+    Ok(GetExplorerContractFullStateResponse {
+        state: ExplorerContractState {
+            data: context
+                .blockchain
+                .get_full_state_page_at(
+                    req.contract_id.parse()?,
+                    req.height,
+                    req.page,
+                    req.page_size,
+                )?
+                .into_iter()
+                .map(|(loc, val)| (loc.to_string(), val))
+                .collect(),
+        },
+    })
+}
@@ -0,0 +1,19 @@
+use super::messages::{GetExplorerContractStateRequest, GetExplorerContractStateResponse};
+use super::{NodeContext, NodeError};
+use crate::blockchain::Blockchain;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+pub async fn get_explorer_contract_state<B: Blockchain>(
+    context: Arc<RwLock<NodeContext<B>>>,
+    req: GetExplorerContractStateRequest,
+) -> Result<GetExplorerContractStateResponse, NodeError> {
+    let context = context.read().await;
+    Ok(GetExplorerContractStateResponse {
+        value: context.blockchain.read_state_at(
+            req.contract_id.parse()?,
+            req.locator.parse()?,
+            req.height,
+        )?,
+    })
+}
@@ -0,0 +1,19 @@
+use super::messages::{GetMpnAccountIndexRequest, GetMpnAccountIndexResponse};
+use super::{NodeContext, NodeError};
+use crate::blockchain::Blockchain;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+pub async fn get_mpn_account_index<B: Blockchain>(
+    context: Arc<RwLock<NodeContext<B>>>,
+    req: GetMpnAccountIndexRequest,
+) -> Result<GetMpnAccountIndexResponse, NodeError> {
+    let context = context.read().await;
+    let index = context
+        .blockchain
+        .get_mpn_account_index(&req.address.parse()?)?;
+    Ok(GetMpnAccountIndexResponse {
+        index,
+        account: context.blockchain.get_mpn_account(index)?,
+    })
+}
@@ -0,0 +1,25 @@
+use super::messages::{GetMpnPaymentBatchRequest, GetMpnPaymentBatchResponse};
+use super::{NodeContext, NodeError};
+use crate::blockchain::Blockchain;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+pub async fn get_mpn_payment_batch<B: Blockchain>(
+    context: Arc<RwLock<NodeContext<B>>>,
+    req: GetMpnPaymentBatchRequest,
+) -> Result<GetMpnPaymentBatchResponse, NodeError> {
+    let context = context.read().await;
+    if !context.blockchain.get_outdated_heights()?.is_empty() {
+        return Err(NodeError::StatesOutdated);
+    }
+    // Payments of the same address are executed in the order of their nonces,
+    // the ones not applicable anymore are skipped, so a mempool that is not
+    // refreshed yet is fine
+    let mut payments = context.mempool.tx_zk.keys().cloned().collect::<Vec<_>>();
+    payments.sort_by_key(|p| p.payment.nonce);
+    Ok(GetMpnPaymentBatchResponse {
+        batch: context
+            .blockchain
+            .execute_mpn_payments(&req.executor, req.circuit_id, &payments)?,
+    })
+}
@@ -22,12 +22,18 @@ mod get_headers;
 pub use get_headers::*;
 mod transact;
 pub use transact::*;
+mod simulate_transaction;
+pub use simulate_transaction::*;
 mod post_mpn_transaction;
 pub use post_mpn_transaction::*;
 mod post_mpn_payment;
 pub use post_mpn_payment::*;
 mod shutdown;
 pub use shutdown::*;
+mod regtest_generate;
+pub use regtest_generate::*;
+mod admin_backup;
+pub use admin_backup::*;
 mod get_zero_mempool;
 pub use get_zero_mempool::*;
 mod get_miner_puzzle;
@@ -38,8 +44,18 @@ mod get_account;
 pub use get_account::*;
 mod get_mpn_account;
 pub use get_mpn_account::*;
+mod get_mpn_account_index;
+pub use get_mpn_account_index::*;
+mod get_mpn_payment_batch;
+pub use get_mpn_payment_batch::*;
 mod get_explorer_mpn_accounts;
 pub use get_explorer_mpn_accounts::*;
+mod get_contract_state_proof;
+pub use get_contract_state_proof::*;
+mod get_explorer_contract_state;
+pub use get_explorer_contract_state::*;
+mod get_explorer_contract_full_state;
+pub use get_explorer_contract_full_state::*;
 mod get_mempool;
 pub use get_mempool::*;
 mod get_debug_data;
@@ -9,9 +9,17 @@ pub async fn post_block<B: Blockchain>(
     req: PostBlockRequest,
 ) -> Result<PostBlockResponse, NodeError> {
     let mut context = context.write().await;
-    context
-        .blockchain
-        .extend(req.block.header.number, &[req.block])?;
+    let number = req.block.header.number;
+    if let Err(e) = context.blockchain.extend(number, &[req.block]) {
+        log::warn!(
+            "Rejected posted block #{}. (Invalid block: {:?}, transaction: {:?}) Error: {}",
+            number,
+            e.block_height(),
+            e.transaction_index(),
+            e
+        );
+        return Err(e.into());
+    }
     context.on_update()?;
     context.blockchain.update_states(&req.patch)?;
     Ok(PostBlockResponse {})
@@ -0,0 +1,51 @@
+use super::messages::{RegtestGenerateRequest, RegtestGenerateResponse};
+use super::{NodeContext, NodeError};
+use crate::blockchain::Blockchain;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+// Regtest stays at its minimum difficulty, so this is only reached when a
+// chain-spec sets a minimum that isn't trivial
+const MAX_NONCE_ATTEMPTS: u64 = 1_000_000;
+
+pub async fn regtest_generate<B: Blockchain>(
+    context: Arc<RwLock<NodeContext<B>>>,
+    req: RegtestGenerateRequest,
+) -> Result<RegtestGenerateResponse, NodeError> {
+    let mut context = context.write().await;
+    if !context.blockchain.config().regtest {
+        return Err(NodeError::RegtestNotEnabled);
+    }
+    let wallet = context.wallet.clone().ok_or(NodeError::NoWalletError)?;
+    let mut headers = Vec::new();
+    for _ in 0..req.blocks {
+        let ts = context.network_timestamp();
+        let mut draft = if let Some(draft) =
+            context
+                .blockchain
+                .draft_block(ts, &context.mempool.tx, &wallet, true)?
+        {
+            draft
+        } else {
+            break;
+        };
+
+        let pow_key = context.blockchain.pow_key(draft.block.header.number)?;
+        let mut attempts = 0;
+        while !draft.block.header.meets_target(&pow_key) {
+            attempts += 1;
+            if attempts >= MAX_NONCE_ATTEMPTS {
+                return Err(NodeError::RegtestNonceNotFound(MAX_NONCE_ATTEMPTS));
+            }
+            draft.block.header.proof_of_work.nonce += 1;
+        }
+
+        context
+            .blockchain
+            .extend(draft.block.header.number, &[draft.block.clone()])?;
+        context.on_update()?;
+        context.blockchain.update_states(&draft.patch)?;
+        headers.push(draft.block.header);
+    }
+    Ok(RegtestGenerateResponse { headers })
+}
@@ -0,0 +1,18 @@
+use super::messages::{SimulateTransactionRequest, SimulateTransactionResponse};
+use super::{NodeContext, NodeError};
+use crate::blockchain::Blockchain;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+pub async fn simulate_transaction<B: Blockchain>(
+    context: Arc<RwLock<NodeContext<B>>>,
+    req: SimulateTransactionRequest,
+) -> Result<SimulateTransactionResponse, NodeError> {
+    let context = context.read().await;
+    Ok(SimulateTransactionResponse {
+        result: context
+            .blockchain
+            .simulate_transaction(&req.tx_delta)
+            .map_err(|e| (&e).into()),
+    })
+}
@@ -186,7 +186,11 @@ pub async fn sync_blocks<B: Blockchain>(
                     result
                 }
                 Err(e) => {
-                    log::warn!("Chain is invalid! Error: {}", e);
+                    log::warn!(
+                        "Chain is invalid! (Invalid block: {:?}) Error: {}",
+                        e.block_height(),
+                        e
+                    );
                     false
                 }
             };
@@ -220,7 +224,12 @@ pub async fn sync_blocks<B: Blockchain>(
                     }
                     Err(e) => {
                         chain_fail = true;
-                        log::warn!("Cannot extend the blockchain. Error: {}", e);
+                        log::warn!(
+                            "Cannot extend the blockchain. (Invalid block: {:?}, transaction: {:?}) Error: {}",
+                            e.block_height(),
+                            e.transaction_index(),
+                            e
+                        );
                         break;
                     }
                 }
@@ -188,6 +188,12 @@ async fn node_service<B: Blockchain>(
                     &api::get_mpn_account(Arc::clone(&context), serde_qs::from_str(&qs)?).await?,
                 )?);
             }
+            (Method::GET, "/mpn/account/index") => {
+                *response.body_mut() = Body::from(serde_json::to_vec(
+                    &api::get_mpn_account_index(Arc::clone(&context), serde_qs::from_str(&qs)?)
+                        .await?,
+                )?);
+            }
             (Method::GET, "/peers") => {
                 *response.body_mut() = Body::from(serde_json::to_vec(
                     &api::get_peers(Arc::clone(&context), serde_qs::from_str(&qs)?).await?,
@@ -213,12 +219,40 @@ async fn node_service<B: Blockchain>(
                     *response.status_mut() = StatusCode::FORBIDDEN;
                 }
             }
+            (Method::POST, "/regtest/generate") => {
+                if is_local {
+                    *response.body_mut() = Body::from(serde_json::to_vec(
+                        &api::regtest_generate(Arc::clone(&context), serde_qs::from_str(&qs)?)
+                            .await?,
+                    )?);
+                } else {
+                    *response.status_mut() = StatusCode::FORBIDDEN;
+                }
+            }
+            (Method::POST, "/admin/backup") => {
+                if is_local {
+                    *response.body_mut() = Body::from(serde_json::to_vec(
+                        &api::admin_backup(Arc::clone(&context), serde_qs::from_str(&qs)?).await?,
+                    )?);
+                } else {
+                    *response.status_mut() = StatusCode::FORBIDDEN;
+                }
+            }
             (Method::POST, "/bincode/transact") => {
                 *response.body_mut() = Body::from(bincode::serialize(
                     &api::transact(Arc::clone(&context), bincode::deserialize(&body_bytes)?)
                         .await?,
                 )?);
             }
+            (Method::POST, "/bincode/transact/simulate") => {
+                *response.body_mut() = Body::from(bincode::serialize(
+                    &api::simulate_transaction(
+                        Arc::clone(&context),
+                        bincode::deserialize(&body_bytes)?,
+                    )
+                    .await?,
+                )?);
+            }
             (Method::POST, "/bincode/transact/zero") => {
                 *response.body_mut() = Body::from(bincode::serialize(
                     &api::post_mpn_transaction(
@@ -249,6 +283,30 @@ async fn node_service<B: Blockchain>(
                         .await?,
                 )?);
             }
+            (Method::GET, "/contract/state/proof") => {
+                *response.body_mut() = Body::from(serde_json::to_vec(
+                    &api::get_contract_state_proof(Arc::clone(&context), serde_qs::from_str(&qs)?)
+                        .await?,
+                )?);
+            }
+            (Method::GET, "/explorer/contract/state") => {
+                *response.body_mut() = Body::from(serde_json::to_vec(
+                    &api::get_explorer_contract_state(
+                        Arc::clone(&context),
+                        serde_qs::from_str(&qs)?,
+                    )
+                    .await?,
+                )?);
+            }
+            (Method::GET, "/explorer/contract/full_state") => {
+                *response.body_mut() = Body::from(serde_json::to_vec(
+                    &api::get_explorer_contract_full_state(
+                        Arc::clone(&context),
+                        serde_qs::from_str(&qs)?,
+                    )
+                    .await?,
+                )?);
+            }
             (Method::GET, "/bincode/headers") => {
                 *response.body_mut() = Body::from(bincode::serialize(
                     &api::get_headers(Arc::clone(&context), bincode::deserialize(&body_bytes)?)
@@ -291,6 +349,15 @@ async fn node_service<B: Blockchain>(
                     .await?,
                 )?);
             }
+            (Method::GET, "/bincode/mpn/payments/batch") => {
+                *response.body_mut() = Body::from(bincode::serialize(
+                    &api::get_mpn_payment_batch(
+                        Arc::clone(&context),
+                        bincode::deserialize(&body_bytes)?,
+                    )
+                    .await?,
+                )?);
+            }
             (Method::GET, "/bincode/mempool") => {
                 *response.body_mut() = Body::from(bincode::serialize(
                     &api::get_mempool(Arc::clone(&context), bincode::deserialize(&body_bytes)?)
@@ -3,7 +3,10 @@ use super::*;
 mod simulation;
 use simulation::*;
 
-use crate::blockchain::BlockchainError;
+use crate::blockchain::{BlockchainErrorCode, BlockchainErrorInfo, ZkBlockchainPatch};
+use crate::client::messages::{
+    GetBlocksRequest, GetBlocksResponse, PostBlockRequest, PostBlockResponse,
+};
 use crate::config::blockchain;
 use crate::core::{ContractId, Money, Signer, TransactionAndDelta, ZkHasher};
 use crate::crypto::SignatureScheme;
@@ -213,6 +216,141 @@ async fn test_blocks_get_synced() -> Result<(), NodeError> {
         chans[1].mine().await?;
         assert_eq!(chans[0].stats().await?.height, 51);
         assert_eq!(chans[1].stats().await?.height, 51);
+        assert_eq!(
+            chans[0].debug_data().await?.db_checksum,
+            chans[1].debug_data().await?.db_checksum
+        );
+
+        for chan in chans.iter() {
+            chan.shutdown().await?;
+        }
+
+        Ok::<(), NodeError>(())
+    };
+    tokio::try_join!(node_futs, route_futs, test_logic)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_regtest_generate() -> Result<(), NodeError> {
+    init();
+
+    let rules = Arc::new(RwLock::new(vec![]));
+
+    let conf = blockchain::get_regtest_blockchain_config();
+
+    let (node_futs, route_futs, chans) = simulation::test_network(
+        Arc::clone(&rules),
+        vec![
+            NodeOpts {
+                config: conf.clone(),
+                priv_key: Signer::generate_keys(b"120").1,
+                wallet: Some(Wallet::new(Vec::from("ABC"))),
+                addr: 120,
+                bootstrap: vec![],
+                timestamp_offset: 5,
+            },
+            NodeOpts {
+                config: blockchain::get_test_blockchain_config(),
+                priv_key: Signer::generate_keys(b"121").1,
+                wallet: Some(Wallet::new(Vec::from("ABC"))),
+                addr: 121,
+                bootstrap: vec![],
+                timestamp_offset: 5,
+            },
+        ],
+    );
+    let test_logic = async {
+        // Only nodes running regtest generate blocks on demand
+        assert!(chans[1].regtest_generate(1).await.is_err());
+        assert_eq!(chans[1].stats().await?.height, 1);
+
+        let headers = chans[0].regtest_generate(3).await?.headers;
+        assert_eq!(
+            headers.iter().map(|h| h.number).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(chans[0].stats().await?.height, 4);
+
+        chans[0].regtest_generate(2).await?;
+        assert_eq!(chans[0].stats().await?.height, 6);
+
+        // The difficulty isn't recalculated, even though blocks come faster
+        // than the block time. The first interval starts with the genesis
+        // timestamp, so it takes a second one to make the target harder.
+        let count = 2 * conf.difficulty_calc_interval as usize + 10;
+        let headers = chans[0].regtest_generate(count).await?.headers;
+        assert_eq!(headers.len(), count);
+        assert!(headers
+            .iter()
+            .all(|h| h.proof_of_work.target == conf.minimum_pow_difficulty));
+
+        for chan in chans.iter() {
+            chan.shutdown().await?;
+        }
+
+        Ok::<(), NodeError>(())
+    };
+    tokio::try_join!(node_futs, route_futs, test_logic)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rejected_block_context() -> Result<(), NodeError> {
+    init();
+
+    let rules = Arc::new(RwLock::new(vec![]));
+
+    let (node_futs, route_futs, chans) = simulation::test_network(
+        Arc::clone(&rules),
+        vec![NodeOpts {
+            config: blockchain::get_regtest_blockchain_config(),
+            priv_key: Signer::generate_keys(b"130").1,
+            wallet: Some(Wallet::new(Vec::from("ABC"))),
+            addr: 130,
+            bootstrap: vec![],
+            timestamp_offset: 5,
+        }],
+    );
+    let test_logic = async {
+        chans[0].regtest_generate(2).await?;
+        let mut block = chans[0]
+            .sender
+            .bincode_get::<GetBlocksRequest, GetBlocksResponse>(
+                format!("{}/bincode/blocks", chans[0].peer),
+                GetBlocksRequest { since: 2, count: 1 },
+                Limit::default(),
+            )
+            .await?
+            .blocks
+            .remove(0);
+        block.header.parent_hash = Default::default();
+
+        let err = chans[0]
+            .sender
+            .bincode_post::<PostBlockRequest, PostBlockResponse>(
+                format!("{}/bincode/blocks", chans[0].peer),
+                PostBlockRequest {
+                    block,
+                    patch: ZkBlockchainPatch {
+                        patches: Default::default(),
+                    },
+                },
+                Limit::default(),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.block_height(), Some(2));
+        assert_eq!(err.transaction_index(), None);
+        let info = match &err {
+            NodeError::BlockchainError(e) => BlockchainErrorInfo::from(e),
+            _ => panic!("unexpected error: {}", err),
+        };
+        assert_eq!(info.code, BlockchainErrorCode::InvalidParentHash);
+        // As received by remote clients
+        let remote = NodeError::RemoteBlockchainError(info);
+        assert_eq!(remote.block_height(), Some(2));
+        assert_eq!(remote.transaction_index(), None);
 
         for chan in chans.iter() {
             chan.shutdown().await?;
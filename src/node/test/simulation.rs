@@ -185,6 +185,7 @@ pub fn test_network(
         .into_iter()
         .map(|node_opts| create_test_node(node_opts))
         .unzip();
+    let chans = nodes.iter().map(|n| n.incoming.clone()).collect();
     let incs: HashMap<_, _> = nodes.iter().map(|n| (n.addr, n.incoming.clone())).collect();
     let route_futs = nodes
         .into_iter()
@@ -194,6 +195,6 @@ pub fn test_network(
     (
         futures::future::try_join_all(node_futs),
         futures::future::try_join_all(route_futs),
-        incs.into_values().collect(),
+        chans,
     )
 }
@@ -100,14 +100,11 @@ impl Wallet {
     }
 
     #[allow(clippy::too_many_arguments)]
-    pub fn call_function(
+    pub fn update_contract(
         &self,
         contract_id: ContractId,
-        function_id: u32,
+        updates: Vec<ContractUpdate>,
         state_delta: zk::ZkDeltaPairs,
-        next_state: zk::ZkCompressedState,
-        proof: zk::ZkProof,
-        exec_fee: Money,
         miner_fee: Money,
         nonce: u32,
     ) -> TransactionAndDelta {
@@ -116,12 +113,7 @@ impl Wallet {
             src: self.get_address(),
             data: TransactionData::UpdateContract {
                 contract_id,
-                updates: vec![ContractUpdate::FunctionCall {
-                    function_id,
-                    next_state,
-                    proof,
-                    fee: exec_fee,
-                }],
+                updates,
             },
             nonce,
             fee: miner_fee,
@@ -135,6 +127,32 @@ impl Wallet {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub fn call_function(
+        &self,
+        contract_id: ContractId,
+        function_id: u32,
+        state_delta: zk::ZkDeltaPairs,
+        next_state: zk::ZkCompressedState,
+        proof: zk::ZkProof,
+        exec_fee: Money,
+        miner_fee: Money,
+        nonce: u32,
+    ) -> TransactionAndDelta {
+        self.update_contract(
+            contract_id,
+            vec![ContractUpdate::FunctionCall {
+                function_id,
+                next_state,
+                proof,
+                fee: exec_fee,
+            }],
+            state_delta,
+            miner_fee,
+            nonce,
+        )
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn pay_contract(
         &self,
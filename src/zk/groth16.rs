@@ -1,38 +1,438 @@
 use super::ZkScalar;
-use bls12_381::{Bls12, G1Affine as BellmanG1, G2Affine as BellmanG2, Scalar as BellmanFr};
-use serde::{Deserialize, Serialize};
+use bellman::groth16::{
+    batch, prepare_verifying_key, verify_proof, PreparedVerifyingKey, Proof, VerifyingKey,
+};
+use bls12_381::{Bls12, G1Affine, G2Affine, Scalar as BellmanFr};
+use ff::PrimeField;
+use lazy_static::lazy_static;
+use num_bigint::BigUint;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::io::Read;
+use thiserror::Error;
+
+mod import;
+pub use import::*;
+#[cfg(feature = "prover")]
+pub mod prover;
+
+#[derive(Error, Debug)]
+pub enum Groth16Error {
+    #[error("bincode error happened: {0}")]
+    BincodeError(#[from] bincode::Error),
+    #[error("point is not canonical, not on the curve, or not in the prime-order subgroup")]
+    InvalidPoint,
+    #[error("json error happened: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("io error happened: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("invalid format: {0}")]
+    InvalidFormat(String),
+}
 
 impl From<ZkScalar> for BellmanFr {
     fn from(s: ZkScalar) -> BellmanFr {
-        unsafe { std::mem::transmute::<ZkScalar, BellmanFr>(s) }
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(s.to_repr().as_ref());
+        // Both are little-endian representations in the same field
+        BellmanFr::from_bytes(&bytes).unwrap()
     }
 }
 
 impl From<BellmanFr> for ZkScalar {
     fn from(bls: BellmanFr) -> ZkScalar {
-        unsafe { std::mem::transmute::<BellmanFr, ZkScalar>(bls) }
+        let mut repr = <ZkScalar as PrimeField>::Repr::default();
+        repr.as_mut().copy_from_slice(&bls.to_bytes());
+        ZkScalar::from_repr(repr).unwrap()
     }
 }
 
+lazy_static! {
+    static ref MODULUS: BigUint = BigUint::parse_bytes(
+        b"1a0111ea397fe69a4b1ba7b6434bacd764774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaab",
+        16
+    )
+    .unwrap();
+    // The Montgomery factor, 2^384, and its inverse
+    static ref R: BigUint = (BigUint::from(1u8) << 384u32) % &*MODULUS;
+    static ref R_INV: BigUint = R.modpow(&(&*MODULUS - 2u8), &MODULUS);
+}
+
+/// A base field element, as the Montgomery-form limbs `bls12_381` keeps in
+/// memory. Keys and proofs are serialized with the in-memory layout of their
+/// points, i.e. these limbs for each coordinate and an infinity flag.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct Fp([u64; 6]);
+struct Fp([u64; 6]);
+
+impl Fp {
+    fn new(n: &BigUint) -> Self {
+        let mut limbs = [0u64; 6];
+        for (limb, digit) in limbs
+            .iter_mut()
+            .zip((n * &*R % &*MODULUS).iter_u64_digits())
+        {
+            *limb = digit;
+        }
+        Self(limbs)
+    }
+
+    fn from_bytes_be(bytes: &[u8]) -> Self {
+        Self::new(&BigUint::from_bytes_be(bytes))
+    }
+
+    fn value(&self) -> Result<BigUint, Groth16Error> {
+        let mut bytes = Vec::with_capacity(48);
+        for limb in self.0.iter() {
+            bytes.extend_from_slice(&limb.to_le_bytes());
+        }
+        let mont = BigUint::from_bytes_le(&bytes);
+        if mont >= *MODULUS {
+            return Err(Groth16Error::InvalidPoint);
+        }
+        Ok(mont * &*R_INV % &*MODULUS)
+    }
+}
+
+type FpG1 = (Fp, Fp, bool);
+type FpG2 = ((Fp, Fp), (Fp, Fp), bool);
+
+// Canonical big-endian encoding of a base field element
+fn fp_bytes(n: &BigUint) -> Result<[u8; 48], Groth16Error> {
+    if *n >= *MODULUS {
+        return Err(Groth16Error::InvalidPoint);
+    }
+    let be = n.to_bytes_be();
+    let mut bytes = [0u8; 48];
+    bytes[48 - be.len()..].copy_from_slice(&be);
+    Ok(bytes)
+}
+
+// Validated point with the given affine coordinates, `None` being the point
+// at infinity
+fn g1_point(coords: Option<[BigUint; 2]>) -> Result<G1Point, Groth16Error> {
+    let mut bytes = [0u8; 96];
+    match coords {
+        Some([x, y]) => {
+            bytes[..48].copy_from_slice(&fp_bytes(&x)?);
+            bytes[48..].copy_from_slice(&fp_bytes(&y)?);
+        }
+        None => {
+            bytes[0] = 0x40;
+        }
+    }
+    Option::from(G1Affine::from_uncompressed(&bytes))
+        .map(G1Point)
+        .ok_or(Groth16Error::InvalidPoint)
+}
+
+// Coordinates are given as `[x.c0, x.c1, y.c0, y.c1]`
+fn g2_point(coords: Option<[BigUint; 4]>) -> Result<G2Point, Groth16Error> {
+    let mut bytes = [0u8; 192];
+    match coords {
+        Some([x0, x1, y0, y1]) => {
+            for (i, fp) in [x1, x0, y1, y0].iter().enumerate() {
+                bytes[i * 48..(i + 1) * 48].copy_from_slice(&fp_bytes(fp)?);
+            }
+        }
+        None => {
+            bytes[0] = 0x40;
+        }
+    }
+    Option::from(G2Affine::from_uncompressed(&bytes))
+        .map(G2Point)
+        .ok_or(Groth16Error::InvalidPoint)
+}
+
+/// A G1 point. Decoding rejects points that are not on the curve, or not in
+/// the prime-order subgroup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct G1Point(pub G1Affine);
+
+/// A G2 point. Decoding rejects points that are not on the curve, or not in
+/// the prime-order subgroup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct G2Point(pub G2Affine);
+
+impl G1Point {
+    fn to_fp(self) -> FpG1 {
+        if bool::from(self.0.is_identity()) {
+            // `bls12_381` represents the point at infinity as (0, 1)
+            return (Fp::new(&0u8.into()), Fp::new(&1u8.into()), true);
+        }
+        let bytes = self.0.to_uncompressed();
+        (
+            Fp::from_bytes_be(&bytes[..48]),
+            Fp::from_bytes_be(&bytes[48..]),
+            false,
+        )
+    }
+
+    fn from_fp(p: &FpG1) -> Result<Self, Groth16Error> {
+        g1_point(if p.2 {
+            None
+        } else {
+            Some([p.0.value()?, p.1.value()?])
+        })
+    }
+
+    fn read_compressed(reader: &mut &[u8]) -> Result<Self, Groth16Error> {
+        let mut bytes = [0u8; 48];
+        reader.read_exact(&mut bytes)?;
+        Option::from(G1Affine::from_compressed(&bytes))
+            .map(G1Point)
+            .ok_or(Groth16Error::InvalidPoint)
+    }
+}
+
+impl G2Point {
+    fn to_fp(self) -> FpG2 {
+        if bool::from(self.0.is_identity()) {
+            let (zero, one) = (Fp::new(&0u8.into()), Fp::new(&1u8.into()));
+            return ((zero.clone(), zero.clone()), (one, zero), true);
+        }
+        let bytes = self.0.to_uncompressed();
+        (
+            (
+                Fp::from_bytes_be(&bytes[48..96]),
+                Fp::from_bytes_be(&bytes[..48]),
+            ),
+            (
+                Fp::from_bytes_be(&bytes[144..]),
+                Fp::from_bytes_be(&bytes[96..144]),
+            ),
+            false,
+        )
+    }
+
+    fn from_fp(p: &FpG2) -> Result<Self, Groth16Error> {
+        let ((x0, x1), (y0, y1)) = (&p.0, &p.1);
+        g2_point(if p.2 {
+            None
+        } else {
+            Some([x0.value()?, x1.value()?, y0.value()?, y1.value()?])
+        })
+    }
+
+    fn read_compressed(reader: &mut &[u8]) -> Result<Self, Groth16Error> {
+        let mut bytes = [0u8; 96];
+        reader.read_exact(&mut bytes)?;
+        Option::from(G2Affine::from_compressed(&bytes))
+            .map(G2Point)
+            .ok_or(Groth16Error::InvalidPoint)
+    }
+}
+
+impl Serialize for G1Point {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_fp().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for G1Point {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        G1Point::from_fp(&FpG1::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for G2Point {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_fp().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for G2Point {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        G2Point::from_fp(&FpG2::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Groth16VerifyingKey {
-    alpha_g1: (Fp, Fp, bool),
-    beta_g1: (Fp, Fp, bool),
-    beta_g2: ((Fp, Fp), (Fp, Fp), bool),
-    gamma_g2: ((Fp, Fp), (Fp, Fp), bool),
-    delta_g1: (Fp, Fp, bool),
-    delta_g2: ((Fp, Fp), (Fp, Fp), bool),
-    ic: Vec<(Fp, Fp, bool)>,
+    alpha_g1: G1Point,
+    beta_g1: G1Point,
+    beta_g2: G2Point,
+    gamma_g2: G2Point,
+    delta_g1: G1Point,
+    delta_g2: G2Point,
+    ic: Vec<G1Point>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Groth16Proof {
-    a: (Fp, Fp, bool),
-    b: ((Fp, Fp), (Fp, Fp), bool),
-    c: (Fp, Fp, bool),
+    a: G1Point,
+    b: G2Point,
+    c: G1Point,
+}
+
+fn check_consumed(reader: &[u8]) -> Result<(), Groth16Error> {
+    if !reader.is_empty() {
+        return Err(Groth16Error::InvalidFormat(format!(
+            "{} trailing bytes",
+            reader.len()
+        )));
+    }
+    Ok(())
+}
+
+impl Groth16VerifyingKey {
+    /// Encodes the key with the canonical compressed form of its points,
+    /// about a quarter of the size of its serialization. This is only an
+    /// alternative representation, keys are serialized as they always were.
+    pub fn to_compressed(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(self.alpha_g1.0.to_compressed());
+        bytes.extend(self.beta_g1.0.to_compressed());
+        bytes.extend(self.beta_g2.0.to_compressed());
+        bytes.extend(self.gamma_g2.0.to_compressed());
+        bytes.extend(self.delta_g1.0.to_compressed());
+        bytes.extend(self.delta_g2.0.to_compressed());
+        bytes.extend((self.ic.len() as u64).to_le_bytes());
+        for p in self.ic.iter() {
+            bytes.extend(p.0.to_compressed());
+        }
+        bytes
+    }
+
+    pub fn from_compressed(mut bytes: &[u8]) -> Result<Self, Groth16Error> {
+        let reader = &mut bytes;
+        let alpha_g1 = G1Point::read_compressed(reader)?;
+        let beta_g1 = G1Point::read_compressed(reader)?;
+        let beta_g2 = G2Point::read_compressed(reader)?;
+        let gamma_g2 = G2Point::read_compressed(reader)?;
+        let delta_g1 = G1Point::read_compressed(reader)?;
+        let delta_g2 = G2Point::read_compressed(reader)?;
+        let mut len = [0u8; 8];
+        reader.read_exact(&mut len)?;
+        let ic = (0..u64::from_le_bytes(len))
+            .map(|_| G1Point::read_compressed(reader))
+            .collect::<Result<Vec<_>, _>>()?;
+        check_consumed(reader)?;
+        Ok(Self {
+            alpha_g1,
+            beta_g1,
+            beta_g2,
+            gamma_g2,
+            delta_g1,
+            delta_g2,
+            ic,
+        })
+    }
+}
+
+impl Groth16Proof {
+    /// Encodes the proof with the canonical compressed form of its points
+    pub fn to_compressed(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(self.a.0.to_compressed());
+        bytes.extend(self.b.0.to_compressed());
+        bytes.extend(self.c.0.to_compressed());
+        bytes
+    }
+
+    pub fn from_compressed(mut bytes: &[u8]) -> Result<Self, Groth16Error> {
+        let reader = &mut bytes;
+        let proof = Self {
+            a: G1Point::read_compressed(reader)?,
+            b: G2Point::read_compressed(reader)?,
+            c: G1Point::read_compressed(reader)?,
+        };
+        check_consumed(reader)?;
+        Ok(proof)
+    }
+}
+
+/// Verifying key prepared for pairing checks, worth caching between
+/// verifications.
+pub struct Groth16PreparedKey {
+    vk: VerifyingKey<Bls12>,
+    pvk: PreparedVerifyingKey<Bls12>,
+}
+
+/// A proof along with its public inputs
+#[derive(Clone)]
+pub struct Groth16Statement {
+    proof: Proof<Bls12>,
+    inputs: [BellmanFr; 4],
+}
+
+impl Groth16VerifyingKey {
+    pub fn prepare(&self) -> Groth16PreparedKey {
+        let vk = VerifyingKey::<Bls12> {
+            alpha_g1: self.alpha_g1.0,
+            beta_g1: self.beta_g1.0,
+            beta_g2: self.beta_g2.0,
+            gamma_g2: self.gamma_g2.0,
+            delta_g1: self.delta_g1.0,
+            delta_g2: self.delta_g2.0,
+            ic: self.ic.iter().map(|p| p.0).collect(),
+        };
+        let pvk = prepare_verifying_key(&vk);
+        Groth16PreparedKey { vk, pvk }
+    }
+}
+
+impl From<&VerifyingKey<Bls12>> for Groth16VerifyingKey {
+    fn from(vk: &VerifyingKey<Bls12>) -> Self {
+        Self {
+            alpha_g1: G1Point(vk.alpha_g1),
+            beta_g1: G1Point(vk.beta_g1),
+            beta_g2: G2Point(vk.beta_g2),
+            gamma_g2: G2Point(vk.gamma_g2),
+            delta_g1: G1Point(vk.delta_g1),
+            delta_g2: G2Point(vk.delta_g2),
+            ic: vk.ic.iter().cloned().map(G1Point).collect(),
+        }
+    }
+}
+
+impl From<&Proof<Bls12>> for Groth16Proof {
+    fn from(proof: &Proof<Bls12>) -> Self {
+        Self {
+            a: G1Point(proof.a),
+            b: G2Point(proof.b),
+            c: G1Point(proof.c),
+        }
+    }
+}
+
+impl Groth16Statement {
+    pub fn new(
+        prev_height: u64,
+        prev_state: ZkScalar,
+        aux_data: ZkScalar,
+        next_state: ZkScalar,
+        proof: &Groth16Proof,
+    ) -> Self {
+        Self {
+            proof: Proof::<Bls12> {
+                a: proof.a.0,
+                b: proof.b.0,
+                c: proof.c.0,
+            },
+            inputs: [
+                prev_height.into(),
+                prev_state.into(),
+                aux_data.into(),
+                next_state.into(),
+            ],
+        }
+    }
+}
+
+impl Groth16PreparedKey {
+    pub fn verify(&self, statement: &Groth16Statement) -> bool {
+        verify_proof(&self.pvk, &statement.proof, &statement.inputs).is_ok()
+    }
+
+    /// Checks all of the statements at once, with a random linear combination
+    /// of their pairing equations. When it fails, at least one of the
+    /// statements is invalid, and they should be checked one by one to find it.
+    pub fn batch_verify(&self, statements: &[Groth16Statement]) -> bool {
+        let mut verifier = batch::Verifier::<Bls12>::new();
+        for statement in statements.iter() {
+            verifier.queue((&statement.proof, &statement.inputs[..]));
+        }
+        verifier.verify_multicore(&self.vk).is_ok()
+    }
 }
 
 pub fn groth16_verify(
@@ -43,48 +443,101 @@ pub fn groth16_verify(
     next_state: ZkScalar,
     proof: &Groth16Proof,
 ) -> bool {
-    let (vk, proof) = unsafe {
-        let alpha_g1 = std::mem::transmute::<(Fp, Fp, bool), BellmanG1>(vk.alpha_g1.clone());
-        let beta_g1 = std::mem::transmute::<(Fp, Fp, bool), BellmanG1>(vk.beta_g1.clone());
-        let beta_g2 =
-            std::mem::transmute::<((Fp, Fp), (Fp, Fp), bool), BellmanG2>(vk.beta_g2.clone());
-        let gamma_g2 =
-            std::mem::transmute::<((Fp, Fp), (Fp, Fp), bool), BellmanG2>(vk.gamma_g2.clone());
-        let delta_g1 = std::mem::transmute::<(Fp, Fp, bool), BellmanG1>(vk.delta_g1.clone());
-        let delta_g2 =
-            std::mem::transmute::<((Fp, Fp), (Fp, Fp), bool), BellmanG2>(vk.delta_g2.clone());
-        let ic = vk
-            .ic
-            .iter()
-            .cloned()
-            .map(|p| std::mem::transmute::<(Fp, Fp, bool), BellmanG1>(p))
-            .collect();
-        let proof = bellman::groth16::Proof::<Bls12> {
-            a: std::mem::transmute::<(Fp, Fp, bool), BellmanG1>(proof.a.clone()),
-            b: std::mem::transmute::<((Fp, Fp), (Fp, Fp), bool), BellmanG2>(proof.b.clone()),
-            c: std::mem::transmute::<(Fp, Fp, bool), BellmanG1>(proof.c.clone()),
-        };
-        let vk =
-            bellman::groth16::prepare_verifying_key(&bellman::groth16::VerifyingKey::<Bls12> {
-                alpha_g1,
-                beta_g1,
-                beta_g2,
-                gamma_g2,
-                delta_g1,
-                delta_g2,
-                ic,
-            });
-        (vk, proof)
-    };
-    bellman::groth16::verify_proof(
-        &vk,
-        &proof,
-        &[
+    vk.prepare().verify(&Groth16Statement::new(
+        prev_height,
+        prev_state,
+        aux_data,
+        next_state,
+        proof,
+    ))
+}
+
+/// Groth16 setup and prover of a circuit accepting any public inputs, whose
+/// proofs are only valid for the public inputs they were created with.
+#[cfg(test)]
+pub struct TestProver {
+    params: bellman::groth16::Parameters<Bls12>,
+}
+
+#[cfg(test)]
+struct TestCircuit([Option<BellmanFr>; 4]);
+
+#[cfg(test)]
+impl bellman::Circuit<BellmanFr> for TestCircuit {
+    fn synthesize<CS: bellman::ConstraintSystem<BellmanFr>>(
+        self,
+        cs: &mut CS,
+    ) -> Result<(), bellman::SynthesisError> {
+        let mut inputs = Vec::new();
+        for (i, value) in self.0.iter().enumerate() {
+            inputs.push(cs.alloc_input(
+                || format!("input {}", i),
+                || value.ok_or(bellman::SynthesisError::AssignmentMissing),
+            )?);
+        }
+        let product = cs.alloc(
+            || "product",
+            || {
+                Ok(self.0[0].ok_or(bellman::SynthesisError::AssignmentMissing)?
+                    * self.0[1].ok_or(bellman::SynthesisError::AssignmentMissing)?)
+            },
+        )?;
+        cs.enforce(
+            || "product",
+            |lc| lc + inputs[0],
+            |lc| lc + inputs[1],
+            |lc| lc + product,
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl TestProver {
+    pub fn new(seed: u64) -> Self {
+        use bls12_381::{G1Projective, G2Projective};
+        let param = |i: u64| BellmanFr::from(seed * 16 + i + 1);
+        let params = bellman::groth16::generate_parameters::<Bls12, _>(
+            TestCircuit([None; 4]),
+            G1Projective::generator(),
+            G2Projective::generator(),
+            param(0),
+            param(1),
+            param(2),
+            param(3),
+            param(4),
+        )
+        .unwrap();
+        Self { params }
+    }
+
+    pub fn verifying_key(&self) -> Groth16VerifyingKey {
+        (&self.params.vk).into()
+    }
+
+    pub fn prove(
+        &self,
+        prev_height: u64,
+        prev_state: ZkScalar,
+        aux_data: ZkScalar,
+        next_state: ZkScalar,
+    ) -> Groth16Proof {
+        let inputs = [
             prev_height.into(),
             prev_state.into(),
             aux_data.into(),
             next_state.into(),
-        ],
-    )
-    .is_ok()
+        ];
+        let proof = bellman::groth16::create_proof::<Bls12, _, _>(
+            TestCircuit(inputs.map(Some)),
+            &self.params,
+            BellmanFr::from(prev_height + 1),
+            BellmanFr::from(prev_height + 2),
+        )
+        .unwrap();
+        (&proof).into()
+    }
 }
+
+#[cfg(test)]
+mod test;
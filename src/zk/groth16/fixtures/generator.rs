@@ -0,0 +1,107 @@
+use ark_bls12_381::{Bls12_381, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::pairing::Pairing;
+use ark_ec::AffineRepr;
+use ark_groth16::Groth16;
+use ark_relations::lc;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_serialize::CanonicalSerialize;
+use ark_snark::SNARK;
+use ark_std::rand::SeedableRng;
+
+// Public inputs: prev_height, prev_state, aux_data, next_state
+// Proves prev_state * aux_data + prev_height == next_state
+#[derive(Clone)]
+struct Circuit([Option<Fr>; 4]);
+
+impl ConstraintSynthesizer<Fr> for Circuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let v = self.0;
+        let ins = v
+            .iter()
+            .map(|x| cs.new_input_variable(|| x.ok_or(SynthesisError::AssignmentMissing)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let prod = cs.new_witness_variable(|| {
+            Ok(v[1].ok_or(SynthesisError::AssignmentMissing)?
+                * v[2].ok_or(SynthesisError::AssignmentMissing)?)
+        })?;
+        cs.enforce_constraint(lc!() + ins[1], lc!() + ins[2], lc!() + prod)?;
+        cs.enforce_constraint(
+            lc!() + prod + ins[0],
+            lc!() + ark_relations::r1cs::Variable::One,
+            lc!() + ins[3],
+        )?;
+        Ok(())
+    }
+}
+
+fn fq(x: &Fq) -> String {
+    x.to_string()
+}
+fn fq2(x: &Fq2) -> String {
+    format!("[\"{}\", \"{}\"]", fq(&x.c0), fq(&x.c1))
+}
+fn g1(p: &G1Affine) -> String {
+    let (x, y) = p.xy().unwrap();
+    format!("[\"{}\", \"{}\", \"1\"]", fq(x), fq(y))
+}
+fn g2(p: &G2Affine) -> String {
+    let (x, y) = p.xy().unwrap();
+    format!("[{}, {}, [\"1\", \"0\"]]", fq2(x), fq2(y))
+}
+
+fn main() {
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(0x62617a756b61);
+    let (pk, vk) =
+        Groth16::<Bls12_381>::circuit_specific_setup(Circuit([None; 4]), &mut rng).unwrap();
+    let inputs = [
+        Fr::from(7u64),
+        Fr::from(3u64),
+        Fr::from(5u64),
+        Fr::from(22u64),
+    ];
+    let proof = Groth16::<Bls12_381>::prove(&pk, Circuit(inputs.map(Some)), &mut rng).unwrap();
+    assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+    assert!(!Groth16::<Bls12_381>::verify(
+        &vk,
+        &[inputs[0], inputs[1], inputs[2], Fr::from(23u64)],
+        &proof
+    )
+    .unwrap());
+
+    let mut b = Vec::new();
+    vk.serialize_compressed(&mut b).unwrap();
+    std::fs::write("arkworks_vk.bin", &b).unwrap();
+    let mut b = Vec::new();
+    vk.serialize_uncompressed(&mut b).unwrap();
+    std::fs::write("arkworks_vk_uncompressed.bin", &b).unwrap();
+    let mut b = Vec::new();
+    proof.serialize_compressed(&mut b).unwrap();
+    std::fs::write("arkworks_proof.bin", &b).unwrap();
+
+    let ab = Bls12_381::pairing(vk.alpha_g1, vk.beta_g2).0;
+    let fq6 = |x: &ark_bls12_381::Fq6| format!("[{}, {}, {}]", fq2(&x.c0), fq2(&x.c1), fq2(&x.c2));
+    let ic = vk
+        .gamma_abc_g1
+        .iter()
+        .map(g1)
+        .collect::<Vec<_>>()
+        .join(",\n  ");
+    let vk_json = format!(
+        "{{\n \"protocol\": \"groth16\",\n \"curve\": \"bls12381\",\n \"nPublic\": 4,\n \"vk_alpha_1\": {},\n \"vk_beta_2\": {},\n \"vk_gamma_2\": {},\n \"vk_delta_2\": {},\n \"vk_alphabeta_12\": [{}, {}],\n \"IC\": [\n  {}\n ]\n}}\n",
+        g1(&vk.alpha_g1), g2(&vk.beta_g2), g2(&vk.gamma_g2), g2(&vk.delta_g2), fq6(&ab.c0), fq6(&ab.c1), ic
+    );
+    std::fs::write("verification_key.json", vk_json).unwrap();
+    let proof_json = format!(
+        "{{\n \"pi_a\": {},\n \"pi_b\": {},\n \"pi_c\": {},\n \"protocol\": \"groth16\",\n \"curve\": \"bls12381\"\n}}\n",
+        g1(&proof.a), g2(&proof.b), g1(&proof.c)
+    );
+    std::fs::write("proof.json", proof_json).unwrap();
+    std::fs::write(
+        "public.json",
+        format!(
+            "[\n \"{}\",\n \"{}\",\n \"{}\",\n \"{}\"\n]\n",
+            inputs[0], inputs[1], inputs[2], inputs[3]
+        ),
+    )
+    .unwrap();
+}
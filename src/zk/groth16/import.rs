@@ -0,0 +1,207 @@
+use super::*;
+use std::str::FromStr;
+
+/// Formats of keys and proofs produced by standard Groth16 tooling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Groth16Format {
+    /// `verification_key.json`/`proof.json` files of snarkjs
+    Snarkjs,
+    /// `CanonicalSerialize` bytes of arkworks 0.4, compressed or not
+    Arkworks,
+    /// `write` bytes of bellman
+    Bellman,
+}
+
+impl FromStr for Groth16Format {
+    type Err = Groth16Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "snarkjs" => Ok(Groth16Format::Snarkjs),
+            "arkworks" => Ok(Groth16Format::Arkworks),
+            "bellman" => Ok(Groth16Format::Bellman),
+            _ => Err(Groth16Error::InvalidFormat(format!("unknown format {}", s))),
+        }
+    }
+}
+
+// Projective coordinates, as decimal strings
+type SnarkjsG1 = [String; 3];
+type SnarkjsG2 = [[String; 2]; 3];
+
+#[derive(Deserialize)]
+struct SnarkjsVerifyingKey {
+    protocol: String,
+    curve: String,
+    vk_alpha_1: SnarkjsG1,
+    vk_beta_2: SnarkjsG2,
+    vk_gamma_2: SnarkjsG2,
+    vk_delta_2: SnarkjsG2,
+    #[serde(rename = "IC")]
+    ic: Vec<SnarkjsG1>,
+}
+
+#[derive(Deserialize)]
+struct SnarkjsProof {
+    protocol: String,
+    curve: String,
+    pi_a: SnarkjsG1,
+    pi_b: SnarkjsG2,
+    pi_c: SnarkjsG1,
+}
+
+fn check_snarkjs_header(protocol: &str, curve: &str) -> Result<(), Groth16Error> {
+    if protocol != "groth16" || curve != "bls12381" {
+        return Err(Groth16Error::InvalidFormat(format!(
+            "expected a groth16 key/proof over bls12381, got {} over {}",
+            protocol, curve
+        )));
+    }
+    Ok(())
+}
+
+fn snarkjs_fp(s: &str) -> Result<BigUint, Groth16Error> {
+    BigUint::parse_bytes(s.as_bytes(), 10)
+        .ok_or_else(|| Groth16Error::InvalidFormat(format!("invalid field element {}", s)))
+}
+
+// Points are expected to be normalized, i.e. have a z-coordinate of either
+// one, or zero for the point at infinity
+fn snarkjs_is_infinity(z: &[&str]) -> Result<bool, Groth16Error> {
+    match z {
+        ["1"] | ["1", "0"] => Ok(false),
+        ["0"] | ["0", "0"] => Ok(true),
+        _ => Err(Groth16Error::InvalidFormat(
+            "points are not normalized".into(),
+        )),
+    }
+}
+
+fn snarkjs_g1(p: &SnarkjsG1) -> Result<G1Point, Groth16Error> {
+    g1_point(if snarkjs_is_infinity(&[&p[2]])? {
+        None
+    } else {
+        Some([snarkjs_fp(&p[0])?, snarkjs_fp(&p[1])?])
+    })
+}
+
+fn snarkjs_g2(p: &SnarkjsG2) -> Result<G2Point, Groth16Error> {
+    g2_point(if snarkjs_is_infinity(&[&p[2][0], &p[2][1]])? {
+        None
+    } else {
+        Some([
+            snarkjs_fp(&p[0][0])?,
+            snarkjs_fp(&p[0][1])?,
+            snarkjs_fp(&p[1][0])?,
+            snarkjs_fp(&p[1][1])?,
+        ])
+    })
+}
+
+// Arkworks uses the same point encodings as `bls12_381`, the compression
+// being marked by the most significant bit of each point
+fn arkworks_g1(reader: &mut &[u8]) -> Result<G1Point, Groth16Error> {
+    let point = if reader.first().map(|b| b & 0x80 != 0) == Some(true) {
+        let mut bytes = [0u8; 48];
+        reader.read_exact(&mut bytes)?;
+        G1Affine::from_compressed(&bytes)
+    } else {
+        let mut bytes = [0u8; 96];
+        reader.read_exact(&mut bytes)?;
+        G1Affine::from_uncompressed(&bytes)
+    };
+    Option::from(point)
+        .map(G1Point)
+        .ok_or(Groth16Error::InvalidPoint)
+}
+
+fn arkworks_g2(reader: &mut &[u8]) -> Result<G2Point, Groth16Error> {
+    let point = if reader.first().map(|b| b & 0x80 != 0) == Some(true) {
+        let mut bytes = [0u8; 96];
+        reader.read_exact(&mut bytes)?;
+        G2Affine::from_compressed(&bytes)
+    } else {
+        let mut bytes = [0u8; 192];
+        reader.read_exact(&mut bytes)?;
+        G2Affine::from_uncompressed(&bytes)
+    };
+    Option::from(point)
+        .map(G2Point)
+        .ok_or(Groth16Error::InvalidPoint)
+}
+
+impl Groth16VerifyingKey {
+    /// Imports a key exported by other tooling. Formats that don't carry
+    /// `beta_g1`/`delta_g1` leave them at infinity, as they are not needed
+    /// for verification.
+    pub fn import(format: Groth16Format, bytes: &[u8]) -> Result<Self, Groth16Error> {
+        match format {
+            Groth16Format::Snarkjs => {
+                let vk: SnarkjsVerifyingKey = serde_json::from_slice(bytes)?;
+                check_snarkjs_header(&vk.protocol, &vk.curve)?;
+                Ok(Self {
+                    alpha_g1: snarkjs_g1(&vk.vk_alpha_1)?,
+                    beta_g1: G1Point(G1Affine::identity()),
+                    beta_g2: snarkjs_g2(&vk.vk_beta_2)?,
+                    gamma_g2: snarkjs_g2(&vk.vk_gamma_2)?,
+                    delta_g1: G1Point(G1Affine::identity()),
+                    delta_g2: snarkjs_g2(&vk.vk_delta_2)?,
+                    ic: vk.ic.iter().map(snarkjs_g1).collect::<Result<_, _>>()?,
+                })
+            }
+            Groth16Format::Arkworks => {
+                let mut reader = bytes;
+                let alpha_g1 = arkworks_g1(&mut reader)?;
+                let beta_g2 = arkworks_g2(&mut reader)?;
+                let gamma_g2 = arkworks_g2(&mut reader)?;
+                let delta_g2 = arkworks_g2(&mut reader)?;
+                let mut len = [0u8; 8];
+                reader.read_exact(&mut len)?;
+                let ic = (0..u64::from_le_bytes(len))
+                    .map(|_| arkworks_g1(&mut reader))
+                    .collect::<Result<_, _>>()?;
+                check_consumed(reader)?;
+                Ok(Self {
+                    alpha_g1,
+                    beta_g1: G1Point(G1Affine::identity()),
+                    beta_g2,
+                    gamma_g2,
+                    delta_g1: G1Point(G1Affine::identity()),
+                    delta_g2,
+                    ic,
+                })
+            }
+            Groth16Format::Bellman => {
+                let mut reader = bytes;
+                let vk = VerifyingKey::<Bls12>::read(&mut reader)?;
+                check_consumed(reader)?;
+                Ok((&vk).into())
+            }
+        }
+    }
+}
+
+impl Groth16Proof {
+    /// Imports a proof generated by other tooling
+    pub fn import(format: Groth16Format, bytes: &[u8]) -> Result<Self, Groth16Error> {
+        let mut reader = bytes;
+        let proof = match format {
+            Groth16Format::Snarkjs => {
+                let proof: SnarkjsProof = serde_json::from_slice(bytes)?;
+                check_snarkjs_header(&proof.protocol, &proof.curve)?;
+                return Ok(Self {
+                    a: snarkjs_g1(&proof.pi_a)?,
+                    b: snarkjs_g2(&proof.pi_b)?,
+                    c: snarkjs_g1(&proof.pi_c)?,
+                });
+            }
+            Groth16Format::Arkworks => Self {
+                a: arkworks_g1(&mut reader)?,
+                b: arkworks_g2(&mut reader)?,
+                c: arkworks_g1(&mut reader)?,
+            },
+            Groth16Format::Bellman => (&Proof::<Bls12>::read(&mut reader)?).into(),
+        };
+        check_consumed(reader)?;
+        Ok(proof)
+    }
+}
@@ -0,0 +1,274 @@
+//! Bellman gadgets for writing the circuits of Zero Contracts, following the
+//! conventions of the verifier: states are compressed with `PoseidonHasher`
+//! into 4-ary trees, signatures are `jubjub` ones, and the public inputs are
+//! given in the order `groth16_verify` provides them.
+
+use super::*;
+use bellman::groth16::{create_proof, Parameters};
+use bellman::{LinearCombination, Variable};
+use rand::Rng;
+#[cfg(any(test, feature = "prover-dev"))]
+use {
+    bellman::groth16::generate_parameters,
+    bls12_381::{G1Projective, G2Projective},
+};
+
+pub use bellman::gadgets::{boolean::Boolean, num::AllocatedNum};
+pub use bellman::{Circuit, ConstraintSystem, SynthesisError};
+pub use bls12_381::Scalar as BellmanFr;
+
+mod eddsa;
+mod merkle;
+mod poseidon;
+pub use eddsa::*;
+pub use merkle::*;
+pub use poseidon::*;
+
+// A linear combination of variables along with its value. Terms of the same
+// variable are merged, so that combinations stay small when they are
+// combined again and again.
+#[derive(Clone)]
+struct Combination {
+    terms: Vec<(Variable, BellmanFr)>,
+    value: Option<BellmanFr>,
+}
+
+impl Combination {
+    fn constant<CS: ConstraintSystem<BellmanFr>>(value: BellmanFr) -> Self {
+        Self {
+            terms: vec![(CS::one(), value)],
+            value: Some(value),
+        }
+    }
+
+    fn num(num: &AllocatedNum<BellmanFr>) -> Self {
+        Self {
+            terms: vec![(num.get_variable(), BellmanFr::one())],
+            value: num.get_value(),
+        }
+    }
+
+    // The bit multiplied by `coeff`
+    fn bit<CS: ConstraintSystem<BellmanFr>>(bit: &Boolean, coeff: BellmanFr) -> Self {
+        let mut comb = Self {
+            terms: Vec::new(),
+            value: bit
+                .get_value()
+                .map(|b| if b { coeff } else { BellmanFr::zero() }),
+        };
+        match bit {
+            Boolean::Is(bit) => comb.push(bit.get_variable(), coeff),
+            Boolean::Not(bit) => {
+                comb.push(CS::one(), coeff);
+                comb.push(bit.get_variable(), -coeff);
+            }
+            Boolean::Constant(true) => comb.push(CS::one(), coeff),
+            Boolean::Constant(false) => {}
+        }
+        comb
+    }
+
+    fn push(&mut self, var: Variable, coeff: BellmanFr) {
+        let index = var.get_unchecked();
+        if let Some((_, c)) = self
+            .terms
+            .iter_mut()
+            .find(|(v, _)| v.get_unchecked() == index)
+        {
+            *c += coeff;
+        } else {
+            self.terms.push((var, coeff));
+        }
+    }
+
+    // Adds `coeff` times `other`
+    fn add(&mut self, other: &Combination, coeff: BellmanFr) {
+        for (var, c) in other.terms.iter() {
+            self.push(*var, *c * coeff);
+        }
+        self.value = self.value.zip(other.value).map(|(a, b)| a + b * coeff);
+    }
+
+    fn plus(&self, other: &Combination, coeff: BellmanFr) -> Self {
+        let mut result = self.clone();
+        result.add(other, coeff);
+        result
+    }
+
+    fn lc(&self) -> LinearCombination<BellmanFr> {
+        self.terms
+            .iter()
+            .fold(LinearCombination::zero(), |lc, (var, c)| lc + (*c, *var))
+    }
+
+    fn value(&self) -> Result<BellmanFr, SynthesisError> {
+        self.value.ok_or(SynthesisError::AssignmentMissing)
+    }
+
+    // Allocates the product of the combinations
+    fn mul<CS: ConstraintSystem<BellmanFr>>(
+        mut cs: CS,
+        a: &Combination,
+        b: &Combination,
+    ) -> Result<Self, SynthesisError> {
+        let product =
+            AllocatedNum::alloc(cs.namespace(|| "product"), || Ok(a.value()? * b.value()?))?;
+        cs.enforce(
+            || "product constraint",
+            |_| a.lc(),
+            |_| b.lc(),
+            |lc| lc + product.get_variable(),
+        );
+        Ok(Self::num(&product))
+    }
+
+    // Allocates the quotient of the combinations, the divisor being nonzero
+    fn div<CS: ConstraintSystem<BellmanFr>>(
+        mut cs: CS,
+        a: &Combination,
+        b: &Combination,
+    ) -> Result<Self, SynthesisError> {
+        let quotient = AllocatedNum::alloc(cs.namespace(|| "quotient"), || {
+            let inv: Option<BellmanFr> = b.value()?.invert().into();
+            Ok(a.value()? * inv.ok_or(SynthesisError::DivisionByZero)?)
+        })?;
+        cs.enforce(
+            || "quotient constraint",
+            |lc| lc + quotient.get_variable(),
+            |_| b.lc(),
+            |_| a.lc(),
+        );
+        Ok(Self::num(&quotient))
+    }
+
+    fn alloc<CS: ConstraintSystem<BellmanFr>>(
+        &self,
+        mut cs: CS,
+    ) -> Result<AllocatedNum<BellmanFr>, SynthesisError> {
+        let num = AllocatedNum::alloc(cs.namespace(|| "num"), || self.value())?;
+        enforce_equal(cs.namespace(|| "num constraint"), &num, self)?;
+        Ok(num)
+    }
+}
+
+fn enforce_equal<CS: ConstraintSystem<BellmanFr>>(
+    mut cs: CS,
+    num: &AllocatedNum<BellmanFr>,
+    comb: &Combination,
+) -> Result<(), SynthesisError> {
+    cs.enforce(
+        || "equality",
+        |lc| lc + num.get_variable(),
+        |lc| lc + CS::one(),
+        |_| comb.lc(),
+    );
+    Ok(())
+}
+
+/// Allocates a private input of the circuit
+pub fn alloc_scalar<CS: ConstraintSystem<BellmanFr>>(
+    mut cs: CS,
+    value: Option<ZkScalar>,
+) -> Result<AllocatedNum<BellmanFr>, SynthesisError> {
+    AllocatedNum::alloc(cs.namespace(|| "scalar"), || {
+        value
+            .map(Into::into)
+            .ok_or(SynthesisError::AssignmentMissing)
+    })
+}
+
+/// Enforces the numbers to be equal
+pub fn enforce_equal_nums<CS: ConstraintSystem<BellmanFr>>(
+    cs: CS,
+    a: &AllocatedNum<BellmanFr>,
+    b: &AllocatedNum<BellmanFr>,
+) -> Result<(), SynthesisError> {
+    enforce_equal(cs, a, &Combination::num(b))
+}
+
+/// Public inputs of the circuit of a contract. They have to be allocated
+/// before any other public input, in the order `groth16_verify` provides
+/// them, which `alloc` takes care of.
+pub struct PublicInputs {
+    pub prev_height: AllocatedNum<BellmanFr>,
+    pub prev_state: AllocatedNum<BellmanFr>,
+    pub aux_data: AllocatedNum<BellmanFr>,
+    pub next_state: AllocatedNum<BellmanFr>,
+}
+
+impl PublicInputs {
+    /// Values are `None` when generating the parameters of the circuit
+    pub fn alloc<CS: ConstraintSystem<BellmanFr>>(
+        cs: &mut CS,
+        prev_height: Option<u64>,
+        prev_state: Option<ZkScalar>,
+        aux_data: Option<ZkScalar>,
+        next_state: Option<ZkScalar>,
+    ) -> Result<Self, SynthesisError> {
+        let mut input = |name: &'static str,
+                         value: Option<ZkScalar>|
+         -> Result<AllocatedNum<BellmanFr>, SynthesisError> {
+            let num = alloc_scalar(cs.namespace(|| name), value)?;
+            num.inputize(cs.namespace(|| format!("{} input", name)))?;
+            Ok(num)
+        };
+        Ok(Self {
+            prev_height: input("prev_height", prev_height.map(ZkScalar::from))?,
+            prev_state: input("prev_state", prev_state)?,
+            aux_data: input("aux_data", aux_data)?,
+            next_state: input("next_state", next_state)?,
+        })
+    }
+}
+
+fn random_scalar() -> BellmanFr {
+    let mut bytes = [0u8; 64];
+    rand::thread_rng().fill(&mut bytes[..]);
+    BellmanFr::from_bytes_wide(&bytes)
+}
+
+/// Proving parameters of a circuit
+pub struct Groth16Params {
+    params: Parameters<Bls12>,
+}
+
+impl Groth16Params {
+    /// Reads parameters written by bellman, e.g. the outcome of a trusted
+    /// setup ceremony. Points are checked to be valid.
+    pub fn read<R: Read>(reader: R) -> Result<Self, Groth16Error> {
+        Ok(Self {
+            params: Parameters::read(reader, true)?,
+        })
+    }
+
+    /// Generates the parameters with local randomness. Whoever runs the setup
+    /// is able to forge proofs, so it is only available to tests and builds
+    /// with the `prover-dev` feature.
+    #[cfg(any(test, feature = "prover-dev"))]
+    pub fn setup<C: Circuit<BellmanFr>>(circuit: C) -> Result<Self, SynthesisError> {
+        let params = generate_parameters::<Bls12, _>(
+            circuit,
+            G1Projective::generator(),
+            G2Projective::generator(),
+            random_scalar(),
+            random_scalar(),
+            random_scalar(),
+            random_scalar(),
+            random_scalar(),
+        )?;
+        Ok(Self { params })
+    }
+
+    pub fn verifying_key(&self) -> Groth16VerifyingKey {
+        (&self.params.vk).into()
+    }
+
+    pub fn prove<C: Circuit<BellmanFr>>(&self, circuit: C) -> Result<Groth16Proof, SynthesisError> {
+        let proof =
+            create_proof::<Bls12, _, _>(circuit, &self.params, random_scalar(), random_scalar())?;
+        Ok((&proof).into())
+    }
+}
+
+#[cfg(test)]
+mod test;
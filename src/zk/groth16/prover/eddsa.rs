@@ -0,0 +1,251 @@
+use super::*;
+use crate::crypto::jubjub::{PointAffine, Signature, BASE, D};
+
+lazy_static! {
+    static ref D_FR: BellmanFr = BellmanFr::from(*D);
+    // 2^i * BASE, for the bits of any scalar
+    static ref BASE_POWERS: Vec<PointAffine> = {
+        let mut powers = vec![*BASE];
+        for _ in 1..ZkScalar::NUM_BITS {
+            powers.push(powers.last().unwrap().double());
+        }
+        powers
+    };
+}
+
+// A point whose coordinates are combinations
+#[derive(Clone)]
+struct Point(Combination, Combination);
+
+impl Point {
+    fn constant<CS: ConstraintSystem<BellmanFr>>(p: &PointAffine) -> Self {
+        Self(
+            Combination::constant::<CS>(p.0.into()),
+            Combination::constant::<CS>(p.1.into()),
+        )
+    }
+
+    // Addition of the twisted Edwards curve of `jubjub`, with `a = -1`. It is
+    // complete, so it also doubles points, and takes six constraints.
+    fn add<CS: ConstraintSystem<BellmanFr>>(
+        &self,
+        mut cs: CS,
+        other: &Point,
+    ) -> Result<Point, SynthesisError> {
+        let one = BellmanFr::one();
+        let x1x2 = Combination::mul(cs.namespace(|| "x1x2"), &self.0, &other.0)?;
+        let y1y2 = Combination::mul(cs.namespace(|| "y1y2"), &self.1, &other.1)?;
+        let cross = Combination::mul(
+            cs.namespace(|| "(x1+y1)(x2+y2)"),
+            &self.0.plus(&self.1, one),
+            &other.0.plus(&other.1, one),
+        )?;
+        let prod = Combination::mul(cs.namespace(|| "x1x2y1y2"), &x1x2, &y1y2)?;
+        let x = Combination::div(
+            cs.namespace(|| "x"),
+            &cross.plus(&x1x2, -one).plus(&y1y2, -one),
+            &Combination::constant::<CS>(one).plus(&prod, *D_FR),
+        )?;
+        let y = Combination::div(
+            cs.namespace(|| "y"),
+            &y1y2.plus(&x1x2, one),
+            &Combination::constant::<CS>(one).plus(&prod, -*D_FR),
+        )?;
+        Ok(Point(x, y))
+    }
+
+    // `a` if the bit is set, `b` otherwise
+    fn select<CS: ConstraintSystem<BellmanFr>>(
+        mut cs: CS,
+        bit: &Boolean,
+        a: &Point,
+        b: &Point,
+    ) -> Result<Point, SynthesisError> {
+        let one = BellmanFr::one();
+        let bit = Combination::bit::<CS>(bit, one);
+        let dx = Combination::mul(cs.namespace(|| "x"), &bit, &a.0.plus(&b.0, -one))?;
+        let dy = Combination::mul(cs.namespace(|| "y"), &bit, &a.1.plus(&b.1, -one))?;
+        Ok(Point(b.0.plus(&dx, one), b.1.plus(&dy, one)))
+    }
+
+    fn alloc<CS: ConstraintSystem<BellmanFr>>(
+        &self,
+        mut cs: CS,
+    ) -> Result<AllocatedPoint, SynthesisError> {
+        Ok(AllocatedPoint {
+            x: self.0.alloc(cs.namespace(|| "x"))?,
+            y: self.1.alloc(cs.namespace(|| "y"))?,
+        })
+    }
+}
+
+/// A point of the curve of `jubjub`, allocated in a circuit
+#[derive(Clone)]
+pub struct AllocatedPoint {
+    x: AllocatedNum<BellmanFr>,
+    y: AllocatedNum<BellmanFr>,
+}
+
+impl AllocatedPoint {
+    /// Allocates a point, enforced to be on the curve
+    pub fn alloc<CS: ConstraintSystem<BellmanFr>>(
+        mut cs: CS,
+        value: Option<PointAffine>,
+    ) -> Result<Self, SynthesisError> {
+        let x = alloc_scalar(cs.namespace(|| "x"), value.map(|p| p.0))?;
+        let y = alloc_scalar(cs.namespace(|| "y"), value.map(|p| p.1))?;
+        Self::from_coordinates(cs, x, y)
+    }
+
+    /// The point of the given coordinates, enforced to be on the curve
+    pub fn from_coordinates<CS: ConstraintSystem<BellmanFr>>(
+        mut cs: CS,
+        x: AllocatedNum<BellmanFr>,
+        y: AllocatedNum<BellmanFr>,
+    ) -> Result<Self, SynthesisError> {
+        // y^2 - x^2 = 1 + d * x^2 * y^2
+        let one = BellmanFr::one();
+        let xx = Combination::mul(
+            cs.namespace(|| "x^2"),
+            &Combination::num(&x),
+            &Combination::num(&x),
+        )?;
+        let yy = Combination::mul(
+            cs.namespace(|| "y^2"),
+            &Combination::num(&y),
+            &Combination::num(&y),
+        )?;
+        let d_xx = Combination::constant::<CS>(BellmanFr::zero()).plus(&xx, *D_FR);
+        cs.enforce(
+            || "on curve",
+            |_| d_xx.lc(),
+            |_| yy.lc(),
+            |_| {
+                yy.plus(&xx, -one)
+                    .plus(&Combination::constant::<CS>(one), -one)
+                    .lc()
+            },
+        );
+        Ok(Self { x, y })
+    }
+
+    pub fn x(&self) -> &AllocatedNum<BellmanFr> {
+        &self.x
+    }
+
+    pub fn y(&self) -> &AllocatedNum<BellmanFr> {
+        &self.y
+    }
+
+    pub fn get_value(&self) -> Option<PointAffine> {
+        Some(PointAffine(
+            self.x.get_value()?.into(),
+            self.y.get_value()?.into(),
+        ))
+    }
+
+    fn point(&self) -> Point {
+        Point(Combination::num(&self.x), Combination::num(&self.y))
+    }
+
+    pub fn add<CS: ConstraintSystem<BellmanFr>>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        self.point()
+            .add(cs.namespace(|| "add"), &other.point())?
+            .alloc(cs.namespace(|| "sum"))
+    }
+
+    /// The point multiplied by the scalar of the bits, least significant
+    /// first, just like `PointAffine::multiply`
+    pub fn multiply<CS: ConstraintSystem<BellmanFr>>(
+        &self,
+        mut cs: CS,
+        bits: &[Boolean],
+    ) -> Result<Self, SynthesisError> {
+        let base = self.point();
+        let mut result = Point::constant::<CS>(&PointAffine::zero());
+        for (i, bit) in bits.iter().enumerate().rev() {
+            let mut cs = cs.namespace(|| format!("bit {}", i));
+            let doubled = result.add(cs.namespace(|| "double"), &result)?;
+            let added = doubled.add(cs.namespace(|| "add"), &base)?;
+            result = Point::select(cs.namespace(|| "select"), bit, &added, &doubled)?;
+        }
+        result.alloc(cs.namespace(|| "result"))
+    }
+}
+
+/// `BASE` multiplied by the scalar of the bits, least significant first
+pub fn multiply_base<CS: ConstraintSystem<BellmanFr>>(
+    mut cs: CS,
+    bits: &[Boolean],
+) -> Result<AllocatedPoint, SynthesisError> {
+    if bits.len() > BASE_POWERS.len() {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    let one = BellmanFr::one();
+    let mut result = Point::constant::<CS>(&PointAffine::zero());
+    for (i, (bit, power)) in bits.iter().zip(BASE_POWERS.iter()).enumerate() {
+        // Either the power or the identity, i.e. (0, 1)
+        let term = Point(
+            Combination::bit::<CS>(bit, power.0.into()),
+            Combination::constant::<CS>(one)
+                .plus(&Combination::bit::<CS>(bit, power.1.into()), one)
+                .plus(&Combination::bit::<CS>(bit, one), -one),
+        );
+        result = result.add(cs.namespace(|| format!("bit {}", i)), &term)?;
+    }
+    result.alloc(cs.namespace(|| "result"))
+}
+
+/// A `jubjub` signature, allocated in a circuit
+#[derive(Clone)]
+pub struct AllocatedSignature {
+    pub r: AllocatedPoint,
+    pub s: AllocatedNum<BellmanFr>,
+}
+
+impl AllocatedSignature {
+    pub fn alloc<CS: ConstraintSystem<BellmanFr>>(
+        mut cs: CS,
+        value: Option<&Signature>,
+    ) -> Result<Self, SynthesisError> {
+        Ok(Self {
+            r: AllocatedPoint::alloc(cs.namespace(|| "r"), value.map(|sig| sig.r))?,
+            s: alloc_scalar(cs.namespace(|| "s"), value.map(|sig| sig.s))?,
+        })
+    }
+}
+
+/// Enforces the signature of the message to be valid for the public key, as
+/// checked by `JubJub<PoseidonHasher>::verify`
+pub fn verify_signature<CS: ConstraintSystem<BellmanFr>>(
+    mut cs: CS,
+    pub_key: &AllocatedPoint,
+    message: &AllocatedNum<BellmanFr>,
+    sig: &AllocatedSignature,
+) -> Result<(), SynthesisError> {
+    // h=H(R,A,M)
+    let h = poseidon(
+        cs.namespace(|| "h"),
+        &[
+            sig.r.x.clone(),
+            sig.r.y.clone(),
+            pub_key.x.clone(),
+            pub_key.y.clone(),
+            message.clone(),
+        ],
+    )?;
+    let h_bits = h.to_bits_le_strict(cs.namespace(|| "h bits"))?;
+    let s_bits = sig.s.to_bits_le_strict(cs.namespace(|| "s bits"))?;
+
+    // sB == R + hA
+    let sb = multiply_base(cs.namespace(|| "sB"), &s_bits)?;
+    let ha = pub_key.multiply(cs.namespace(|| "hA"), &h_bits)?;
+    let r_plus_ha = ha.point().add(cs.namespace(|| "R + hA"), &sig.r.point())?;
+    enforce_equal(cs.namespace(|| "x"), &sb.x, &r_plus_ha.0)?;
+    enforce_equal(cs.namespace(|| "y"), &sb.y, &r_plus_ha.1)?;
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use super::*;
+use ff::PrimeFieldBits;
+
+/// Bits of the index of an item of a `List`, two per layer of its tree and
+/// least significant first. They are enforced to compose the index, which
+/// makes indices out of the list unsatisfiable.
+pub fn alloc_index_bits<CS: ConstraintSystem<BellmanFr>>(
+    mut cs: CS,
+    index: &AllocatedNum<BellmanFr>,
+    log4_size: u8,
+) -> Result<Vec<Boolean>, SynthesisError> {
+    let index_bits = index.get_value().map(|v| ZkScalar::from(v).to_le_bits());
+    let mut bits = Vec::new();
+    let mut sum = Combination::constant::<CS>(BellmanFr::zero());
+    let mut coeff = BellmanFr::one();
+    for i in 0..2 * log4_size as usize {
+        let bit = Boolean::from(bellman::gadgets::boolean::AllocatedBit::alloc(
+            cs.namespace(|| format!("bit {}", i)),
+            index_bits.as_ref().map(|bits| bits[i]),
+        )?);
+        sum.add(&Combination::bit::<CS>(&bit, coeff), BellmanFr::one());
+        coeff = coeff.double();
+        bits.push(bit);
+    }
+    enforce_equal(cs.namespace(|| "index"), index, &sum)?;
+    Ok(bits)
+}
+
+// A condition as a combination of bits, whose value is either zero or one
+struct Condition(Combination);
+
+impl Condition {
+    // `a` if the condition holds, `b` otherwise, taking one constraint
+    fn select<CS: ConstraintSystem<BellmanFr>>(
+        &self,
+        cs: CS,
+        a: &Combination,
+        b: &Combination,
+    ) -> Result<Combination, SynthesisError> {
+        let diff = Combination::mul(cs, &self.0, &a.plus(b, -BellmanFr::one()))?;
+        Ok(b.plus(&diff, BellmanFr::one()))
+    }
+}
+
+/// Root of the tree of a `List`, given the compressed value of one of its
+/// items, the bits of its index, and the proof `KvStoreStateManager::prove`
+/// gives for it, i.e. the siblings of its path from the leaf up to the root
+pub fn merkle_root<CS: ConstraintSystem<BellmanFr>>(
+    mut cs: CS,
+    leaf: &AllocatedNum<BellmanFr>,
+    index_bits: &[Boolean],
+    proof: &[[AllocatedNum<BellmanFr>; 3]],
+) -> Result<AllocatedNum<BellmanFr>, SynthesisError> {
+    if index_bits.len() != 2 * proof.len() {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    let one = BellmanFr::one();
+    let mut curr = Combination::num(leaf);
+    for (layer, (bits, siblings)) in index_bits.chunks(2).zip(proof.iter()).enumerate() {
+        let mut cs = cs.namespace(|| format!("layer {}", layer));
+        let (b0, b1) = (&bits[0], &bits[1]);
+        let both = Boolean::and(cs.namespace(|| "both"), b0, b1)?;
+        let b0 = Combination::bit::<CS>(b0, one);
+        let b1 = Combination::bit::<CS>(b1, one);
+        let both = Combination::bit::<CS>(&both, one);
+        // Position of the current node among its siblings
+        let is = [
+            Combination::constant::<CS>(one)
+                .plus(&b0, -one)
+                .plus(&b1, -one)
+                .plus(&both, one),
+            b0.plus(&both, -one),
+            b1.plus(&both, -one),
+            both,
+        ]
+        .map(Condition);
+        let below_2 = Condition(Combination::constant::<CS>(one).plus(&b1, -one));
+
+        let c = curr;
+        let s = siblings.iter().map(Combination::num).collect::<Vec<_>>();
+        let children = [
+            is[0].select(cs.namespace(|| "child 0"), &c, &s[0])?,
+            {
+                let t = is[0].select(cs.namespace(|| "child 1 sibling"), &s[0], &s[1])?;
+                is[1].select(cs.namespace(|| "child 1"), &c, &t)?
+            },
+            {
+                let t = below_2.select(cs.namespace(|| "child 2 sibling"), &s[1], &s[2])?;
+                is[2].select(cs.namespace(|| "child 2"), &c, &t)?
+            },
+            is[3].select(cs.namespace(|| "child 3"), &c, &s[2])?,
+        ];
+        curr = hash(cs.namespace(|| "hash"), &children)?;
+    }
+    curr.alloc(cs.namespace(|| "root"))
+}
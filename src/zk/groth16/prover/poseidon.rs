@@ -0,0 +1,61 @@
+use super::*;
+use crate::zk::poseidon::PoseidonParams;
+
+// x^5, taking three constraints
+fn quintic_s_box<CS: ConstraintSystem<BellmanFr>>(
+    mut cs: CS,
+    x: &Combination,
+) -> Result<Combination, SynthesisError> {
+    let x2 = Combination::mul(cs.namespace(|| "x^2"), x, x)?;
+    let x4 = Combination::mul(cs.namespace(|| "x^4"), &x2, &x2)?;
+    Combination::mul(cs.namespace(|| "x^5"), &x4, x)
+}
+
+pub(super) fn hash<CS: ConstraintSystem<BellmanFr>>(
+    mut cs: CS,
+    vals: &[Combination],
+) -> Result<Combination, SynthesisError> {
+    let params = PoseidonParams::for_width(vals.len() + 1)
+        .filter(|_| !vals.is_empty())
+        .ok_or(SynthesisError::Unsatisfiable)?;
+    let mut elements = vec![Combination::constant::<CS>(BellmanFr::zero())];
+    elements.extend(vals.iter().cloned());
+
+    let mut constants = params.round_constants.iter().map(|c| BellmanFr::from(*c));
+    let rounds = params.full_rounds + params.partial_rounds;
+    for round in 0..rounds {
+        let mut cs = cs.namespace(|| format!("round {}", round));
+        for elem in elements.iter_mut() {
+            let constant = constants.next().ok_or(SynthesisError::Unsatisfiable)?;
+            elem.add(&Combination::constant::<CS>(constant), BellmanFr::one());
+        }
+        let full = round < params.full_rounds / 2 || round >= rounds - params.full_rounds / 2;
+        for (i, elem) in elements.iter_mut().enumerate() {
+            if full || i == 0 {
+                *elem = quintic_s_box(cs.namespace(|| format!("s-box {}", i)), elem)?;
+            }
+        }
+        elements = params
+            .mds_constants
+            .iter()
+            .map(|row| {
+                let mut result = Combination::constant::<CS>(BellmanFr::zero());
+                for (c, elem) in row.iter().zip(elements.iter()) {
+                    result.add(elem, BellmanFr::from(*c));
+                }
+                result
+            })
+            .collect();
+    }
+
+    Ok(elements.swap_remove(1))
+}
+
+/// Hash of the values, as computed by `PoseidonHasher`
+pub fn poseidon<CS: ConstraintSystem<BellmanFr>>(
+    mut cs: CS,
+    vals: &[AllocatedNum<BellmanFr>],
+) -> Result<AllocatedNum<BellmanFr>, SynthesisError> {
+    let vals = vals.iter().map(Combination::num).collect::<Vec<_>>();
+    hash(cs.namespace(|| "poseidon"), &vals)?.alloc(cs.namespace(|| "hash"))
+}
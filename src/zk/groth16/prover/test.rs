@@ -0,0 +1,211 @@
+use super::*;
+use crate::core::ZkHasher;
+use crate::crypto::{jubjub, ZkSignatureScheme};
+use crate::zk::{poseidon, ZkDataLocator, ZkStateBuilder, ZkStateModel};
+use bellman::gadgets::test::TestConstraintSystem;
+
+#[test]
+fn test_poseidon_gadget() {
+    for arity in 1..poseidon::MAX_ARITY + 1 {
+        let vals = (0..arity)
+            .map(|i| ZkScalar::from(i as u64 * 123 + 45))
+            .collect::<Vec<_>>();
+        let mut cs = TestConstraintSystem::<BellmanFr>::new();
+        let nums = vals
+            .iter()
+            .enumerate()
+            .map(|(i, v)| alloc_scalar(cs.namespace(|| format!("val {}", i)), Some(*v)))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let hash = poseidon(cs.namespace(|| "poseidon"), &nums).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(
+            ZkScalar::from(hash.get_value().unwrap()),
+            poseidon::poseidon(&vals)
+        );
+    }
+}
+
+#[test]
+fn test_merkle_root_gadget() {
+    let state_model = ZkStateModel::List {
+        item_type: Box::new(ZkStateModel::Scalar),
+        log4_size: 3,
+    };
+    let mut builder = ZkStateBuilder::<ZkHasher>::new(state_model);
+    builder
+        .batch_set(&crate::zk::ZkDeltaPairs(
+            [5, 37, 63]
+                .into_iter()
+                .map(|i| (ZkDataLocator(vec![i]), Some(ZkScalar::from(i as u64 + 1))))
+                .collect(),
+        ))
+        .unwrap();
+
+    let root_of = |index: u32, leaf: ZkScalar, proof: &[[ZkScalar; 3]]| {
+        let mut cs = TestConstraintSystem::<BellmanFr>::new();
+        let index =
+            alloc_scalar(cs.namespace(|| "index"), Some(ZkScalar::from(index as u64))).unwrap();
+        let leaf = alloc_scalar(cs.namespace(|| "leaf"), Some(leaf)).unwrap();
+        let proof = proof
+            .iter()
+            .enumerate()
+            .map(|(i, siblings)| {
+                let mut cs = cs.namespace(|| format!("siblings {}", i));
+                [0, 1, 2].map(|j| {
+                    alloc_scalar(cs.namespace(|| format!("sibling {}", j)), Some(siblings[j]))
+                        .unwrap()
+                })
+            })
+            .collect::<Vec<_>>();
+        let bits = alloc_index_bits(cs.namespace(|| "bits"), &index, 3).unwrap();
+        let root = merkle_root(cs.namespace(|| "root"), &leaf, &bits, &proof).unwrap();
+        (cs.is_satisfied(), ZkScalar::from(root.get_value().unwrap()))
+    };
+
+    let mut proofs = Vec::new();
+    for index in [0, 5, 37, 62, 63] {
+        let leaf = builder.get(ZkDataLocator(vec![index])).unwrap();
+        proofs.push((
+            index,
+            leaf,
+            builder.prove(ZkDataLocator(vec![]), index).unwrap(),
+        ));
+    }
+    let root = builder.compress().unwrap().state_hash;
+    for (index, leaf, proof) in proofs.iter() {
+        assert_eq!(root_of(*index, *leaf, proof), (true, root));
+        let (satisfied, other_root) = root_of(*index, *leaf + ZkScalar::from(1), proof);
+        assert!(satisfied);
+        assert_ne!(other_root, root);
+    }
+    // Indices out of the list can't be decomposed
+    let (index, leaf, proof) = &proofs[0];
+    assert!(!root_of(index + 64, *leaf, proof).0);
+}
+
+#[test]
+fn test_signature_gadget() {
+    type JubJub = jubjub::JubJub<ZkHasher>;
+    let (pk, sk) = JubJub::generate_keys(b"ABC");
+    let message = ZkScalar::from(123456);
+    let sig = JubJub::sign(&sk, message);
+
+    let check = |pk: &jubjub::PublicKey, message: ZkScalar, sig: &jubjub::Signature| {
+        let mut cs = TestConstraintSystem::<BellmanFr>::new();
+        let pk = AllocatedPoint::alloc(cs.namespace(|| "pk"), Some(pk.0.decompress())).unwrap();
+        let message = alloc_scalar(cs.namespace(|| "message"), Some(message)).unwrap();
+        let sig = AllocatedSignature::alloc(cs.namespace(|| "sig"), Some(sig)).unwrap();
+        verify_signature(cs.namespace(|| "verify"), &pk, &message, &sig).unwrap();
+        cs.is_satisfied()
+    };
+    assert!(check(&pk, message, &sig));
+    assert!(!check(&pk, message + ZkScalar::from(1), &sig));
+    let (other_pk, _) = JubJub::generate_keys(b"CBA");
+    assert!(!check(&other_pk, message, &sig));
+}
+
+#[test]
+fn test_point_gadgets() {
+    let scalar = ZkScalar::from(123456789);
+    let point = jubjub::BASE.multiply(&ZkScalar::from(5));
+    let mut cs = TestConstraintSystem::<BellmanFr>::new();
+    let num = alloc_scalar(cs.namespace(|| "scalar"), Some(scalar)).unwrap();
+    let bits = num.to_bits_le_strict(cs.namespace(|| "bits")).unwrap();
+    let p = AllocatedPoint::alloc(cs.namespace(|| "point"), Some(point)).unwrap();
+    let sum = p.add(cs.namespace(|| "sum"), &p).unwrap();
+    let product = p.multiply(cs.namespace(|| "product"), &bits).unwrap();
+    let base_product = multiply_base(cs.namespace(|| "base product"), &bits).unwrap();
+    assert!(cs.is_satisfied());
+    assert_eq!(sum.get_value(), Some(point.double()));
+    assert_eq!(product.get_value(), Some(point.multiply(&scalar)));
+    assert_eq!(
+        base_product.get_value(),
+        Some(jubjub::BASE.multiply(&scalar))
+    );
+
+    // Points off the curve are rejected
+    let mut cs = TestConstraintSystem::<BellmanFr>::new();
+    AllocatedPoint::alloc(
+        cs.namespace(|| "point"),
+        Some(jubjub::PointAffine(point.0, point.1 + ZkScalar::from(1))),
+    )
+    .unwrap();
+    assert!(!cs.is_satisfied());
+}
+
+// Proves the next state to be the hash of the previous state and aux data
+struct HashCircuit {
+    prev_height: Option<u64>,
+    prev_state: Option<ZkScalar>,
+    aux_data: Option<ZkScalar>,
+    next_state: Option<ZkScalar>,
+}
+
+impl Circuit<BellmanFr> for HashCircuit {
+    fn synthesize<CS: ConstraintSystem<BellmanFr>>(
+        self,
+        cs: &mut CS,
+    ) -> Result<(), SynthesisError> {
+        let inputs = PublicInputs::alloc(
+            cs,
+            self.prev_height,
+            self.prev_state,
+            self.aux_data,
+            self.next_state,
+        )?;
+        let hash = poseidon(
+            cs.namespace(|| "hash"),
+            &[inputs.prev_state.clone(), inputs.aux_data.clone()],
+        )?;
+        enforce_equal_nums(cs.namespace(|| "next state"), &inputs.next_state, &hash)
+    }
+}
+
+#[test]
+fn test_groth16_prover() {
+    let params = Groth16Params::setup(HashCircuit {
+        prev_height: None,
+        prev_state: None,
+        aux_data: None,
+        next_state: None,
+    })
+    .unwrap();
+    let vk = params.verifying_key();
+    let (prev_state, aux_data) = (ZkScalar::from(12), ZkScalar::from(34));
+    let next_state = poseidon::poseidon(&[prev_state, aux_data]);
+    let prove = |next_state: ZkScalar| {
+        params
+            .prove(HashCircuit {
+                prev_height: Some(5),
+                prev_state: Some(prev_state),
+                aux_data: Some(aux_data),
+                next_state: Some(next_state),
+            })
+            .unwrap()
+    };
+
+    let proof = prove(next_state);
+    assert!(groth16_verify(
+        &vk, 5, prev_state, aux_data, next_state, &proof
+    ));
+    assert!(!groth16_verify(
+        &vk, 6, prev_state, aux_data, next_state, &proof
+    ));
+    let forged = next_state + ZkScalar::from(1);
+    assert!(!groth16_verify(
+        &vk,
+        5,
+        prev_state,
+        aux_data,
+        forged,
+        &prove(forged)
+    ));
+
+    // Parameters written by bellman are read back
+    let mut bytes = Vec::new();
+    params.params.write(&mut bytes).unwrap();
+    let params = Groth16Params::read(&bytes[..]).unwrap();
+    assert_eq!(params.verifying_key(), vk);
+    assert!(Groth16Params::read(&bytes[..bytes.len() - 1]).is_err());
+}
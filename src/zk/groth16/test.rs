@@ -0,0 +1,266 @@
+use super::*;
+use serde_json::{json, Value};
+
+// Encoders of the formats produced by other tooling
+
+fn snarkjs_fp(bytes: &[u8]) -> Value {
+    BigUint::from_bytes_be(bytes).to_str_radix(10).into()
+}
+
+fn snarkjs_g1(p: &G1Point) -> Value {
+    let bytes = p.0.to_uncompressed();
+    json!([snarkjs_fp(&bytes[..48]), snarkjs_fp(&bytes[48..]), "1"])
+}
+
+fn snarkjs_g2(p: &G2Point) -> Value {
+    let bytes = p.0.to_uncompressed();
+    json!([
+        [snarkjs_fp(&bytes[48..96]), snarkjs_fp(&bytes[..48])],
+        [snarkjs_fp(&bytes[144..]), snarkjs_fp(&bytes[96..144])],
+        ["1", "0"]
+    ])
+}
+
+fn snarkjs_vk(vk: &Groth16VerifyingKey) -> Value {
+    json!({
+        "protocol": "groth16",
+        "curve": "bls12381",
+        "nPublic": vk.ic.len() - 1,
+        "vk_alpha_1": snarkjs_g1(&vk.alpha_g1),
+        "vk_beta_2": snarkjs_g2(&vk.beta_g2),
+        "vk_gamma_2": snarkjs_g2(&vk.gamma_g2),
+        "vk_delta_2": snarkjs_g2(&vk.delta_g2),
+        "IC": vk.ic.iter().map(snarkjs_g1).collect::<Vec<_>>(),
+    })
+}
+
+fn snarkjs_proof(proof: &Groth16Proof) -> Value {
+    json!({
+        "protocol": "groth16",
+        "curve": "bls12381",
+        "pi_a": snarkjs_g1(&proof.a),
+        "pi_b": snarkjs_g2(&proof.b),
+        "pi_c": snarkjs_g1(&proof.c),
+    })
+}
+
+fn arkworks_vk(vk: &Groth16VerifyingKey, compressed: bool) -> Vec<u8> {
+    let g1 = |p: &G1Point| {
+        if compressed {
+            p.0.to_compressed().to_vec()
+        } else {
+            p.0.to_uncompressed().to_vec()
+        }
+    };
+    let g2 = |p: &G2Point| {
+        if compressed {
+            p.0.to_compressed().to_vec()
+        } else {
+            p.0.to_uncompressed().to_vec()
+        }
+    };
+    let mut bytes = g1(&vk.alpha_g1);
+    for p in [&vk.beta_g2, &vk.gamma_g2, &vk.delta_g2] {
+        bytes.extend(g2(p));
+    }
+    bytes.extend((vk.ic.len() as u64).to_le_bytes());
+    for p in vk.ic.iter() {
+        bytes.extend(g1(p));
+    }
+    bytes
+}
+
+fn import_vk(format: Groth16Format, bytes: &[u8]) -> Groth16VerifyingKey {
+    let vk = Groth16VerifyingKey::import(format, bytes).unwrap();
+    // Serialization through the Bazuka encoding keeps the key intact
+    let encoded = bincode::serialize(&vk).unwrap();
+    assert_eq!(
+        bincode::deserialize::<Groth16VerifyingKey>(&encoded).unwrap(),
+        vk
+    );
+    vk
+}
+
+#[test]
+fn test_import_groth16() {
+    let prover = TestProver::new(1);
+    let vk = prover.verifying_key();
+    let inputs = [ZkScalar::from(2), ZkScalar::from(3), ZkScalar::from(5)];
+    let proof = prover.prove(1, inputs[0], inputs[1], inputs[2]);
+    let verify = |vk: &Groth16VerifyingKey, proof: &Groth16Proof| {
+        groth16_verify(vk, 1, inputs[0], inputs[1], inputs[2], proof)
+    };
+
+    let snarkjs_vk = import_vk(
+        Groth16Format::Snarkjs,
+        &serde_json::to_vec(&snarkjs_vk(&vk)).unwrap(),
+    );
+    // Not carried by snarkjs keys
+    assert_eq!(snarkjs_vk.beta_g1.0, G1Affine::identity());
+    assert_eq!(snarkjs_vk.delta_g1.0, G1Affine::identity());
+    let snarkjs_proof = Groth16Proof::import(
+        Groth16Format::Snarkjs,
+        &serde_json::to_vec(&snarkjs_proof(&proof)).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(snarkjs_proof, proof);
+    assert!(verify(&snarkjs_vk, &snarkjs_proof));
+
+    for compressed in [true, false] {
+        let arkworks_vk = import_vk(Groth16Format::Arkworks, &arkworks_vk(&vk, compressed));
+        assert_eq!(arkworks_vk, snarkjs_vk);
+    }
+    let arkworks_proof = [
+        proof.a.0.to_uncompressed().as_ref(),
+        proof.b.0.to_compressed().as_ref(),
+        proof.c.0.to_compressed().as_ref(),
+    ]
+    .concat();
+    assert_eq!(
+        Groth16Proof::import(Groth16Format::Arkworks, &arkworks_proof).unwrap(),
+        proof
+    );
+
+    let mut bellman_vk = Vec::new();
+    prover.params.vk.write(&mut bellman_vk).unwrap();
+    assert_eq!(import_vk(Groth16Format::Bellman, &bellman_vk), vk);
+    let mut bellman_proof = Vec::new();
+    Proof::<Bls12> {
+        a: proof.a.0,
+        b: proof.b.0,
+        c: proof.c.0,
+    }
+    .write(&mut bellman_proof)
+    .unwrap();
+    let bellman_proof = Groth16Proof::import(Groth16Format::Bellman, &bellman_proof).unwrap();
+    assert!(verify(&vk, &bellman_proof));
+}
+
+// Key and proof of an arkworks setup, see `fixtures/README.md`
+#[test]
+fn test_import_groth16_fixtures() {
+    let inputs: Vec<String> = serde_json::from_str(include_str!("fixtures/public.json")).unwrap();
+    let inputs = inputs
+        .iter()
+        .map(|s| s.parse::<u64>().unwrap())
+        .collect::<Vec<_>>();
+    let verify = |vk: &Groth16VerifyingKey, proof: &Groth16Proof, next_state: u64| {
+        groth16_verify(
+            vk,
+            inputs[0],
+            ZkScalar::from(inputs[1]),
+            ZkScalar::from(inputs[2]),
+            ZkScalar::from(next_state),
+            proof,
+        )
+    };
+
+    let snarkjs_vk = import_vk(
+        Groth16Format::Snarkjs,
+        include_bytes!("fixtures/verification_key.json"),
+    );
+    let snarkjs_proof = Groth16Proof::import(
+        Groth16Format::Snarkjs,
+        include_bytes!("fixtures/proof.json"),
+    )
+    .unwrap();
+    assert!(verify(&snarkjs_vk, &snarkjs_proof, inputs[3]));
+    assert!(!verify(&snarkjs_vk, &snarkjs_proof, inputs[3] + 1));
+
+    let arkworks_vk = import_vk(
+        Groth16Format::Arkworks,
+        include_bytes!("fixtures/arkworks_vk.bin"),
+    );
+    assert_eq!(
+        import_vk(
+            Groth16Format::Arkworks,
+            include_bytes!("fixtures/arkworks_vk_uncompressed.bin"),
+        ),
+        arkworks_vk
+    );
+    assert_eq!(arkworks_vk, snarkjs_vk);
+    let arkworks_proof = Groth16Proof::import(
+        Groth16Format::Arkworks,
+        include_bytes!("fixtures/arkworks_proof.bin"),
+    )
+    .unwrap();
+    assert_eq!(arkworks_proof, snarkjs_proof);
+    assert!(verify(&arkworks_vk, &arkworks_proof, inputs[3]));
+    assert!(!verify(&arkworks_vk, &arkworks_proof, inputs[3] + 1));
+}
+
+#[test]
+fn test_import_groth16_invalid() {
+    let prover = TestProver::new(1);
+    let vk = prover.verifying_key();
+    let import_snarkjs = |json: &Value| {
+        Groth16VerifyingKey::import(Groth16Format::Snarkjs, &serde_json::to_vec(json).unwrap())
+    };
+
+    let mut other_curve = snarkjs_vk(&vk);
+    other_curve["curve"] = "bn128".into();
+    assert!(matches!(
+        import_snarkjs(&other_curve),
+        Err(Groth16Error::InvalidFormat(_))
+    ));
+    let mut not_normalized = snarkjs_vk(&vk);
+    not_normalized["vk_alpha_1"][2] = "2".into();
+    assert!(matches!(
+        import_snarkjs(&not_normalized),
+        Err(Groth16Error::InvalidFormat(_))
+    ));
+    let mut not_a_number = snarkjs_vk(&vk);
+    not_a_number["IC"][0][0] = "0x1".into();
+    assert!(matches!(
+        import_snarkjs(&not_a_number),
+        Err(Groth16Error::InvalidFormat(_))
+    ));
+    let mut unreduced = snarkjs_vk(&vk);
+    let x = BigUint::parse_bytes(unreduced["IC"][0][0].as_str().unwrap().as_bytes(), 10).unwrap();
+    unreduced["IC"][0][0] = (x + &*MODULUS).to_str_radix(10).into();
+    assert!(matches!(
+        import_snarkjs(&unreduced),
+        Err(Groth16Error::InvalidPoint)
+    ));
+    let mut off_curve = snarkjs_vk(&vk);
+    off_curve["vk_beta_2"][0][0] = "1".into();
+    assert!(matches!(
+        import_snarkjs(&off_curve),
+        Err(Groth16Error::InvalidPoint)
+    ));
+    let mut missing = snarkjs_vk(&vk);
+    missing.as_object_mut().unwrap().remove("IC");
+    assert!(matches!(
+        import_snarkjs(&missing),
+        Err(Groth16Error::JsonError(_))
+    ));
+
+    let arkworks = arkworks_vk(&vk, true);
+    assert!(matches!(
+        Groth16VerifyingKey::import(Groth16Format::Arkworks, &arkworks[..arkworks.len() - 1]),
+        Err(Groth16Error::IoError(_))
+    ));
+    assert!(matches!(
+        Groth16VerifyingKey::import(Groth16Format::Arkworks, &[&arkworks[..], &[0]].concat()),
+        Err(Groth16Error::InvalidFormat(_))
+    ));
+    let mut off_curve = arkworks.clone();
+    off_curve[47] ^= 1;
+    assert!(matches!(
+        Groth16VerifyingKey::import(Groth16Format::Arkworks, &off_curve),
+        Err(Groth16Error::InvalidPoint)
+    ));
+    let mut bellman = Vec::new();
+    prover.params.vk.write(&mut bellman).unwrap();
+    bellman[95] ^= 1;
+    assert!(matches!(
+        Groth16VerifyingKey::import(Groth16Format::Bellman, &bellman),
+        Err(Groth16Error::IoError(_))
+    ));
+
+    assert_eq!(
+        "snarkjs".parse::<Groth16Format>().unwrap(),
+        Groth16Format::Snarkjs
+    );
+    assert!("circom".parse::<Groth16Format>().is_err());
+}
@@ -5,14 +5,17 @@ use ff::PrimeField;
 use num_bigint::BigUint;
 use num_integer::Integer;
 use serde::{Deserialize, Serialize};
+use std::any::TypeId;
 use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 
 use thiserror::Error;
 
 mod state;
 pub use state::*;
 pub mod groth16;
+pub mod plonk;
 pub mod poseidon;
 
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
@@ -23,6 +26,14 @@ pub struct MpnAccount {
 }
 
 lazy_static! {
+    pub static ref MPN_ACCOUNT_STATE_MODEL: ZkStateModel = ZkStateModel::Struct {
+        field_types: vec![
+            ZkStateModel::Scalar, // Nonce
+            ZkStateModel::Scalar, // Pub-key X
+            ZkStateModel::Scalar, // Pub-key Y
+            ZkStateModel::Scalar, // Balance
+        ],
+    };
     pub static ref CONTRACT_PAYMENT_STATE_MODEL: ZkStateModel = ZkStateModel::Struct {
         field_types: vec![
             ZkStateModel::Scalar, // amount
@@ -41,10 +52,13 @@ pub enum ZkError {
     ScalarBiggerThanU64,
 }
 
-#[derive(Debug, Clone, Default)]
-pub struct ZkStateProof(Vec<ZkScalar>);
+/// Siblings of the nodes on the path from a value up to the root of the
+/// state, lowest first. The state model and the locator tell how many of them
+/// each ancestor takes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ZkStateProof(pub Vec<ZkScalar>);
 
-pub trait ZkHasher: Clone {
+pub trait ZkHasher: Clone + 'static {
     const MAX_ARITY: usize;
     fn hash(vals: &[ZkScalar]) -> ZkScalar;
 }
@@ -58,9 +72,7 @@ pub fn check_proof(
     proof: &ZkProof,
 ) -> bool {
     match vk {
-        ZkVerifierKey::Groth16(vk) =>
-        {
-            #[allow(irrefutable_let_patterns)]
+        ZkVerifierKey::Groth16(vk) => {
             if let ZkProof::Groth16(proof) = proof {
                 groth16::groth16_verify(
                     vk,
@@ -74,6 +86,20 @@ pub fn check_proof(
                 false
             }
         }
+        ZkVerifierKey::Plonk(vk) => {
+            if let ZkProof::Plonk(proof) = proof {
+                plonk::plonk_verify(
+                    vk,
+                    prev_height,
+                    prev_state.state_hash,
+                    aux_data.state_hash,
+                    next_state.state_hash,
+                    proof,
+                )
+            } else {
+                false
+            }
+        }
         #[cfg(test)]
         ZkVerifierKey::Dummy => {
             if let ZkProof::Dummy(result) = proof {
@@ -148,7 +174,7 @@ pub enum ZkStatePatch {
     Delta(ZkDeltaPairs),
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, std::hash::Hash, Serialize, Deserialize)]
 pub enum ZkStateModel {
     // Allocate 1
     Scalar,
@@ -161,6 +187,60 @@ pub enum ZkStateModel {
         log4_size: u8,
         item_type: Box<ZkStateModel>,
     },
+    // Allocate 4^MAP_LOG4_SIZE * size(item_type), sparsely. Items are located
+    // by keys, and placed in the tree at the hash of their key
+    Map {
+        item_type: Box<ZkStateModel>,
+    },
+}
+
+/// Depth of the (4-ary) trees of `Map`s, deep enough for any hash of a key
+pub const MAP_LOG4_SIZE: usize = 128;
+
+/// Number of locator components holding a key of a `Map`
+pub const MAP_KEY_WIDTH: usize = 8;
+
+// The cache is cleared when it reaches this size
+const MAX_CACHED_MAP_DEFAULTS: usize = 1024;
+
+type MapDefaults = HashMap<(TypeId, ZkStateModel), Arc<Vec<ZkScalar>>>;
+
+lazy_static! {
+    static ref MAP_DEFAULTS: Mutex<MapDefaults> = Default::default();
+}
+
+// Values of the empty nodes of the tree of a `Map`, by depth. They take a few
+// hundred hashes to compute, so they are cached.
+fn map_defaults<H: ZkHasher>(item_type: &ZkStateModel) -> Arc<Vec<ZkScalar>> {
+    let cache_key = (TypeId::of::<H>(), item_type.clone());
+    if let Some(defaults) = MAP_DEFAULTS.lock().unwrap().get(&cache_key) {
+        return defaults.clone();
+    }
+    let mut defaults = vec![item_type.compress_default::<H>()];
+    for _ in 0..MAP_LOG4_SIZE {
+        let child = *defaults.last().unwrap();
+        defaults.push(H::hash(&[child; 4]));
+    }
+    defaults.reverse();
+    let defaults = Arc::new(defaults);
+    let mut cache = MAP_DEFAULTS.lock().unwrap();
+    if cache.len() >= MAX_CACHED_MAP_DEFAULTS {
+        cache.clear();
+    }
+    cache.insert(cache_key, defaults.clone());
+    defaults
+}
+
+// Key of a `Map` held by locator components, if canonical
+fn map_key(parts: &[u32]) -> Option<ZkScalar> {
+    if parts.len() != MAP_KEY_WIDTH {
+        return None;
+    }
+    let mut repr = ZkScalarRepr([0u8; 32]);
+    for (i, part) in parts.iter().rev().enumerate() {
+        repr.0[i * 4..(i + 1) * 4].copy_from_slice(&part.to_le_bytes());
+    }
+    ZkScalar::from_repr_vartime(repr)
 }
 
 #[derive(Error, Debug)]
@@ -180,32 +260,58 @@ impl ZkStateModel {
                 }
             }
             ZkStateModel::List { item_type, .. } => item_type.is_valid::<H>(),
+            ZkStateModel::Map { item_type } => item_type.is_valid::<H>(),
             ZkStateModel::Scalar => true,
         }
     }
     pub fn locate(&self, locator: &ZkDataLocator) -> Result<ZkStateModel, ZkLocatorError> {
+        Ok(self.ancestors(locator)?.1)
+    }
+
+    // Types of the ancestors of the located element, along with the lengths
+    // of their locators
+    fn ancestors(
+        &self,
+        locator: &ZkDataLocator,
+    ) -> Result<(Vec<(usize, ZkStateModel)>, ZkStateModel), ZkLocatorError> {
+        let mut ancestors = Vec::new();
         let mut curr = self.clone();
-        for l in locator.0.iter() {
-            match curr {
-                ZkStateModel::Struct { field_types } => {
-                    curr = field_types[*l as usize].clone();
-                }
+        let mut pos = 0;
+        while let Some(l) = locator.0.get(pos) {
+            let (next, width) = match &curr {
+                ZkStateModel::Struct { field_types } => (
+                    field_types
+                        .get(*l as usize)
+                        .ok_or(ZkLocatorError::InvalidLocator)?
+                        .clone(),
+                    1,
+                ),
                 ZkStateModel::List {
                     item_type,
                     log4_size,
                 } => {
                     if *l < 1 << (2 * log4_size) {
-                        curr = *item_type.clone();
+                        (*item_type.clone(), 1)
                     } else {
                         return Err(ZkLocatorError::InvalidLocator);
                     }
                 }
+                ZkStateModel::Map { item_type } => {
+                    locator
+                        .0
+                        .get(pos..pos + MAP_KEY_WIDTH)
+                        .and_then(map_key)
+                        .ok_or(ZkLocatorError::InvalidLocator)?;
+                    (*item_type.clone(), MAP_KEY_WIDTH)
+                }
                 ZkStateModel::Scalar => {
                     return Err(ZkLocatorError::InvalidLocator);
                 }
-            }
+            };
+            ancestors.push((pos, std::mem::replace(&mut curr, next)));
+            pos += width;
         }
-        Ok(curr)
+        Ok((ancestors, curr))
     }
 
     pub fn compress<H: ZkHasher>(
@@ -238,6 +344,7 @@ impl ZkStateModel {
                 }
                 root_default
             }
+            ZkStateModel::Map { item_type } => map_defaults::<H>(item_type)[0],
         }
     }
 }
@@ -251,6 +358,18 @@ impl ZkDataLocator {
         result.0.push(ind);
         result
     }
+    /// Locator of the item of a `Map` with the given key
+    pub fn key(&self, key: ZkScalar) -> ZkDataLocator {
+        let mut result = self.clone();
+        let repr = key.to_repr();
+        result.0.extend(
+            repr.as_ref()
+                .chunks(4)
+                .rev()
+                .map(|c| u32::from_le_bytes(c.try_into().unwrap())),
+        );
+        result
+    }
 }
 
 impl std::fmt::Display for ZkDataLocator {
@@ -277,6 +396,9 @@ pub enum ParseZkDataLocatorError {
 impl std::str::FromStr for ZkDataLocator {
     type Err = ParseZkDataLocatorError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(Self(vec![]));
+        }
         Ok(Self(
             s.split('_')
                 .map(|s| u32::from_str_radix(s, 16))
@@ -376,6 +498,7 @@ pub enum ZkVerifierKey {
     Groth16(Box<groth16::Groth16VerifyingKey>),
     #[cfg(test)]
     Dummy,
+    Plonk(Box<plonk::PlonkVerifyingKey>),
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -440,6 +563,7 @@ pub enum ZkProof {
     Groth16(Box<groth16::Groth16Proof>),
     #[cfg(test)]
     Dummy(bool),
+    Plonk(Box<plonk::PlonkProof>),
 }
 
 #[cfg(test)]
@@ -0,0 +1,314 @@
+//! Verifier of PLONK proofs over BLS12-381, with KZG polynomial commitments.
+//!
+//! Unlike Groth16, the setup is universal: any circuit with up to as many
+//! gates as the powers of the SRS can be proven, without a per-circuit
+//! ceremony. The SRS is still trusted, so the chain only accepts keys whose
+//! `[x]₂` is the one of the ceremony it pins. Circuits use the standard
+//! arithmetization `q_m·a·b + q_l·a + q_r·b + q_o·c + q_c + PI = 0` with a
+//! copy-permutation over the `a`, `b` and `c` wires. The public inputs (`prev_height`,
+//! `prev_state`, `aux_data` and `next_state`, in that order) are assigned to
+//! the `a` wires of the first rows, i.e. `PI(X) = -Σ x_i·L_i(X)`.
+//!
+//! Instead of a linearization polynomial, the prover opens every polynomial
+//! at the challenge point, and the openings are checked with a single
+//! batched KZG pairing check. The quotient is split in chunks of
+//! `domain_size + 2` coefficients, leaving room for blinding factors.
+
+use super::ZkScalar;
+use bls12_381::{multi_miller_loop, G1Affine, G1Projective, G2Affine, G2Prepared, Gt, Scalar};
+use ff::PrimeField;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_512};
+
+pub const NUM_PUBLIC_INPUTS: usize = 4;
+
+// Generators of the cosets of the evaluation domain, labelling the positions
+// of the `b` and `c` wires in the copy-permutation.
+const K1: u64 = 7;
+const K2: u64 = 49;
+
+/// Group elements are stored in compressed form, and checked to be in the
+/// prime-order subgroup when decoded.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlonkVerifyingKey {
+    /// Number of rows of the circuit, a power of two
+    pub domain_size: u64,
+    pub q_m: Vec<u8>,
+    pub q_l: Vec<u8>,
+    pub q_r: Vec<u8>,
+    pub q_o: Vec<u8>,
+    pub q_c: Vec<u8>,
+    pub s_sigma1: Vec<u8>,
+    pub s_sigma2: Vec<u8>,
+    pub s_sigma3: Vec<u8>,
+    /// `[x]₂` of the SRS, which must be the one pinned by the chain-config
+    pub x_g2: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlonkProof {
+    pub a: Vec<u8>,
+    pub b: Vec<u8>,
+    pub c: Vec<u8>,
+    pub z: Vec<u8>,
+    pub t_lo: Vec<u8>,
+    pub t_mid: Vec<u8>,
+    pub t_hi: Vec<u8>,
+    /// Opening of all polynomials at `ζ`
+    pub w_zeta: Vec<u8>,
+    /// Opening of `z` at `ζω`
+    pub w_zeta_omega: Vec<u8>,
+    pub a_eval: ZkScalar,
+    pub b_eval: ZkScalar,
+    pub c_eval: ZkScalar,
+    pub s_sigma1_eval: ZkScalar,
+    pub s_sigma2_eval: ZkScalar,
+    pub s_sigma3_eval: ZkScalar,
+    pub q_m_eval: ZkScalar,
+    pub q_l_eval: ZkScalar,
+    pub q_r_eval: ZkScalar,
+    pub q_o_eval: ZkScalar,
+    pub q_c_eval: ZkScalar,
+    pub z_eval: ZkScalar,
+    pub z_omega_eval: ZkScalar,
+}
+
+/// Fiat-Shamir transcript, shared with the prover used in tests
+pub(crate) struct Transcript(Vec<u8>);
+
+impl Transcript {
+    pub fn new(label: &[u8]) -> Self {
+        Self(label.to_vec())
+    }
+    pub fn append_bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+    pub fn append_point(&mut self, point: &G1Affine) {
+        self.append_bytes(&point.to_compressed());
+    }
+    pub fn append_scalar(&mut self, scalar: &Scalar) {
+        self.append_bytes(&scalar.to_bytes());
+    }
+    pub fn challenge(&mut self) -> Scalar {
+        let mut wide = [0u8; 64];
+        wide.copy_from_slice(&Sha3_512::digest(&self.0));
+        let challenge = Scalar::from_bytes_wide(&wide);
+        self.append_scalar(&challenge);
+        challenge
+    }
+}
+
+/// Generator of the multiplicative subgroup of size `n`
+pub(crate) fn domain_generator(n: u64) -> Option<Scalar> {
+    if !n.is_power_of_two() || n.trailing_zeros() > Scalar::S {
+        return None;
+    }
+    Some(Scalar::root_of_unity().pow_vartime(&[1 << (Scalar::S - n.trailing_zeros()), 0, 0, 0]))
+}
+
+pub(crate) fn coset_generators() -> [Scalar; 3] {
+    [Scalar::one(), Scalar::from(K1), Scalar::from(K2)]
+}
+
+fn decode_g1(bytes: &[u8]) -> Option<G1Affine> {
+    Option::from(G1Affine::from_compressed(bytes.try_into().ok()?))
+}
+
+fn decode_g1s<const N: usize>(points: [&Vec<u8>; N]) -> Option<[G1Affine; N]> {
+    let mut decoded = [G1Affine::identity(); N];
+    for (point, bytes) in decoded.iter_mut().zip(points) {
+        *point = decode_g1(bytes)?;
+    }
+    Some(decoded)
+}
+
+fn decode_g2(bytes: &[u8]) -> Option<G2Affine> {
+    Option::from(G2Affine::from_compressed(bytes.try_into().ok()?))
+}
+
+impl PlonkVerifyingKey {
+    fn selectors(&self) -> [&Vec<u8>; 8] {
+        [
+            &self.s_sigma1,
+            &self.s_sigma2,
+            &self.s_sigma3,
+            &self.q_m,
+            &self.q_l,
+            &self.q_r,
+            &self.q_o,
+            &self.q_c,
+        ]
+    }
+}
+
+pub(crate) fn start_transcript(vk: &PlonkVerifyingKey, public_inputs: &[Scalar]) -> Transcript {
+    let mut transcript = Transcript::new(b"bazuka-plonk");
+    transcript.append_bytes(&vk.domain_size.to_le_bytes());
+    for commitment in vk.selectors() {
+        transcript.append_bytes(commitment);
+    }
+    transcript.append_bytes(&vk.x_g2);
+    for input in public_inputs {
+        transcript.append_scalar(input);
+    }
+    transcript
+}
+
+fn verify(vk: &PlonkVerifyingKey, public_inputs: &[Scalar], proof: &PlonkProof) -> Option<bool> {
+    let n = vk.domain_size;
+    let omega = domain_generator(n)?;
+    if n < public_inputs.len() as u64 {
+        return None;
+    }
+    let [s_sigma1, s_sigma2, s_sigma3, q_m, q_l, q_r, q_o, q_c] = decode_g1s(vk.selectors())?;
+    let x_g2 = decode_g2(&vk.x_g2)?;
+    let [a, b, c, z, t_lo, t_mid, t_hi, w_zeta, w_zeta_omega] = decode_g1s([
+        &proof.a,
+        &proof.b,
+        &proof.c,
+        &proof.z,
+        &proof.t_lo,
+        &proof.t_mid,
+        &proof.t_hi,
+        &proof.w_zeta,
+        &proof.w_zeta_omega,
+    ])?;
+    let evals: [Scalar; 13] = [
+        proof.a_eval,
+        proof.b_eval,
+        proof.c_eval,
+        proof.s_sigma1_eval,
+        proof.s_sigma2_eval,
+        proof.s_sigma3_eval,
+        proof.q_m_eval,
+        proof.q_l_eval,
+        proof.q_r_eval,
+        proof.q_o_eval,
+        proof.q_c_eval,
+        proof.z_eval,
+        proof.z_omega_eval,
+    ]
+    .map(Scalar::from);
+    let [a_eval, b_eval, c_eval, s1_eval, s2_eval, s3_eval, qm_eval, ql_eval, qr_eval, qo_eval, qc_eval, z_eval, z_omega_eval] =
+        evals;
+
+    let mut transcript = start_transcript(vk, public_inputs);
+    for commitment in [a, b, c] {
+        transcript.append_point(&commitment);
+    }
+    let beta = transcript.challenge();
+    let gamma = transcript.challenge();
+    transcript.append_point(&z);
+    let alpha = transcript.challenge();
+    for commitment in [t_lo, t_mid, t_hi] {
+        transcript.append_point(&commitment);
+    }
+    let zeta = transcript.challenge();
+    for eval in evals.iter() {
+        transcript.append_scalar(eval);
+    }
+    let v = transcript.challenge();
+    transcript.append_point(&w_zeta);
+    transcript.append_point(&w_zeta_omega);
+    let u = transcript.challenge();
+
+    // Vanishing polynomial of the domain, and the Lagrange bases of the
+    // public input rows, at ζ
+    let n_scalar = Scalar::from(n);
+    let zeta_n = zeta.pow_vartime(&[n, 0, 0, 0]);
+    let vanishing = zeta_n - Scalar::one();
+    let vanishing_inv = Option::<Scalar>::from(vanishing.invert())?;
+    let lagrange = |omega_i: Scalar| -> Option<Scalar> {
+        Some(omega_i * vanishing * Option::<Scalar>::from((n_scalar * (zeta - omega_i)).invert())?)
+    };
+    let l1 = lagrange(Scalar::one())?;
+    let mut omega_i = Scalar::one();
+    let mut pi = Scalar::zero();
+    for input in public_inputs.iter() {
+        pi -= input * lagrange(omega_i)?;
+        omega_i *= omega;
+    }
+
+    let [_, k1, k2] = coset_generators();
+    let gate = a_eval * b_eval * qm_eval
+        + a_eval * ql_eval
+        + b_eval * qr_eval
+        + c_eval * qo_eval
+        + qc_eval
+        + pi;
+    let perm = (a_eval + beta * zeta + gamma)
+        * (b_eval + beta * k1 * zeta + gamma)
+        * (c_eval + beta * k2 * zeta + gamma)
+        * z_eval
+        - (a_eval + beta * s1_eval + gamma)
+            * (b_eval + beta * s2_eval + gamma)
+            * (c_eval + beta * s3_eval + gamma)
+            * z_omega_eval;
+    let t_eval =
+        (gate + alpha * perm + alpha * alpha * (z_eval - Scalar::one()) * l1) * vanishing_inv;
+
+    // Commitment of the quotient, and its claimed evaluation
+    let zeta_chunk = zeta.pow_vartime(&[n + 2, 0, 0, 0]);
+    let t = G1Projective::from(t_lo) + t_mid * zeta_chunk + t_hi * (zeta_chunk * zeta_chunk);
+
+    let commitments = [
+        G1Projective::from(a),
+        b.into(),
+        c.into(),
+        s_sigma1.into(),
+        s_sigma2.into(),
+        s_sigma3.into(),
+        q_m.into(),
+        q_l.into(),
+        q_r.into(),
+        q_o.into(),
+        q_c.into(),
+        z.into(),
+        t,
+    ];
+    let claimed = evals[..12].iter().chain(std::iter::once(&t_eval));
+    let mut batched = G1Projective::identity();
+    let mut batched_eval = Scalar::zero();
+    let mut v_i = Scalar::one();
+    for (commitment, eval) in commitments.iter().zip(claimed) {
+        batched += commitment * v_i;
+        batched_eval += eval * v_i;
+        v_i *= v;
+    }
+
+    // e(W_ζ + u·W_ζω, [x]₂) = e(ζ·W_ζ + uζω·W_ζω + F - E·G + u·([z] - z(ζω)·G), [1]₂)
+    let g1 = G1Projective::generator();
+    let lhs = G1Affine::from(w_zeta + w_zeta_omega * u);
+    let rhs = G1Affine::from(
+        w_zeta * zeta + w_zeta_omega * (u * zeta * omega) + batched - g1 * batched_eval
+            + (G1Projective::from(z) - g1 * z_omega_eval) * u,
+    );
+    Some(
+        multi_miller_loop(&[
+            (&lhs, &G2Prepared::from(x_g2)),
+            (&-rhs, &G2Prepared::from(G2Affine::generator())),
+        ])
+        .final_exponentiation()
+            == Gt::identity(),
+    )
+}
+
+pub fn plonk_verify(
+    vk: &PlonkVerifyingKey,
+    prev_height: u64,
+    prev_state: ZkScalar,
+    aux_data: ZkScalar,
+    next_state: ZkScalar,
+    proof: &PlonkProof,
+) -> bool {
+    let public_inputs = [
+        Scalar::from(prev_height),
+        prev_state.into(),
+        aux_data.into(),
+        next_state.into(),
+    ];
+    verify(vk, &public_inputs, proof).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,476 @@
+use super::*;
+use crate::zk::{check_proof, ZkCompressedState, ZkProof, ZkVerifierKey};
+use bls12_381::G2Projective;
+use ff::Field;
+
+// Polynomials in coefficient form, lowest degree first. The prover is only
+// meant for small test circuits, so the arithmetic is quadratic.
+type Poly = Vec<Scalar>;
+
+fn eval(p: &[Scalar], x: Scalar) -> Scalar {
+    p.iter().rev().fold(Scalar::zero(), |acc, c| acc * x + c)
+}
+
+fn add(p: &[Scalar], q: &[Scalar]) -> Poly {
+    (0..p.len().max(q.len()))
+        .map(|i| p.get(i).copied().unwrap_or_default() + q.get(i).copied().unwrap_or_default())
+        .collect()
+}
+
+fn scale(p: &[Scalar], s: Scalar) -> Poly {
+    p.iter().map(|c| c * s).collect()
+}
+
+fn mul(p: &[Scalar], q: &[Scalar]) -> Poly {
+    let mut r = vec![Scalar::zero(); p.len() + q.len() - 1];
+    for (i, a) in p.iter().enumerate() {
+        for (j, b) in q.iter().enumerate() {
+            r[i + j] += a * b;
+        }
+    }
+    r
+}
+
+fn constant(c: Scalar) -> Poly {
+    vec![c]
+}
+
+// p(ω·X)
+fn shift(p: &[Scalar], omega: Scalar) -> Poly {
+    let mut omega_i = Scalar::one();
+    p.iter()
+        .map(|c| {
+            let r = c * omega_i;
+            omega_i *= omega;
+            r
+        })
+        .collect()
+}
+
+fn interpolate(values: &[Scalar], omega: Scalar) -> Poly {
+    let n = values.len() as u64;
+    let omega_inv = omega.invert().unwrap();
+    let n_inv = Scalar::from(n).invert().unwrap();
+    (0..n)
+        .map(|j| {
+            let w = omega_inv.pow_vartime(&[j, 0, 0, 0]);
+            eval(values, w) * n_inv
+        })
+        .collect()
+}
+
+// Divides by `X^n - 1`, panics if there's a remainder
+fn div_vanishing(p: &[Scalar], n: usize) -> Poly {
+    let mut rem = p.to_vec();
+    let mut q = vec![Scalar::zero(); rem.len().saturating_sub(n)];
+    for i in (n..rem.len()).rev() {
+        let c = rem[i];
+        q[i - n] = c;
+        rem[i] = Scalar::zero();
+        rem[i - n] += c;
+    }
+    assert!(rem.iter().all(|c| bool::from(c.is_zero())));
+    q
+}
+
+// (p(X) - p(x)) / (X - x)
+fn div_linear(p: &[Scalar], x: Scalar) -> Poly {
+    let mut q = vec![Scalar::zero(); p.len().saturating_sub(1)];
+    let mut carry = Scalar::zero();
+    for i in (1..p.len()).rev() {
+        carry = p[i] + carry * x;
+        q[i - 1] = carry;
+    }
+    q
+}
+
+struct Srs {
+    g1: Vec<G1Affine>,
+    x_g2: G2Affine,
+}
+
+impl Srs {
+    fn new(tau: Scalar, size: usize) -> Self {
+        let mut tau_i = Scalar::one();
+        let g1 = (0..size)
+            .map(|_| {
+                let p = G1Affine::from(G1Projective::generator() * tau_i);
+                tau_i *= tau;
+                p
+            })
+            .collect();
+        Self {
+            g1,
+            x_g2: G2Affine::from(G2Projective::generator() * tau),
+        }
+    }
+    fn commit(&self, p: &[Scalar]) -> G1Affine {
+        assert!(p.len() <= self.g1.len());
+        G1Affine::from(
+            p.iter()
+                .zip(self.g1.iter())
+                .fold(G1Projective::identity(), |acc, (c, g)| acc + g * c),
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Position {
+    wire: usize,
+    row: usize,
+}
+
+struct Circuit {
+    // Selectors in the order q_m, q_l, q_r, q_o, q_c
+    selectors: [Vec<Scalar>; 5],
+    // Values of the `a`, `b` and `c` wires
+    wires: [Vec<Scalar>; 3],
+    copies: Vec<Vec<Position>>,
+}
+
+struct Setup {
+    srs: Srs,
+    n: usize,
+    omega: Scalar,
+    selectors: [Poly; 5],
+    sigmas: [Poly; 3],
+    sigma_values: [Vec<Scalar>; 3],
+    vk: PlonkVerifyingKey,
+}
+
+fn setup(circuit: &Circuit, tau: Scalar) -> Setup {
+    let n = circuit.wires[0].len();
+    let omega = domain_generator(n as u64).unwrap();
+    let ks = coset_generators();
+    let label = |p: Position| ks[p.wire] * omega.pow_vartime(&[p.row as u64, 0, 0, 0]);
+    let mut sigma_values = [0, 1, 2].map(|wire| {
+        (0..n)
+            .map(|row| label(Position { wire, row }))
+            .collect::<Vec<_>>()
+    });
+    for cycle in circuit.copies.iter() {
+        for (i, p) in cycle.iter().enumerate() {
+            sigma_values[p.wire][p.row] = label(cycle[(i + 1) % cycle.len()]);
+        }
+    }
+    let srs = Srs::new(tau, n + 2);
+    let selectors = circuit.selectors.clone().map(|s| interpolate(&s, omega));
+    let sigmas = sigma_values.clone().map(|s| interpolate(&s, omega));
+    let [s_sigma1, s_sigma2, s_sigma3] = sigmas
+        .clone()
+        .map(|s| srs.commit(&s).to_compressed().to_vec());
+    let [q_m, q_l, q_r, q_o, q_c] = selectors
+        .clone()
+        .map(|s| srs.commit(&s).to_compressed().to_vec());
+    let vk = PlonkVerifyingKey {
+        domain_size: n as u64,
+        q_m,
+        q_l,
+        q_r,
+        q_o,
+        q_c,
+        s_sigma1,
+        s_sigma2,
+        s_sigma3,
+        x_g2: srs.x_g2.to_compressed().to_vec(),
+    };
+    Setup {
+        srs,
+        n,
+        omega,
+        selectors,
+        sigmas,
+        sigma_values,
+        vk,
+    }
+}
+
+fn prove(setup: &Setup, circuit: &Circuit, public_inputs: &[Scalar]) -> PlonkProof {
+    let Setup { srs, n, omega, .. } = setup;
+    let (n, omega) = (*n, *omega);
+    let ks = coset_generators();
+    let mut transcript = start_transcript(&setup.vk, public_inputs);
+
+    let wires = circuit.wires.clone().map(|w| interpolate(&w, omega));
+    let wire_commitments = wires.clone().map(|w| srs.commit(&w));
+    for c in wire_commitments.iter() {
+        transcript.append_point(c);
+    }
+    let beta = transcript.challenge();
+    let gamma = transcript.challenge();
+
+    let mut z_values = vec![Scalar::one()];
+    for i in 0..n - 1 {
+        let omega_i = omega.pow_vartime(&[i as u64, 0, 0, 0]);
+        let mut ratio = Scalar::one();
+        for (w, k) in ks.iter().enumerate() {
+            let v = circuit.wires[w][i];
+            ratio *= (v + beta * k * omega_i + gamma)
+                * (v + beta * setup.sigma_values[w][i] + gamma)
+                    .invert()
+                    .unwrap();
+        }
+        z_values.push(z_values[i] * ratio);
+    }
+    let z = interpolate(&z_values, omega);
+    let z_commitment = srs.commit(&z);
+    transcript.append_point(&z_commitment);
+    let alpha = transcript.challenge();
+
+    let lagrange = |i: usize| {
+        let mut unit = vec![Scalar::zero(); n];
+        unit[i] = Scalar::one();
+        interpolate(&unit, omega)
+    };
+    let mut pi = vec![];
+    for (i, x) in public_inputs.iter().enumerate() {
+        pi = add(&pi, &scale(&lagrange(i), -x));
+    }
+    let [q_m, q_l, q_r, q_o, q_c] = &setup.selectors;
+    let [a, b, c] = &wires;
+    let gate = [
+        mul(&mul(a, b), q_m),
+        mul(a, q_l),
+        mul(b, q_r),
+        mul(c, q_o),
+        q_c.clone(),
+        pi,
+    ]
+    .iter()
+    .fold(vec![], |acc, p| add(&acc, p));
+    let x = vec![Scalar::zero(), Scalar::one()];
+    let mut perm_num = z.clone();
+    let mut perm_den = shift(&z, omega);
+    for w in 0..3 {
+        perm_num = mul(
+            &perm_num,
+            &add(&add(&wires[w], &scale(&x, beta * ks[w])), &constant(gamma)),
+        );
+        perm_den = mul(
+            &perm_den,
+            &add(
+                &add(&wires[w], &scale(&setup.sigmas[w], beta)),
+                &constant(gamma),
+            ),
+        );
+    }
+    let perm = add(&perm_num, &scale(&perm_den, -Scalar::one()));
+    let first_row = mul(&add(&z, &constant(-Scalar::one())), &lagrange(0));
+    let numerator = add(
+        &add(&gate, &scale(&perm, alpha)),
+        &scale(&first_row, alpha * alpha),
+    );
+    let mut t = div_vanishing(&numerator, n);
+    t.resize(3 * (n + 2), Scalar::zero());
+    let chunks: Vec<Poly> = t.chunks(n + 2).map(|c| c.to_vec()).collect();
+    let t_commitments: Vec<G1Affine> = chunks.iter().map(|c| srs.commit(c)).collect();
+    for c in t_commitments.iter() {
+        transcript.append_point(c);
+    }
+    let zeta = transcript.challenge();
+
+    let zeta_chunk = zeta.pow_vartime(&[(n + 2) as u64, 0, 0, 0]);
+    let t_folded = add(
+        &add(&chunks[0], &scale(&chunks[1], zeta_chunk)),
+        &scale(&chunks[2], zeta_chunk * zeta_chunk),
+    );
+    let opened = [
+        a.clone(),
+        b.clone(),
+        c.clone(),
+        setup.sigmas[0].clone(),
+        setup.sigmas[1].clone(),
+        setup.sigmas[2].clone(),
+        q_m.clone(),
+        q_l.clone(),
+        q_r.clone(),
+        q_o.clone(),
+        q_c.clone(),
+        z.clone(),
+        t_folded,
+    ];
+    let evals: Vec<Scalar> = opened
+        .iter()
+        .take(12)
+        .map(|p| eval(p, zeta))
+        .chain(std::iter::once(eval(&z, zeta * omega)))
+        .collect();
+    for e in evals.iter() {
+        transcript.append_scalar(e);
+    }
+    let v = transcript.challenge();
+
+    let mut batched = vec![];
+    let mut v_i = Scalar::one();
+    for p in opened.iter() {
+        batched = add(&batched, &scale(p, v_i));
+        v_i *= v;
+    }
+    let w_zeta = srs.commit(&div_linear(&batched, zeta));
+    let w_zeta_omega = srs.commit(&div_linear(&z, zeta * omega));
+
+    let point = |p: &G1Affine| p.to_compressed().to_vec();
+    let eval_at = |i: usize| ZkScalar::from(evals[i]);
+    PlonkProof {
+        a: point(&wire_commitments[0]),
+        b: point(&wire_commitments[1]),
+        c: point(&wire_commitments[2]),
+        z: point(&z_commitment),
+        t_lo: point(&t_commitments[0]),
+        t_mid: point(&t_commitments[1]),
+        t_hi: point(&t_commitments[2]),
+        w_zeta: point(&w_zeta),
+        w_zeta_omega: point(&w_zeta_omega),
+        a_eval: eval_at(0),
+        b_eval: eval_at(1),
+        c_eval: eval_at(2),
+        s_sigma1_eval: eval_at(3),
+        s_sigma2_eval: eval_at(4),
+        s_sigma3_eval: eval_at(5),
+        q_m_eval: eval_at(6),
+        q_l_eval: eval_at(7),
+        q_r_eval: eval_at(8),
+        q_o_eval: eval_at(9),
+        q_c_eval: eval_at(10),
+        z_eval: eval_at(11),
+        z_omega_eval: eval_at(12),
+    }
+}
+
+// Proves `next_state = prev_state * aux_data + prev_height`
+fn update_circuit(prev_height: u64, prev_state: u64, aux_data: u64) -> (Circuit, [Scalar; 4]) {
+    let [h, p, x] = [prev_height, prev_state, aux_data].map(Scalar::from);
+    let m = p * x;
+    let next = m + h;
+    let (zero, one) = (Scalar::zero(), Scalar::one());
+    let pos = |wire, row| Position { wire, row };
+    let circuit = Circuit {
+        selectors: [
+            vec![zero, zero, zero, zero, one, zero, zero, zero],
+            vec![one, one, one, one, zero, one, zero, zero],
+            vec![zero, zero, zero, zero, zero, one, zero, zero],
+            vec![zero, zero, zero, zero, -one, -one, zero, zero],
+            vec![zero; 8],
+        ],
+        wires: [
+            vec![h, p, x, next, p, m, zero, zero],
+            vec![zero, zero, zero, zero, x, h, zero, zero],
+            vec![zero, zero, zero, zero, m, next, zero, zero],
+        ],
+        copies: vec![
+            vec![pos(0, 0), pos(1, 5)],
+            vec![pos(0, 1), pos(0, 4)],
+            vec![pos(0, 2), pos(1, 4)],
+            vec![pos(0, 3), pos(2, 5)],
+            vec![pos(2, 4), pos(0, 5)],
+        ],
+    };
+    (circuit, [h, p, x, next])
+}
+
+fn compressed(v: Scalar) -> ZkCompressedState {
+    ZkCompressedState {
+        state_hash: v.into(),
+        state_size: 0,
+    }
+}
+
+#[test]
+fn test_plonk_proof() {
+    let (circuit, [h, p, x, next]) = update_circuit(5, 3, 4);
+    let setup = setup(&circuit, Scalar::from(123456789u64));
+    let proof = prove(&setup, &circuit, &[h, p, x, next]);
+    let vk = ZkVerifierKey::Plonk(Box::new(setup.vk.clone()));
+    let zk_proof = ZkProof::Plonk(Box::new(proof.clone()));
+
+    assert!(plonk_verify(
+        &setup.vk,
+        5,
+        p.into(),
+        x.into(),
+        next.into(),
+        &proof
+    ));
+    assert!(check_proof(
+        &vk,
+        5,
+        &compressed(p),
+        &compressed(x),
+        &compressed(next),
+        &zk_proof
+    ));
+
+    // Public inputs are bound to the proof
+    assert!(!check_proof(
+        &vk,
+        6,
+        &compressed(p),
+        &compressed(x),
+        &compressed(next),
+        &zk_proof
+    ));
+    assert!(!check_proof(
+        &vk,
+        5,
+        &compressed(p),
+        &compressed(x),
+        &compressed(next + Scalar::one()),
+        &zk_proof
+    ));
+    assert!(!check_proof(
+        &vk,
+        5,
+        &compressed(p),
+        &compressed(x),
+        &compressed(next),
+        &ZkProof::Dummy(true)
+    ));
+
+    let bytes = bincode::serialize(&zk_proof).unwrap();
+    assert_eq!(bincode::deserialize::<ZkProof>(&bytes).unwrap(), zk_proof);
+}
+
+#[test]
+fn test_plonk_tampered_proof() {
+    let (circuit, public_inputs) = update_circuit(1, 2, 3);
+    let setup = setup(&circuit, Scalar::from(987654321u64));
+    let proof = prove(&setup, &circuit, &public_inputs);
+    let [_, p, x, next] = public_inputs;
+    let verify =
+        |proof: &PlonkProof| plonk_verify(&setup.vk, 1, p.into(), x.into(), next.into(), proof);
+    assert!(verify(&proof));
+
+    let mut tampered = proof.clone();
+    tampered.z_omega_eval += ZkScalar::from(1);
+    assert!(!verify(&tampered));
+
+    let mut tampered = proof.clone();
+    tampered.t_hi = tampered.t_lo.clone();
+    assert!(!verify(&tampered));
+
+    let mut tampered = proof.clone();
+    tampered.w_zeta = vec![0u8; 48];
+    assert!(!verify(&tampered));
+
+    let mut tampered = proof.clone();
+    tampered.a.pop();
+    assert!(!verify(&tampered));
+
+    // Wire values that don't satisfy the circuit
+    let (mut bad, _) = update_circuit(1, 2, 3);
+    bad.wires[2][5] += Scalar::one();
+    bad.wires[0][3] += Scalar::one();
+    let bad_proof = std::panic::catch_unwind(|| prove(&setup, &bad, &public_inputs));
+    assert!(bad_proof.is_err());
+
+    // Proofs for another setup
+    let other = self::setup(&circuit, Scalar::from(42));
+    assert!(!plonk_verify(
+        &other.vk,
+        1,
+        p.into(),
+        x.into(),
+        next.into(),
+        &proof
+    ));
+}
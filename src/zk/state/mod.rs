@@ -3,7 +3,9 @@ use thiserror::Error;
 use super::*;
 use crate::core::ContractId;
 use crate::crypto::jubjub;
-use crate::db::{keys, KvStore, KvStoreError, RamKvStore, WriteOp};
+use crate::db::{
+    keys, DbKey, KvStore, KvStoreError, RamKvStore, RamMirrorKvStore, ScanQuery, WriteOp,
+};
 use ff::Field;
 use std::collections::HashMap;
 use std::str::FromStr;
@@ -22,8 +24,12 @@ pub enum StateManagerError {
     LocatorParseError(#[from] ParseZkDataLocatorError),
     #[error("not locating a tree")]
     NonTreeLocatorError,
+    #[error("accounts of the contract are not indexed")]
+    MpnAccountsNotIndexed,
     #[error("zk error: {0}")]
     ZkError(#[from] ZkError),
+    #[error("state at height {0} is not available")]
+    HeightNotAvailable(u64),
 }
 
 #[derive(Clone)]
@@ -79,6 +85,122 @@ impl<H: ZkHasher> ZkStateBuilder<H> {
     ) -> Result<Vec<[ZkScalar; 3]>, StateManagerError> {
         KvStoreStateManager::<H>::prove(&self.db, self.contract_id, tree_loc, ind)
     }
+
+    pub fn prove_map(
+        &self,
+        map_loc: ZkDataLocator,
+        key: ZkScalar,
+    ) -> Result<Vec<[ZkScalar; 3]>, StateManagerError> {
+        KvStoreStateManager::<H>::prove_map(&self.db, self.contract_id, map_loc, key)
+    }
+}
+
+// Path of an item of a `Map` in its tree, i.e. the big-endian bits of the
+// hash of its key, two bits per layer
+/// Keys of the counters of used accounts covering the account at `index`,
+/// from its leaf up to the root of the tree over the accounts
+pub fn mpn_used_keys(contract_id: &ContractId, log4_size: u8, index: u32) -> Vec<DbKey> {
+    (0..=log4_size)
+        .map(|level| keys::local_mpn_used(contract_id, level, (index as u64 >> (2 * level)) as u32))
+        .collect()
+}
+
+fn map_path<H: ZkHasher>(key: ZkScalar) -> [u8; 32] {
+    let mut path = [0u8; 32];
+    path.copy_from_slice(H::hash(&[key]).to_repr().as_ref());
+    path.reverse();
+    path
+}
+
+// Child taken by the path at the given depth
+fn map_digit(path: &[u8; 32], depth: usize) -> u8 {
+    (path[depth / 4] >> (6 - 2 * (depth % 4))) & 3
+}
+
+// Path of the child `digit` of the node of the path at the given depth
+fn map_child(path: &[u8; 32], depth: usize, digit: u8) -> [u8; 32] {
+    let shift = 6 - 2 * (depth % 4);
+    let mut node = [0u8; 32];
+    node[..depth / 4].copy_from_slice(&path[..depth / 4]);
+    node[depth / 4] = (path[depth / 4] & !((1u16 << (shift + 2)) - 1) as u8) | (digit << shift);
+    node
+}
+
+// Hash of the children of a node, given the child on the path and its
+// position among them, taking the other children from the proof
+fn hash_with_siblings<H: ZkHasher>(
+    value: ZkScalar,
+    index: usize,
+    arity: usize,
+    siblings: &mut impl Iterator<Item = ZkScalar>,
+) -> Option<ZkScalar> {
+    let mut vals = Vec::with_capacity(arity);
+    for i in 0..arity {
+        vals.push(if i == index { value } else { siblings.next()? });
+    }
+    Some(H::hash(&vals))
+}
+
+/// Checks that the value is located at the locator, in a state of the given
+/// model whose root is `root`
+pub fn verify_state_proof<H: ZkHasher>(
+    state_model: &ZkStateModel,
+    locator: &ZkDataLocator,
+    value: ZkScalar,
+    proof: &ZkStateProof,
+    root: ZkScalar,
+) -> bool {
+    let mut ancestors = match state_model.ancestors(locator) {
+        Ok((ancestors, _)) => ancestors,
+        Err(_) => {
+            return false;
+        }
+    };
+    let mut siblings = proof.0.iter().cloned();
+    let mut value = value;
+    let mut curr_len = locator.0.len();
+    while let Some((parent_len, parent_type)) = ancestors.pop() {
+        // Parts of the locator leading from the parent to the current node
+        let curr_loc = &locator.0[parent_len..curr_len];
+        curr_len = parent_len;
+        let hashed = match parent_type {
+            ZkStateModel::Struct { field_types } => hash_with_siblings::<H>(
+                value,
+                curr_loc[0] as usize,
+                field_types.len(),
+                &mut siblings,
+            ),
+            ZkStateModel::List { log4_size, .. } => {
+                let mut curr_ind = curr_loc[0];
+                let mut hashed = Some(value);
+                for _ in 0..log4_size {
+                    hashed = hashed.and_then(|v| {
+                        hash_with_siblings::<H>(v, (curr_ind % 4) as usize, 4, &mut siblings)
+                    });
+                    curr_ind /= 4;
+                }
+                hashed
+            }
+            ZkStateModel::Map { .. } => map_key(curr_loc).and_then(|key| {
+                let path = map_path::<H>(key);
+                let mut hashed = Some(value);
+                for depth in (0..MAP_LOG4_SIZE).rev() {
+                    let digit = map_digit(&path, depth) as usize;
+                    hashed =
+                        hashed.and_then(|v| hash_with_siblings::<H>(v, digit, 4, &mut siblings));
+                }
+                hashed
+            }),
+            ZkStateModel::Scalar => None,
+        };
+        value = match hashed {
+            Some(hashed) => hashed,
+            None => {
+                return false;
+            }
+        };
+    }
+    siblings.next().is_none() && value == root
 }
 
 impl<H: ZkHasher> KvStoreStateManager<H> {
@@ -103,13 +225,21 @@ impl<H: ZkHasher> KvStoreStateManager<H> {
         page: usize,
         page_size: usize,
     ) -> Result<Vec<(u32, MpnAccount)>, StateManagerError> {
+        // Locators are big-endian encoded, so the values of an account are
+        // adjacent and accounts are ordered by index.
         let mut indices = Vec::new();
-        for (k, _) in db.pairs(keys::local_scalar_value_prefix(&mpn_contract_id).into())? {
-            let loc = ZkDataLocator::from_str(k.0.split('-').nth(3).unwrap())?;
-            indices.push(loc.0[0]);
+        for item in db.scan(ScanQuery::prefix(keys::local_scalar_value_prefix(
+            &mpn_contract_id,
+        )))? {
+            let (k, _) = item?;
+            let loc = keys::local_value_locator(&k).ok_or(ParseZkDataLocatorError::Invalid)?;
+            if indices.last() != loc.0.first() {
+                indices.push(loc.0[0]);
+            }
+            if indices.len() > page_size * (page + 1) {
+                break;
+            }
         }
-        indices.sort_unstable();
-        indices.dedup();
         let mut accs = Vec::new();
         for ind in indices.into_iter().skip(page_size * page).take(page_size) {
             accs.push((
@@ -148,14 +278,205 @@ impl<H: ZkHasher> KvStoreStateManager<H> {
         Ok(())
     }
 
+    // Indices of the accounts having any non-zero field, in increasing order
+    fn mpn_account_indices<K: KvStore>(
+        db: &K,
+        mpn_contract_id: ContractId,
+    ) -> Result<Vec<u32>, StateManagerError> {
+        let mut indices = Vec::new();
+        for item in db.scan(ScanQuery::prefix(keys::local_scalar_value_prefix(
+            &mpn_contract_id,
+        )))? {
+            let (k, _) = item?;
+            let loc = keys::local_value_locator(&k).ok_or(ParseZkDataLocatorError::Invalid)?;
+            if indices.last() != loc.0.first() {
+                indices.push(loc.0[0]);
+            }
+        }
+        Ok(indices)
+    }
+
+    /// Indexes the accounts of an MPN contract by their public keys, and
+    /// counts the used ones for finding free accounts. The index is then kept
+    /// along with the state, through updates, resets and rollbacks. Does nothing when the accounts are already indexed, or when
+    /// the contract doesn't exist or its state is not a list of MPN accounts.
+    pub fn index_mpn_accounts<K: KvStore>(
+        db: &mut K,
+        mpn_contract_id: ContractId,
+    ) -> Result<(), StateManagerError> {
+        if db.get(keys::mpn_indexed(&mpn_contract_id))?.is_some() {
+            return Ok(());
+        }
+        let log4_size = match Self::type_of(db, mpn_contract_id) {
+            Ok(ZkStateModel::List {
+                item_type,
+                log4_size,
+            }) if *item_type == *MPN_ACCOUNT_STATE_MODEL => log4_size,
+            Ok(_) | Err(StateManagerError::ContractNotFound) => {
+                return Ok(());
+            }
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        let mut ops = vec![WriteOp::Put(
+            keys::mpn_indexed(&mpn_contract_id),
+            0u32.into(),
+        )];
+        let mut used = HashMap::<DbKey, u64>::new();
+        for ind in Self::mpn_account_indices(db, mpn_contract_id)? {
+            for k in mpn_used_keys(&mpn_contract_id, log4_size, ind) {
+                *used.entry(k).or_default() += 1;
+            }
+            let acc = Self::get_mpn_account(db, mpn_contract_id, ind)?;
+            if acc.address != Default::default() {
+                ops.push(WriteOp::Put(
+                    keys::local_mpn_index(&mpn_contract_id, &acc.address.compress(), ind),
+                    ind.into(),
+                ));
+            }
+        }
+        ops.extend(used.into_iter().map(|(k, n)| WriteOp::Put(k, n.into())));
+        db.update(&ops)?;
+        Ok(())
+    }
+
+    // Changes of the index of an indexed MPN contract, when one of the fields
+    // of an account is set
+    fn mpn_index_ops<K: KvStore>(
+        db: &K,
+        id: ContractId,
+        contract_type: &ZkStateModel,
+        locator: &ZkDataLocator,
+        value: ZkScalar,
+    ) -> Result<Vec<WriteOp>, StateManagerError> {
+        let (log4_size, index, field) = match (contract_type, &locator.0[..]) {
+            (ZkStateModel::List { log4_size, .. }, &[index, field @ 0..=3]) => {
+                (*log4_size, index, field as usize)
+            }
+            _ => {
+                return Ok(vec![]);
+            }
+        };
+        if db.get(keys::mpn_indexed(&id))?.is_none() {
+            return Ok(vec![]);
+        }
+        let prev = (0..4)
+            .map(|i| Self::get_data(db, id, &ZkDataLocator(vec![index, i])))
+            .collect::<Result<Vec<ZkScalar>, StateManagerError>>()?;
+        let mut next = prev.clone();
+        next[field] = value;
+        let mut ops = Vec::new();
+
+        let (prev_addr, next_addr) = (
+            jubjub::PointAffine(prev[1], prev[2]),
+            jubjub::PointAffine(next[1], next[2]),
+        );
+        if prev_addr != next_addr {
+            if prev_addr != Default::default() {
+                ops.push(WriteOp::Remove(keys::local_mpn_index(
+                    &id,
+                    &prev_addr.compress(),
+                    index,
+                )));
+            }
+            if next_addr != Default::default() {
+                ops.push(WriteOp::Put(
+                    keys::local_mpn_index(&id, &next_addr.compress(), index),
+                    index.into(),
+                ));
+            }
+        }
+
+        let is_used = |cells: &[ZkScalar]| cells.iter().any(|c| !bool::from(c.is_zero()));
+        let (was_used, used) = (is_used(&prev), is_used(&next));
+        if was_used != used {
+            for k in mpn_used_keys(&id, log4_size, index) {
+                let count: u64 = match db.get(k.clone())? {
+                    Some(v) => v.try_into()?,
+                    None => 0,
+                };
+                let count = if used { count + 1 } else { count - 1 };
+                ops.push(if count == 0 {
+                    WriteOp::Remove(k)
+                } else {
+                    WriteOp::Put(k, count.into())
+                });
+            }
+        }
+        Ok(ops)
+    }
+
+    /// Index of the lowest account owned by the address
+    pub fn find_mpn_account<K: KvStore>(
+        db: &K,
+        mpn_contract_id: ContractId,
+        address: &jubjub::PublicKey,
+    ) -> Result<Option<u32>, StateManagerError> {
+        if db.get(keys::mpn_indexed(&mpn_contract_id))?.is_none() {
+            return Err(StateManagerError::MpnAccountsNotIndexed);
+        }
+        Ok(
+            match db
+                .scan(ScanQuery::prefix(keys::local_mpn_index_prefix(
+                    &mpn_contract_id,
+                    &address.0,
+                )))?
+                .next()
+                .transpose()?
+            {
+                Some((_, v)) => Some(v.try_into()?),
+                None => None,
+            },
+        )
+    }
+
+    /// Index of the lowest account with all fields zero, `None` when there
+    /// is no such account left. Found by descending the counters of used
+    /// accounts, from the root of the tree over the accounts.
+    pub fn free_mpn_account<K: KvStore>(
+        db: &K,
+        mpn_contract_id: ContractId,
+    ) -> Result<Option<u32>, StateManagerError> {
+        let log4_size = match Self::type_of(db, mpn_contract_id)? {
+            ZkStateModel::List { log4_size, .. } => log4_size,
+            _ => {
+                return Err(StateManagerError::NonTreeLocatorError);
+            }
+        };
+        if db.get(keys::mpn_indexed(&mpn_contract_id))?.is_none() {
+            return Err(StateManagerError::MpnAccountsNotIndexed);
+        }
+        let used = |level: u8, node: u32| -> Result<u64, StateManagerError> {
+            Ok(
+                match db.get(keys::local_mpn_used(&mpn_contract_id, level, node))? {
+                    Some(v) => v.try_into()?,
+                    None => 0,
+                },
+            )
+        };
+        if used(log4_size, 0)? >= 1u64 << (2 * log4_size) {
+            return Ok(None);
+        }
+        let mut node = 0u32;
+        for level in (0..log4_size).rev() {
+            node *= 4;
+            while used(level, node)? >= 1u64 << (2 * level) {
+                node += 1;
+            }
+        }
+        Ok(Some(node))
+    }
+
     pub fn delete_contract<K: KvStore>(
         db: &mut K,
         id: ContractId,
     ) -> Result<(), StateManagerError> {
-        let mut rems = Vec::new();
-        for (k, _) in db.pairs(keys::local_prefix(&id).into())? {
-            rems.push(WriteOp::Remove(k));
-        }
+        let mut rems = db
+            .scan(ScanQuery::prefix(keys::local_prefix(&id)))?
+            .map(|item| item.map(|(k, _)| WriteOp::Remove(k)))
+            .collect::<Result<Vec<_>, _>>()?;
+        rems.push(WriteOp::Remove(keys::mpn_indexed(&id)));
         db.update(&rems)?;
         Ok(())
     }
@@ -216,6 +537,89 @@ impl<H: ZkHasher> KvStoreStateManager<H> {
         }
     }
 
+    /// Proves the item of a `Map` with the given key, the proof consisting of
+    /// the siblings of its path, from the leaf up to the root
+    pub fn prove_map<K: KvStore>(
+        db: &K,
+        id: ContractId,
+        map_loc: ZkDataLocator,
+        key: ZkScalar,
+    ) -> Result<Vec<[ZkScalar; 3]>, StateManagerError> {
+        if let ZkStateModel::Map { item_type } = Self::type_of(db, id)?.locate(&map_loc)? {
+            let path = map_path::<H>(key);
+            let defaults = map_defaults::<H>(&item_type);
+            let mut proof = Vec::new();
+            for depth in (0..MAP_LOG4_SIZE).rev() {
+                let digit = map_digit(&path, depth);
+                let mut proof_part = [ZkScalar::default(); 3];
+                for (i, sibling) in (0..4).filter(|d| *d != digit).enumerate() {
+                    proof_part[i] = Self::get_map_node(
+                        db,
+                        id,
+                        &map_loc,
+                        depth + 1,
+                        &map_child(&path, depth, sibling),
+                        defaults[depth + 1],
+                    )?;
+                }
+                proof.push(proof_part);
+            }
+            Ok(proof)
+        } else {
+            Err(StateManagerError::NonTreeLocatorError)
+        }
+    }
+
+    /// Proves the data at the locator, which may be any element of the state
+    pub fn prove_data<K: KvStore>(
+        db: &K,
+        id: ContractId,
+        locator: &ZkDataLocator,
+    ) -> Result<ZkStateProof, StateManagerError> {
+        let (mut ancestors, _) = Self::type_of(db, id)?.ancestors(locator)?;
+        let mut locator = locator.clone();
+        let mut proof = Vec::new();
+        while let Some((parent_len, parent_type)) = ancestors.pop() {
+            let curr_loc = locator.0.split_off(parent_len);
+            match parent_type {
+                ZkStateModel::Struct { field_types } => {
+                    for field_index in 0..field_types.len() as u32 {
+                        if field_index != curr_loc[0] {
+                            proof.push(Self::get_data(db, id, &locator.index(field_index))?);
+                        }
+                    }
+                }
+                ZkStateModel::List { .. } => {
+                    proof.extend(Self::prove(db, id, locator.clone(), curr_loc[0])?.concat());
+                }
+                ZkStateModel::Map { .. } => {
+                    let key = map_key(&curr_loc).unwrap();
+                    proof.extend(Self::prove_map(db, id, locator.clone(), key)?.concat());
+                }
+                ZkStateModel::Scalar => {
+                    panic!()
+                }
+            }
+        }
+        Ok(ZkStateProof(proof))
+    }
+
+    fn get_map_node<K: KvStore>(
+        db: &K,
+        id: ContractId,
+        map_loc: &ZkDataLocator,
+        depth: usize,
+        node: &[u8; 32],
+        default: ZkScalar,
+    ) -> Result<ZkScalar, StateManagerError> {
+        Ok(
+            match db.get(keys::local_map_aux(&id, map_loc, depth as u8, node))? {
+                Some(b) => b.try_into()?,
+                None => default,
+            },
+        )
+    }
+
     pub fn type_of<K: KvStore>(db: &K, id: ContractId) -> Result<ZkStateModel, StateManagerError> {
         let cont: ZkContract = db
             .get(keys::contract(&id))?
@@ -292,14 +696,78 @@ impl<H: ZkHasher> KvStoreStateManager<H> {
         )
     }
 
+    // The store, with the contract rolled back to the given height
+    fn at_height<K: KvStore>(
+        db: &K,
+        id: ContractId,
+        height: u64,
+    ) -> Result<RamMirrorKvStore<'_, K>, StateManagerError> {
+        let mut fork = db.mirror();
+        if height > Self::height_of(db, id)? {
+            return Err(StateManagerError::HeightNotAvailable(height));
+        }
+        while Self::height_of(&fork, id)? > height {
+            if Self::rollback_contract(&mut fork, id)?.is_none() {
+                return Err(StateManagerError::HeightNotAvailable(height));
+            }
+        }
+        Ok(fork)
+    }
+
+    /// Reads the data of the contract as it was at an earlier height, as long
+    /// as the rollback deltas down to that height are retained
+    pub fn get_data_at<K: KvStore>(
+        db: &K,
+        id: ContractId,
+        locator: &ZkDataLocator,
+        height: u64,
+    ) -> Result<ZkScalar, StateManagerError> {
+        Self::get_data(&Self::at_height(db, id, height)?, id, locator)
+    }
+
+    /// A page of the non-zero values of the contract at an earlier height,
+    /// ordered by locator
+    pub fn get_data_page_at<K: KvStore>(
+        db: &K,
+        id: ContractId,
+        height: u64,
+        page: usize,
+        page_size: usize,
+    ) -> Result<Vec<(ZkDataLocator, ZkScalar)>, StateManagerError> {
+        let fork = Self::at_height(db, id, height)?;
+        let mut values = Vec::new();
+        for item in fork
+            .scan(
+                ScanQuery::prefix(keys::local_scalar_value_prefix(&id))
+                    .limit(page_size.saturating_mul(page.saturating_add(1))),
+            )?
+            .skip(page_size.saturating_mul(page))
+        {
+            let (k, v) = item?;
+            let loc = keys::local_value_locator(&k).ok_or(ParseZkDataLocatorError::Invalid)?;
+            values.push((loc, v.try_into()?));
+        }
+        Ok(values)
+    }
+
+    /// Like `get_full_state`, at an earlier height
+    pub fn get_full_state_at<K: KvStore>(
+        db: &K,
+        id: ContractId,
+        height: u64,
+    ) -> Result<ZkState, StateManagerError> {
+        Self::get_full_state(&Self::at_height(db, id, height)?, id)
+    }
+
     pub fn get_full_state<K: KvStore>(
         db: &K,
         id: ContractId,
     ) -> Result<ZkState, StateManagerError> {
         const MAX_ROLLBACKS: u64 = 5;
         let mut data = ZkDataPairs(Default::default());
-        for (k, v) in db.pairs(keys::local_scalar_value_prefix(&id).into())? {
-            let loc = ZkDataLocator::from_str(k.0.split('-').nth(3).unwrap())?;
+        for item in db.scan(ScanQuery::prefix(keys::local_scalar_value_prefix(&id)))? {
+            let (k, v) = item?;
+            let loc = keys::local_value_locator(&k).ok_or(ParseZkDataLocatorError::Invalid)?;
             data.0.insert(loc, v.try_into()?);
         }
         let mut rollbacks = Vec::<ZkDeltaPairs>::new();
@@ -328,9 +796,11 @@ impl<H: ZkHasher> KvStoreStateManager<H> {
         state: &ZkState,
     ) -> Result<(ZkCompressedState, Vec<ZkCompressedState>), StateManagerError> {
         let contract_type = Self::type_of(db, id)?;
-        for (k, _) in db.pairs(keys::local_prefix(&id).into())? {
-            db.update(&[WriteOp::Remove(k)])?;
-        }
+        let rems = db
+            .scan(ScanQuery::prefix(keys::local_prefix(&id)))?
+            .map(|item| item.map(|(k, _)| WriteOp::Remove(k)))
+            .collect::<Result<Vec<_>, _>>()?;
+        db.update(&rems)?;
 
         let mut state_hash = contract_type.compress_default::<H>();
         let mut state_size = 0;
@@ -353,22 +823,27 @@ impl<H: ZkHasher> KvStoreStateManager<H> {
         let mut rollback_results = Vec::new();
         let mut root = Self::root(db, id)?;
 
+        // Rollbacks are applied on a mirror, only for calculating the states
+        // they lead to
+        let mut fork = db.mirror();
+        let mut rollback_ops = Vec::new();
         for (i, rollback) in state.rollbacks.iter().enumerate() {
             for (k, v) in &rollback.0 {
                 root.state_hash = Self::set_data(
-                    db,
+                    &mut fork,
                     id,
                     k.clone(),
                     v.unwrap_or_default(),
                     &mut root.state_size,
                 )?;
             }
-            db.update(&[WriteOp::Put(
+            rollback_ops.push(WriteOp::Put(
                 keys::local_rollback_to_height(&id, height - 1 - i as u64),
                 rollback.into(),
-            )])?;
+            ));
             rollback_results.push(root);
         }
+        db.update(&rollback_ops)?;
 
         Ok((root, rollback_results))
     }
@@ -421,11 +896,19 @@ impl<H: ZkHasher> KvStoreStateManager<H> {
         let contract_type = Self::type_of(db, id)?;
         let mut ops = Vec::new();
 
-        if contract_type.locate(&locator)? != ZkStateModel::Scalar {
+        let (mut ancestors, located) = contract_type.ancestors(&locator)?;
+        if located != ZkStateModel::Scalar {
             return Err(StateManagerError::NonScalarLocatorError);
         }
 
         let prev_is_zero: bool = Self::get_data(db, id, &locator)?.is_zero().into();
+        ops.extend(Self::mpn_index_ops(
+            db,
+            id,
+            &contract_type,
+            &locator,
+            value,
+        )?);
 
         ops.push(if value.is_zero().into() {
             if !prev_is_zero {
@@ -439,14 +922,14 @@ impl<H: ZkHasher> KvStoreStateManager<H> {
             WriteOp::Put(keys::local_value(&id, &locator, true), value.into())
         });
 
-        while let Some(curr_loc) = locator.0.pop() {
-            let curr_type = contract_type.locate(&locator)?;
+        while let Some((parent_len, curr_type)) = ancestors.pop() {
+            let curr_loc = locator.0.split_off(parent_len);
             match curr_type.clone() {
                 ZkStateModel::List {
                     item_type,
                     log4_size,
                 } => {
-                    let leaf_index = curr_loc;
+                    let leaf_index = curr_loc[0];
                     let mut curr_ind = leaf_index;
                     let mut default_value = item_type.compress_default::<H>();
                     for layer in (0..log4_size).rev() {
@@ -492,7 +975,7 @@ impl<H: ZkHasher> KvStoreStateManager<H> {
                 ZkStateModel::Struct { field_types } => {
                     let mut dats = Vec::new();
                     for field_index in 0..field_types.len() {
-                        dats.push(if field_index as u32 == curr_loc {
+                        dats.push(if field_index as u32 == curr_loc[0] {
                             value
                         } else {
                             let mut full_loc = locator.clone();
@@ -502,6 +985,40 @@ impl<H: ZkHasher> KvStoreStateManager<H> {
                     }
                     value = H::hash(&dats);
                 }
+                ZkStateModel::Map { item_type } => {
+                    let path = map_path::<H>(map_key(&curr_loc).unwrap());
+                    let defaults = map_defaults::<H>(&item_type);
+                    for depth in (0..MAP_LOG4_SIZE).rev() {
+                        let digit = map_digit(&path, depth);
+                        let aux_key = keys::local_map_aux(
+                            &id,
+                            &locator,
+                            depth as u8 + 1,
+                            &map_child(&path, depth, digit),
+                        );
+                        ops.push(if value == defaults[depth + 1] {
+                            WriteOp::Remove(aux_key)
+                        } else {
+                            WriteOp::Put(aux_key, value.into())
+                        });
+                        let mut dats = Vec::new();
+                        for sibling in 0..4 {
+                            dats.push(if sibling == digit {
+                                value
+                            } else {
+                                Self::get_map_node(
+                                    db,
+                                    id,
+                                    &locator,
+                                    depth + 1,
+                                    &map_child(&path, depth, sibling),
+                                    defaults[depth + 1],
+                                )?
+                            });
+                        }
+                        value = H::hash(&dats);
+                    }
+                }
                 ZkStateModel::Scalar => {
                     panic!()
                 }
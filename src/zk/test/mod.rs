@@ -1,6 +1,6 @@
 use super::*;
 use crate::core::ContractId;
-use crate::db::{KvStore, RamKvStore, WriteOp};
+use crate::db::{keys, KvStore, RamKvStore, WriteOp};
 use std::ops::*;
 use std::str::FromStr;
 
@@ -77,7 +77,7 @@ fn test_state_manager_scalar() -> Result<(), StateManagerError> {
             .unwrap();
 
     db.update(&[WriteOp::Put(
-        format!("CON-{}", c0).into(),
+        keys::contract(&c0),
         empty_contract::<SumHasher>(ZkStateModel::Scalar).into(),
     )])?;
 
@@ -103,7 +103,7 @@ fn test_state_manager_struct() -> Result<(), StateManagerError> {
             .unwrap();
 
     db.update(&[WriteOp::Put(
-        format!("CON-{}", c0).into(),
+        keys::contract(&c0),
         empty_contract::<SumHasher>(ZkStateModel::Struct {
             field_types: vec![ZkStateModel::Scalar, ZkStateModel::Scalar],
         })
@@ -177,7 +177,7 @@ fn test_state_manager_list() -> Result<(), StateManagerError> {
     let mut roots = Vec::new();
 
     db.update(&[WriteOp::Put(
-        format!("CON-{}", c0).into(),
+        keys::contract(&c0),
         empty_contract::<PoseidonHasher>(ZkStateModel::List {
             log4_size: 3,
             item_type: Box::new(ZkStateModel::Struct {
@@ -281,3 +281,658 @@ fn test_state_manager_list() -> Result<(), StateManagerError> {
 
     Ok(())
 }
+
+#[test]
+fn test_state_manager_map() -> Result<(), StateManagerError> {
+    type Manager = KvStoreStateManager<PoseidonHasher>;
+    let mut db = RamKvStore::new();
+
+    let c0 =
+        ContractId::from_str("0000000000000000000000000000000000000000000000000000000000000000")
+            .unwrap();
+    let item_type = ZkStateModel::Struct {
+        field_types: vec![ZkStateModel::Scalar, ZkStateModel::Scalar],
+    };
+    let model = ZkStateModel::Struct {
+        field_types: vec![
+            ZkStateModel::Scalar,
+            ZkStateModel::Map {
+                item_type: Box::new(item_type.clone()),
+            },
+        ],
+    };
+    assert!(model.is_valid::<PoseidonHasher>());
+    db.update(&[WriteOp::Put(
+        keys::contract(&c0),
+        empty_contract::<PoseidonHasher>(model.clone()).into(),
+    )])?;
+
+    let map = ZkDataLocator(vec![1]);
+    let keys = [ZkScalar::from(123), -ZkScalar::from(1)];
+    let mut roots = vec![Manager::root(&db, c0)?];
+    let deltas = [
+        vec![(map.key(keys[0]).index(0), ZkScalar::from(10))],
+        vec![
+            (map.key(keys[1]).index(1), ZkScalar::from(20)),
+            (ZkDataLocator(vec![0]), ZkScalar::from(30)),
+        ],
+        vec![(map.key(keys[0]).index(1), ZkScalar::from(40))],
+        vec![(map.key(keys[0]).index(0), ZkScalar::from(0))],
+    ];
+    for delta in deltas.iter() {
+        Manager::update_contract(
+            &mut db,
+            c0,
+            &ZkDeltaPairs(delta.iter().map(|(k, v)| (k.clone(), Some(*v))).collect()),
+        )?;
+        roots.push(Manager::root(&db, c0)?);
+    }
+    assert_eq!(
+        Manager::get_data(&db, c0, &map.key(keys[0]).index(1))?,
+        ZkScalar::from(40)
+    );
+    assert_eq!(
+        Manager::get_data(&db, c0, &map.key(keys[1]).index(1))?,
+        ZkScalar::from(20)
+    );
+    assert_eq!(
+        Manager::get_data(&db, c0, &map.key(ZkScalar::from(7)))?,
+        item_type.compress_default::<PoseidonHasher>()
+    );
+    assert_eq!(roots.last().unwrap().state_size, 3);
+
+    // The same state built from scratch
+    let state = Manager::get_full_state(&db, c0)?;
+    assert_eq!(model.compress::<PoseidonHasher>(&state.data)?, roots[4]);
+
+    // Membership and non-membership proofs
+    let map_root = Manager::get_data(&db, c0, &map)?;
+    for key in [keys[0], keys[1], ZkScalar::from(7)] {
+        let mut path = PoseidonHasher::hash(&[key]).to_repr();
+        path.as_mut().reverse();
+        let proof = Manager::prove_map(&db, c0, map.clone(), key)?;
+        assert_eq!(proof.len(), MAP_LOG4_SIZE);
+        let mut value = Manager::get_data(&db, c0, &map.key(key))?;
+        for (depth, siblings) in (0..MAP_LOG4_SIZE).rev().zip(proof) {
+            let digit = (path.as_ref()[depth / 4] >> (6 - 2 * (depth % 4))) & 3;
+            let mut vals = siblings.to_vec();
+            vals.insert(digit as usize, value);
+            value = PoseidonHasher::hash(&vals);
+        }
+        assert_eq!(value, map_root);
+    }
+    assert!(matches!(
+        Manager::prove_map(&db, c0, ZkDataLocator(vec![0]), keys[0]),
+        Err(StateManagerError::NonTreeLocatorError)
+    ));
+
+    // Keys must be complete and canonical
+    for loc in [
+        ZkDataLocator(vec![1, 0, 0, 0, 0, 0, 0, 0]),
+        ZkDataLocator(vec![1, u32::MAX, 0, 0, 0, 0, 0, 0, 0, 0]),
+    ] {
+        assert!(matches!(
+            Manager::get_data(&db, c0, &loc),
+            Err(StateManagerError::LocatorError(_))
+        ));
+    }
+
+    while Manager::height_of(&db, c0)? > 0 {
+        roots.pop();
+        assert_eq!(
+            roots.last().cloned(),
+            Manager::rollback_contract(&mut db, c0)?
+        );
+    }
+    assert_eq!(
+        Manager::root(&db, c0)?,
+        ZkCompressedState::empty::<PoseidonHasher>(model)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_state_proof() -> Result<(), StateManagerError> {
+    type Manager = KvStoreStateManager<PoseidonHasher>;
+    let mut db = RamKvStore::new();
+
+    let c0 =
+        ContractId::from_str("0000000000000000000000000000000000000000000000000000000000000000")
+            .unwrap();
+    let model = ZkStateModel::Struct {
+        field_types: vec![
+            ZkStateModel::Scalar,
+            ZkStateModel::List {
+                item_type: Box::new(ZkStateModel::Struct {
+                    field_types: vec![ZkStateModel::Scalar, ZkStateModel::Scalar],
+                }),
+                log4_size: 3,
+            },
+            ZkStateModel::Map {
+                item_type: Box::new(ZkStateModel::Scalar),
+            },
+            ZkStateModel::Map {
+                item_type: Box::new(ZkStateModel::Struct {
+                    field_types: vec![ZkStateModel::Scalar, ZkStateModel::Scalar],
+                }),
+            },
+            ZkStateModel::Map {
+                item_type: Box::new(ZkStateModel::List {
+                    item_type: Box::new(ZkStateModel::Scalar),
+                    log4_size: 2,
+                }),
+            },
+        ],
+    };
+    db.update(&[WriteOp::Put(
+        keys::contract(&c0),
+        empty_contract::<PoseidonHasher>(model.clone()).into(),
+    )])?;
+    let list = ZkDataLocator(vec![1]);
+    let map = ZkDataLocator(vec![2]);
+    let struct_map = ZkDataLocator(vec![3]);
+    let list_map = ZkDataLocator(vec![4]);
+    Manager::update_contract(
+        &mut db,
+        c0,
+        &ZkDeltaPairs(
+            [
+                (ZkDataLocator(vec![0]), ZkScalar::from(10)),
+                (list.index(5).index(1), ZkScalar::from(20)),
+                (list.index(62).index(0), ZkScalar::from(30)),
+                (map.key(ZkScalar::from(123)), ZkScalar::from(40)),
+                (
+                    struct_map.key(ZkScalar::from(5)).index(1),
+                    ZkScalar::from(50),
+                ),
+                (list_map.key(ZkScalar::from(6)).index(9), ZkScalar::from(60)),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k, Some(v)))
+            .collect(),
+        ),
+    )?;
+    let root = Manager::root(&db, c0)?.state_hash;
+    assert_eq!(Manager::get_data(&db, c0, &ZkDataLocator(vec![]))?, root);
+
+    let verify = |loc: &ZkDataLocator, value: ZkScalar, proof: &ZkStateProof| {
+        verify_state_proof::<PoseidonHasher>(&model, loc, value, proof, root)
+    };
+    for loc in [
+        ZkDataLocator(vec![]),
+        ZkDataLocator(vec![0]),
+        list.clone(),
+        list.index(5),
+        list.index(5).index(1),
+        list.index(62).index(0),
+        list.index(7).index(0),
+        map.clone(),
+        map.key(ZkScalar::from(123)),
+        map.key(ZkScalar::from(7)),
+        struct_map.key(ZkScalar::from(5)),
+        struct_map.key(ZkScalar::from(5)).index(0),
+        struct_map.key(ZkScalar::from(5)).index(1),
+        struct_map.key(ZkScalar::from(8)).index(1),
+        list_map.key(ZkScalar::from(6)),
+        list_map.key(ZkScalar::from(6)).index(9),
+        list_map.key(ZkScalar::from(6)).index(10),
+        list_map.key(ZkScalar::from(8)).index(9),
+    ] {
+        let value = Manager::get_data(&db, c0, &loc)?;
+        let proof = Manager::prove_data(&db, c0, &loc)?;
+        assert!(verify(&loc, value, &proof));
+        assert!(!verify(&loc, value + ZkScalar::from(1), &proof));
+        if !proof.0.is_empty() {
+            let mut truncated = proof.clone();
+            truncated.0.pop();
+            assert!(!verify(&loc, value, &truncated));
+        }
+        let mut extended = proof.clone();
+        extended.0.push(ZkScalar::from(0));
+        assert!(!verify(&loc, value, &extended));
+    }
+
+    // The proof of a value doesn't hold for other locations
+    let loc = list.index(5).index(1);
+    let proof = Manager::prove_data(&db, c0, &loc)?;
+    assert!(!verify(&list.index(5).index(0), ZkScalar::from(20), &proof));
+    assert!(!verify(&list.index(6).index(1), ZkScalar::from(20), &proof));
+    assert!(!verify(
+        &list.index(64).index(1),
+        ZkScalar::from(20),
+        &proof
+    ));
+    assert!(!verify(&ZkDataLocator(vec![5]), ZkScalar::from(20), &proof));
+
+    // Nor for other keys or items of a map
+    let loc = struct_map.key(ZkScalar::from(5)).index(1);
+    let proof = Manager::prove_data(&db, c0, &loc)?;
+    assert!(verify(&loc, ZkScalar::from(50), &proof));
+    assert!(!verify(
+        &struct_map.key(ZkScalar::from(6)).index(1),
+        ZkScalar::from(50),
+        &proof
+    ));
+    assert!(!verify(
+        &struct_map.key(ZkScalar::from(5)).index(0),
+        ZkScalar::from(50),
+        &proof
+    ));
+    let loc = list_map.key(ZkScalar::from(6)).index(9);
+    let proof = Manager::prove_data(&db, c0, &loc)?;
+    assert!(!verify(
+        &list_map.key(ZkScalar::from(6)).index(8),
+        ZkScalar::from(60),
+        &proof
+    ));
+
+    assert!(matches!(
+        Manager::prove_data(&db, c0, &ZkDataLocator(vec![0, 1])),
+        Err(StateManagerError::LocatorError(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_mpn_account_index() -> Result<(), StateManagerError> {
+    use crate::crypto::{jubjub, ZkSignatureScheme};
+    type Manager = KvStoreStateManager<PoseidonHasher>;
+    let mut db = RamKvStore::new();
+
+    let c0 =
+        ContractId::from_str("0000000000000000000000000000000000000000000000000000000000000000")
+            .unwrap();
+    let model = ZkStateModel::List {
+        item_type: Box::new(MPN_ACCOUNT_STATE_MODEL.clone()),
+        log4_size: 1,
+    };
+    db.update(&[WriteOp::Put(
+        keys::contract(&c0),
+        empty_contract::<PoseidonHasher>(model).into(),
+    )])?;
+    let pks =
+        [b"A", b"B", b"C"].map(|seed| jubjub::JubJub::<PoseidonHasher>::generate_keys(seed).0);
+    let account = |i: usize, balance: u64| MpnAccount {
+        nonce: 0,
+        address: pks[i].0.decompress(),
+        balance: balance.into(),
+    };
+    let find = |db: &RamKvStore, i: usize| Manager::find_mpn_account(db, c0, &pks[i]);
+
+    // Accounts existing before the contract is indexed
+    let mut size = 0;
+    Manager::set_mpn_account(&mut db, c0, 1, account(0, 10), &mut size)?;
+    assert!(matches!(
+        find(&db, 0),
+        Err(StateManagerError::MpnAccountsNotIndexed)
+    ));
+    Manager::index_mpn_accounts(&mut db, c0)?;
+    assert_eq!(find(&db, 0)?, Some(1));
+    assert_eq!(find(&db, 1)?, None);
+    assert_eq!(Manager::free_mpn_account(&db, c0)?, Some(0));
+
+    let set_accounts = |db: &mut RamKvStore, accs: &[(u32, MpnAccount)]| {
+        let mut delta = ZkDeltaPairs(Default::default());
+        for (i, acc) in accs {
+            delta.0.extend([
+                (ZkDataLocator(vec![*i, 0]), Some(acc.nonce.into())),
+                (ZkDataLocator(vec![*i, 1]), Some(acc.address.0)),
+                (ZkDataLocator(vec![*i, 2]), Some(acc.address.1)),
+                (ZkDataLocator(vec![*i, 3]), Some(acc.balance.into())),
+            ]);
+        }
+        Manager::update_contract(db, c0, &delta)
+    };
+    set_accounts(&mut db, &[(0, account(1, 20)), (3, account(0, 30))])?;
+    assert_eq!(find(&db, 0)?, Some(1));
+    assert_eq!(find(&db, 1)?, Some(0));
+    assert_eq!(Manager::free_mpn_account(&db, c0)?, Some(2));
+    set_accounts(
+        &mut db,
+        &[
+            (1, MpnAccount::default()),
+            (2, account(2, 40)),
+            (0, account(0, 50)),
+        ],
+    )?;
+    assert_eq!(find(&db, 0)?, Some(0));
+    assert_eq!(find(&db, 1)?, None);
+    assert_eq!(find(&db, 2)?, Some(2));
+    assert_eq!(Manager::free_mpn_account(&db, c0)?, Some(1));
+
+    // The index is rolled back with the state
+    Manager::rollback_contract(&mut db, c0)?;
+    assert_eq!(find(&db, 0)?, Some(1));
+    assert_eq!(find(&db, 1)?, Some(0));
+    assert_eq!(find(&db, 2)?, None);
+    set_accounts(&mut db, &[(1, account(2, 60)), (2, account(2, 70))])?;
+    assert_eq!(Manager::free_mpn_account(&db, c0)?, None);
+    assert_eq!(find(&db, 0)?, Some(3));
+    assert_eq!(find(&db, 2)?, Some(1));
+
+    // And rebuilt when the state is reset
+    let state = Manager::get_full_state(&db, c0)?;
+    let mut other = RamKvStore::new();
+    other.update(&[WriteOp::Put(
+        keys::contract(&c0),
+        db.get(keys::contract(&c0))?.unwrap(),
+    )])?;
+    Manager::index_mpn_accounts(&mut other, c0)?;
+    Manager::reset_contract(&mut other, c0, Manager::height_of(&db, c0)?, &state)?;
+    for (i, pk) in pks.iter().enumerate() {
+        let mut owned = Vec::new();
+        for ind in 0..4 {
+            if Manager::get_mpn_account(&other, c0, ind)?.address == pk.0.decompress() {
+                owned.push(ind);
+            }
+        }
+        assert_eq!(find(&other, i)?, owned.first().cloned());
+    }
+    Ok(())
+}
+
+fn mpn_accounts_db(
+    id: ContractId,
+    log4_size: u8,
+    accounts: &[(u32, MpnAccount)],
+    indexed: bool,
+) -> Result<RamKvStore, StateManagerError> {
+    type Manager = KvStoreStateManager<PoseidonHasher>;
+    let mut db = RamKvStore::new();
+    db.update(&[WriteOp::Put(
+        keys::contract(&id),
+        empty_contract::<PoseidonHasher>(ZkStateModel::List {
+            item_type: Box::new(MPN_ACCOUNT_STATE_MODEL.clone()),
+            log4_size,
+        })
+        .into(),
+    )])?;
+    if indexed {
+        Manager::index_mpn_accounts(&mut db, id)?;
+    }
+    let mut size = 0;
+    for (i, acc) in accounts {
+        Manager::set_mpn_account(&mut db, id, *i, acc.clone(), &mut size)?;
+    }
+    Ok(db)
+}
+
+#[test]
+fn test_free_mpn_account() -> Result<(), StateManagerError> {
+    use crate::crypto::{jubjub, ZkSignatureScheme};
+    type Manager = KvStoreStateManager<PoseidonHasher>;
+    let c0 =
+        ContractId::from_str("0000000000000000000000000000000000000000000000000000000000000000")
+            .unwrap();
+    let pk = jubjub::JubJub::<PoseidonHasher>::generate_keys(b"A").0;
+    let mut db = mpn_accounts_db(c0, 2, &[], true)?;
+    let lowest_free = |db: &RamKvStore| -> Result<Option<u32>, StateManagerError> {
+        for i in 0..16 {
+            if Manager::get_mpn_account(db, c0, i)? == MpnAccount::default() {
+                return Ok(Some(i));
+            }
+        }
+        Ok(None)
+    };
+
+    let mut size = 0;
+    // Any non-zero field makes an account used
+    let steps: Vec<(u32, MpnAccount)> = vec![
+        (
+            0,
+            MpnAccount {
+                nonce: 1,
+                ..Default::default()
+            },
+        ),
+        (
+            1,
+            MpnAccount {
+                address: pk.0.decompress(),
+                ..Default::default()
+            },
+        ),
+        (
+            2,
+            MpnAccount {
+                balance: 5.into(),
+                ..Default::default()
+            },
+        ),
+        (
+            3,
+            MpnAccount {
+                nonce: 2,
+                ..Default::default()
+            },
+        ),
+        (
+            5,
+            MpnAccount {
+                nonce: 3,
+                ..Default::default()
+            },
+        ),
+        (
+            4,
+            MpnAccount {
+                nonce: 4,
+                ..Default::default()
+            },
+        ),
+        (1, MpnAccount::default()),
+        (
+            1,
+            MpnAccount {
+                nonce: 5,
+                ..Default::default()
+            },
+        ),
+    ];
+    for (i, acc) in steps {
+        Manager::set_mpn_account(&mut db, c0, i, acc, &mut size)?;
+        assert_eq!(Manager::free_mpn_account(&db, c0)?, lowest_free(&db)?);
+    }
+    for i in 6..16 {
+        Manager::set_mpn_account(
+            &mut db,
+            c0,
+            i,
+            MpnAccount {
+                nonce: 1,
+                ..Default::default()
+            },
+            &mut size,
+        )?;
+        assert_eq!(Manager::free_mpn_account(&db, c0)?, lowest_free(&db)?);
+    }
+    assert_eq!(Manager::free_mpn_account(&db, c0)?, None);
+    Manager::set_mpn_account(&mut db, c0, 9, MpnAccount::default(), &mut size)?;
+    assert_eq!(Manager::free_mpn_account(&db, c0)?, Some(9));
+    Ok(())
+}
+
+#[test]
+fn test_mpn_accounts_index_migration() -> Result<(), StateManagerError> {
+    use crate::crypto::{jubjub, ZkSignatureScheme};
+    use crate::db::migrations;
+    let c0 =
+        ContractId::from_str("0000000000000000000000000000000000000000000000000000000000000000")
+            .unwrap();
+    let pks =
+        [b"A", b"B", b"C"].map(|seed| jubjub::JubJub::<PoseidonHasher>::generate_keys(seed).0);
+    let accounts = [
+        (0, pks[0].0.decompress(), 10),
+        (1, pks[1].0.decompress(), 0),
+        (2, Default::default(), 20),
+        (5, pks[0].0.decompress(), 30),
+        (6, pks[2].0.decompress(), 40),
+        (13, pks[1].0.decompress(), 50),
+    ]
+    .map(|(i, address, balance)| {
+        (
+            i,
+            MpnAccount {
+                nonce: 1,
+                address,
+                balance: balance.into(),
+            },
+        )
+    });
+
+    let mut expected = mpn_accounts_db(c0, 2, &accounts, true)?;
+    let mut legacy = mpn_accounts_db(c0, 2, &accounts, false)?;
+    // A contract that is not an MPN contract is left as is
+    let c1 =
+        ContractId::from_str("1111111111111111111111111111111111111111111111111111111111111111")
+            .unwrap();
+    let other = WriteOp::Put(
+        keys::contract(&c1),
+        empty_contract::<PoseidonHasher>(ZkStateModel::Scalar).into(),
+    );
+    expected.update(&[
+        other.clone(),
+        WriteOp::Put(keys::schema_version(), migrations::SCHEMA_VERSION.into()),
+    ])?;
+    legacy.update(&[other, WriteOp::Put(keys::schema_version(), 2u32.into())])?;
+
+    // Small batches, so that the step is resumed several times
+    assert_eq!(migrations::migrate_in_batches(&mut legacy, 5)?.len(), 1);
+    assert_eq!(legacy.pairs("".into())?, expected.pairs("".into())?);
+    Ok(())
+}
+
+#[test]
+fn test_groth16_batch_verify() {
+    let prover = groth16::TestProver::new(1);
+    let key = prover.verifying_key().prepare();
+    let statement = |height: u64| {
+        let inputs = [2 * height, 3 * height, 5 * height].map(ZkScalar::from);
+        groth16::Groth16Statement::new(
+            height,
+            inputs[0],
+            inputs[1],
+            inputs[2],
+            &prover.prove(height, inputs[0], inputs[1], inputs[2]),
+        )
+    };
+    let mut statements = (0..5).map(statement).collect::<Vec<_>>();
+    assert!(statements.iter().all(|s| key.verify(s)));
+    assert!(key.batch_verify(&statements));
+    assert!(key.batch_verify(&[]));
+
+    // Proof of a different statement
+    statements[3] = groth16::Groth16Statement::new(
+        3,
+        ZkScalar::from(6),
+        ZkScalar::from(9),
+        ZkScalar::from(16),
+        &prover.prove(3, ZkScalar::from(6), ZkScalar::from(9), ZkScalar::from(15)),
+    );
+    assert!(!key.verify(&statements[3]));
+    assert!(!key.batch_verify(&statements));
+    assert!(key.batch_verify(&statements[..3]));
+
+    // Proofs of another circuit
+    let other = groth16::TestProver::new(2).verifying_key();
+    assert!(!other.prepare().batch_verify(&statements[..3]));
+    assert!(groth16::groth16_verify(
+        &prover.verifying_key(),
+        1,
+        ZkScalar::from(2),
+        ZkScalar::from(3),
+        ZkScalar::from(5),
+        &prover.prove(1, ZkScalar::from(2), ZkScalar::from(3), ZkScalar::from(5)),
+    ));
+}
+
+#[test]
+fn test_groth16_encoding() {
+    let vk = &*crate::config::blockchain::MPN_UPDATE_VK;
+    let bytes = bincode::serialize(vk).unwrap();
+    let decoded = bincode::deserialize::<groth16::Groth16VerifyingKey>(&bytes).unwrap();
+    assert_eq!(decoded, *vk);
+    assert_eq!(bincode::serialize(&decoded).unwrap(), bytes);
+    assert_ne!(*vk, *crate::config::blockchain::MPN_PAYMENT_VK);
+
+    // Points are Montgomery-form limbs of their coordinates and a flag
+    let prover = groth16::TestProver::new(1);
+    let proof = prover.prove(1, ZkScalar::from(2), ZkScalar::from(3), ZkScalar::from(5));
+    let bytes = bincode::serialize(&proof).unwrap();
+    assert_eq!(bytes.len(), 97 + 193 + 97);
+    assert_eq!(
+        bincode::deserialize::<groth16::Groth16Proof>(&bytes).unwrap(),
+        proof
+    );
+    let json = serde_json::to_string(&prover.verifying_key()).unwrap();
+    assert_eq!(
+        serde_json::from_str::<groth16::Groth16VerifyingKey>(&json).unwrap(),
+        prover.verifying_key()
+    );
+
+    let legacy = bincode::serialize(&(
+        ([0u64; 6], [0u64; 6], true),
+        ([0u64; 6], [0u64; 6], true),
+        (([0u64; 6], [0u64; 6]), ([0u64; 6], [0u64; 6]), true),
+        (([0u64; 6], [0u64; 6]), ([0u64; 6], [0u64; 6]), true),
+        ([0u64; 6], [0u64; 6], true),
+        (([0u64; 6], [0u64; 6]), ([0u64; 6], [0u64; 6]), true),
+        vec![([0u64; 6], [0u64; 6], true)],
+    ))
+    .unwrap();
+    assert!(bincode::deserialize::<groth16::Groth16VerifyingKey>(&legacy).is_ok());
+
+    // Point off the curve
+    let mut off_curve = legacy.clone();
+    off_curve[0] = 1;
+    off_curve[96] = 0;
+    assert!(bincode::deserialize::<groth16::Groth16VerifyingKey>(&off_curve).is_err());
+
+    // Coordinate not reduced modulo the field characteristic
+    let mut unreduced = legacy;
+    unreduced[..48].copy_from_slice(&[0xff; 48]);
+    unreduced[96] = 0;
+    assert!(bincode::deserialize::<groth16::Groth16VerifyingKey>(&unreduced).is_err());
+    assert!(bincode::deserialize::<groth16::Groth16VerifyingKey>(&[1, 2, 3]).is_err());
+}
+
+#[test]
+fn test_groth16_compressed_encoding() {
+    let prover = groth16::TestProver::new(1);
+    let vk = prover.verifying_key();
+    let proof = prover.prove(1, ZkScalar::from(2), ZkScalar::from(3), ZkScalar::from(5));
+
+    let bytes = proof.to_compressed();
+    assert_eq!(bytes.len(), 48 + 96 + 48);
+    assert_eq!(
+        groth16::Groth16Proof::from_compressed(&bytes).unwrap(),
+        proof
+    );
+    assert_eq!(
+        groth16::Groth16VerifyingKey::from_compressed(&vk.to_compressed()).unwrap(),
+        vk
+    );
+
+    // Flipping the sort flag gives the negated point, flipping a bit of the
+    // x-coordinate most likely a point off the curve
+    let mut negated = bytes.clone();
+    negated[0] ^= 0x20;
+    assert_ne!(
+        groth16::Groth16Proof::from_compressed(&negated).unwrap(),
+        proof
+    );
+    let mut off_curve = bytes.clone();
+    off_curve[47] ^= 1;
+    assert!(matches!(
+        groth16::Groth16Proof::from_compressed(&off_curve),
+        Err(groth16::Groth16Error::InvalidPoint)
+    ));
+    let mut uncompressed = bytes.clone();
+    uncompressed[0] &= 0x7f;
+    assert!(groth16::Groth16Proof::from_compressed(&uncompressed).is_err());
+    assert!(groth16::Groth16Proof::from_compressed(&bytes[..100]).is_err());
+    let mut trailing = bytes;
+    trailing.push(0);
+    assert!(groth16::Groth16Proof::from_compressed(&trailing).is_err());
+}